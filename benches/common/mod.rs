@@ -0,0 +1,29 @@
+//! Helpers shared by the benchmarks, and by the tests through `#[path]`
+
+#![allow(dead_code)]
+
+/// xorshift64*, seeded so that every run draws the same numbers
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new() -> Self {
+        Self::with_seed(0x9e37_79b9_7f4a_7c15)
+    }
+
+    /// a zero state would only ever draw zeros
+    pub fn with_seed(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// uniform in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
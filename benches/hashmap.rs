@@ -0,0 +1,96 @@
+//! Throughput and probe distances of the `HashMap` collision strategies
+//!
+//! Run with `cargo bench --bench hashmap`, `BENCH_N` sets the number of keys.
+
+mod common;
+
+use common::Rng;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use tsinghua_ds::{Chained, Cuckoo, HashMap, Quadratic, RobinHood, Table};
+
+/// nanoseconds per key of a phase over `n` keys
+fn per_key(elapsed: Duration, n: usize) -> f64 {
+    elapsed.as_nanos() as f64 / n as f64
+}
+
+/// insert `keys`, look every one up, look up `missing`, then remove them all
+fn run<K, T>(strategy: &str, keys: &[K], missing: &[K])
+where
+    K: Eq + Hash + Clone + Debug,
+    T: Table<K, usize>,
+{
+    let mut map = HashMap::with_table(T::default());
+
+    let start = Instant::now();
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+    let insert = start.elapsed();
+    let stats = map.stats();
+
+    let start = Instant::now();
+    for key in keys {
+        black_box(map.get(key));
+    }
+    let hit = start.elapsed();
+
+    let start = Instant::now();
+    for key in missing {
+        black_box(map.get(key));
+    }
+    let miss = start.elapsed();
+
+    let start = Instant::now();
+    for key in keys {
+        black_box(map.remove(key));
+    }
+    let remove = start.elapsed();
+    assert!(map.is_empty());
+
+    println!(
+        "{:<12} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.3} {:>10} {:>10.3}",
+        strategy,
+        per_key(insert, keys.len()),
+        per_key(hit, keys.len()),
+        per_key(miss, missing.len()),
+        per_key(remove, keys.len()),
+        stats.average_probe_distance(),
+        stats.max_probe_distance(),
+        stats.load_factor(),
+    );
+}
+
+fn run_all<K: Eq + Hash + Clone + Debug>(title: &str, keys: &[K], missing: &[K]) {
+    println!("\n{} ({} keys), ns per key", title, keys.len());
+    println!(
+        "{:<12} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "strategy", "insert", "hit", "miss", "remove", "avg probe", "max probe", "load"
+    );
+    run::<K, Quadratic<K, usize>>("quadratic", keys, missing);
+    run::<K, Chained<K, usize>>("chained", keys, missing);
+    run::<K, RobinHood<K, usize>>("robin hood", keys, missing);
+    run::<K, Cuckoo<K, usize>>("cuckoo", keys, missing);
+}
+
+fn main() {
+    let n = std::env::var("BENCH_N")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(200_000);
+    let mut rng = Rng::new();
+
+    let sequential: Vec<u64> = (0..n as u64).collect();
+    let sequential_missing: Vec<u64> = (n as u64..2 * n as u64).collect();
+    run_all("sequential u64", &sequential, &sequential_missing);
+
+    let random: Vec<u64> = (0..n).map(|_| rng.next()).collect();
+    let random_missing: Vec<u64> = (0..n).map(|_| rng.next()).collect();
+    run_all("random u64", &random, &random_missing);
+
+    let words: Vec<String> = (0..n).map(|i| format!("key-{:x}", i * 7919)).collect();
+    let words_missing: Vec<String> = (0..n).map(|i| format!("nokey-{}", i)).collect();
+    run_all("strings", &words, &words_missing);
+}
@@ -0,0 +1,77 @@
+//! Push, pop and heap sort throughput of d-ary heaps against the binary heap
+//!
+//! Run with `cargo bench --bench heap`, `BENCH_N` sets the number of items.
+
+mod common;
+
+use common::Rng;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use tsinghua_ds::{dary_heap_sort, heap_sort, BinaryHeap, DaryHeap};
+
+/// nanoseconds per item of a phase over `n` items
+fn per_item(elapsed: Duration, n: usize) -> f64 {
+    elapsed.as_nanos() as f64 / n as f64
+}
+
+fn time(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn report(name: &str, items: &[u64], push: Duration, pop: Duration, sort: Duration) {
+    println!(
+        "{:<12} {:>10.1} {:>10.1} {:>10.1}",
+        name,
+        per_item(push, items.len()),
+        per_item(pop, items.len()),
+        per_item(sort, items.len()),
+    );
+}
+
+/// push every item, pop them all, then heap sort a copy
+fn run_dary<const D: usize>(items: &[u64]) {
+    let mut heap = DaryHeap::<u64, D>::with_capacity(items.len());
+    let push = time(|| items.iter().for_each(|&item| heap.push(item)));
+    let pop = time(|| while black_box(heap.pop()).is_some() {});
+    let mut array = items.to_vec();
+    let sort = time(|| dary_heap_sort::<D, _>(&mut array, 0, items.len()));
+    assert!(array.windows(2).all(|w| w[0] <= w[1]));
+    report(&format!("d-ary {}", D), items, push, pop, sort);
+}
+
+fn run_binary(items: &[u64]) {
+    let mut heap = BinaryHeap::with_capacity(items.len());
+    let push = time(|| items.iter().for_each(|&item| heap.push(item)));
+    let pop = time(|| while black_box(heap.pop()).is_some() {});
+    let mut array = items.to_vec();
+    let sort = time(|| heap_sort(&mut array, 0, items.len()));
+    report("BinaryHeap", items, push, pop, sort);
+}
+
+fn run_all(title: &str, items: &[u64]) {
+    println!("\n{} ({} items), ns per item", title, items.len());
+    println!(
+        "{:<12} {:>10} {:>10} {:>10}",
+        "heap", "push", "pop", "heap sort"
+    );
+    run_binary(items);
+    run_dary::<2>(items);
+    run_dary::<4>(items);
+    run_dary::<8>(items);
+}
+
+fn main() {
+    let n = std::env::var("BENCH_N")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(1_000_000);
+    let mut rng = Rng::new();
+
+    let random: Vec<u64> = (0..n).map(|_| rng.next()).collect();
+    run_all("random u64", &random);
+
+    let ascending: Vec<u64> = (0..n as u64).collect();
+    run_all("ascending u64", &ascending);
+}
@@ -0,0 +1,67 @@
+//! Serial sorts against their parallel versions, and the radix sort
+//!
+//! Run with `cargo bench --bench sort`, `BENCH_N` sets the number of items,
+//! `BENCH_THREADS` the threads of the parallel sorts, one per CPU by default.
+
+mod common;
+
+use common::Rng;
+use std::time::{Duration, Instant};
+use tsinghua_ds::{merge_sort, radix, sort, ParSort};
+
+/// sort a copy of `items` with `f`, report milliseconds and the speedup
+/// over `serial`
+fn run(
+    name: &str,
+    items: &[u64],
+    serial: Option<Duration>,
+    f: impl FnOnce(&mut [u64]),
+) -> Duration {
+    let mut array = items.to_vec();
+    let start = Instant::now();
+    f(&mut array);
+    let elapsed = start.elapsed();
+    assert!(array.windows(2).all(|w| w[0] <= w[1]));
+    match serial {
+        Some(serial) => println!(
+            "{:<16} {:>10.1} {:>9.2}x",
+            name,
+            elapsed.as_secs_f64() * 1e3,
+            serial.as_secs_f64() / elapsed.as_secs_f64()
+        ),
+        None => println!("{:<16} {:>10.1}", name, elapsed.as_secs_f64() * 1e3),
+    }
+    elapsed
+}
+
+fn run_all(title: &str, items: &[u64], config: ParSort) {
+    println!("\n{} ({} items)", title, items.len());
+    println!("{:<16} {:>10} {:>10}", "sort", "ms", "speedup");
+    let serial = run("sort", items, None, sort);
+    run("par_sort", items, Some(serial), |v| config.sort(v));
+    let serial = run("merge_sort", items, None, merge_sort);
+    run("par_merge_sort", items, Some(serial), |v| {
+        config.merge_sort(v)
+    });
+    run("radix::sort", items, None, radix::sort);
+    run("slice::sort", items, None, |v| v.sort());
+}
+
+fn main() {
+    let var = |name| std::env::var(name).ok().and_then(|n| n.parse().ok());
+    let n = var("BENCH_N").unwrap_or(4_000_000);
+    let mut config = ParSort::new();
+    if let Some(threads) = var("BENCH_THREADS") {
+        config = config.threads(threads);
+    }
+    let mut rng = Rng::new();
+
+    let random: Vec<u64> = (0..n).map(|_| rng.next()).collect();
+    run_all("random u64", &random, config);
+
+    let few: Vec<u64> = (0..n).map(|_| rng.next() % 16).collect();
+    run_all("16 distinct u64", &few, config);
+
+    let ascending: Vec<u64> = (0..n as u64).collect();
+    run_all("ascending u64", &ascending, config);
+}
@@ -1,170 +1,286 @@
 //! AVLTree
 
-use crate::{TreeNode, BST};
+use crate::invariant::{check_balance, check_order};
+use crate::{
+    InvariantError, Keys, TreeIntoIter, TreeIter, TreeIterMut, TreeNode, TreeRange, Values, BST,
+};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::mem::replace;
+use std::ops::RangeBounds;
 
-/// AVL Tree
+/// AVL Tree, with the number of rotations done so far
 #[derive(Debug, PartialEq, Eq)]
-pub struct AVLTree(Option<Box<TreeNode>>);
+pub struct AVLTree<K, V>(Option<Box<TreeNode<K, V>>>, usize);
 
-impl AVLTree {
+impl<K: Ord, V> AVLTree<K, V> {
     /// AVL tree
-    pub fn new(root_key: i32, root_val: i32) -> Self {
-        Self(Some(Box::new(TreeNode::new(root_key, root_val))))
+    pub fn new(root_key: K, root_val: V) -> Self {
+        Self(Some(Box::new(TreeNode::new(root_key, root_val))), 0)
     }
-}
 
-impl BST for AVLTree {
-    /// search val by key
-    fn search(&mut self, key: i32) -> Option<i32> {
+    /// Returns the number of rotations done by rebalancing so far
+    pub fn rotations(&self) -> usize {
+        self.1
+    }
+
+    /// Gets an iterator over the entries of the tree, sorted by key
+    pub fn iter(&self) -> TreeIter<'_, K, V> {
+        TreeIter::new(self.0.as_deref())
+    }
+
+    /// Gets a mutable iterator over the entries of the tree, sorted by key
+    pub fn iter_mut(&mut self) -> TreeIterMut<'_, K, V> {
+        TreeIterMut::new(self.0.as_deref_mut())
+    }
+
+    /// Gets an iterator over the keys of the tree, in sorted order
+    pub fn keys(&self) -> Keys<TreeIter<'_, K, V>> {
+        Keys(self.iter())
+    }
+
+    /// Gets an iterator over the values of the tree, in order by key
+    pub fn values(&self) -> Values<TreeIter<'_, K, V>> {
+        Values(self.iter())
+    }
+
+    /// Constructs a double-ended iterator over a sub-range of entries in the tree
+    pub fn range<T, R>(&self, range: R) -> TreeRange<'_, K, V>
+    where
+        K: Borrow<T>,
+        T: Ord + ?Sized,
+        R: RangeBounds<T>,
+    {
+        TreeRange::new(self.0.as_deref(), range)
+    }
+
+    /// Returns the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, |root| root.size)
+    }
+
+    /// Returns `true` if the tree contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Checks key ordering, stored heights and sizes, and that every balance
+    /// factor is in [-1, 1]
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        check_order(self.0.as_deref())?;
+        check_balance(&self.0, 0).map(|_| ())
+    }
+
+    /// Returns the `k`-th smallest entry, counting from 0, in *O*(log(*n*))
+    pub fn select(&self, mut k: usize) -> Option<(&K, &V)> {
         let mut ptr = self.0.as_ref();
         while let Some(node) = ptr {
-            let node_key = node.get_key();
-            if key > node_key {
-                ptr = node.right.as_ref();
-            } else if key < node_key {
-                ptr = node.left.as_ref();
-            } else if key == node_key {
-                return Some(node.get_val());
+            let left_size = node.left_size();
+            match k.cmp(&left_size) {
+                Ordering::Less => ptr = node.left.as_ref(),
+                Ordering::Equal => return Some((&node.key, &node.val)),
+                Ordering::Greater => {
+                    k -= left_size + 1;
+                    ptr = node.right.as_ref();
+                }
             }
         }
         None
     }
 
-    /// method rotated
-    fn insert(&mut self, key: i32, val: i32) {
-        let mut ptr = self.0.as_mut();
-        let mut prev_ptrs = Vec::<*mut TreeNode>::new();
+    /// Returns how many keys are smaller than `key`, in *O*(log(*n*))
+    pub fn rank(&self, key: &K) -> usize {
+        let mut rank = 0;
+        let mut ptr = self.0.as_ref();
         while let Some(node) = ptr {
-            let node_key = node.get_key();
-            if key > node_key {
-                prev_ptrs.push(&mut **node);
-                if node.right.is_none() {
-                    node.right = Some(Box::new(TreeNode::new(key, val)));
-                    break;
-                }
-                ptr = node.right.as_mut();
-            } else if key < node_key {
-                prev_ptrs.push(&mut **node);
-                if node.left.is_none() {
-                    node.left = Some(Box::new(TreeNode::new(key, val)));
-                    break;
+            match key.cmp(&node.key) {
+                Ordering::Less => ptr = node.left.as_ref(),
+                Ordering::Equal => return rank + node.left_size(),
+                Ordering::Greater => {
+                    rank += node.left_size() + 1;
+                    ptr = node.right.as_ref();
                 }
-                ptr = node.left.as_mut();
-            } else {
-                node.set_val(val);
-                break;
             }
         }
+        rank
+    }
 
-        while let Some(node_ptr) = prev_ptrs.pop() {
-            let node = unsafe { &mut *node_ptr };
-            node.update_height();
-            node.rebalance();
+    /// Returns how many keys lie in the half-open range `[lo, hi)`, in *O*(log(*n*))
+    pub fn count_range(&self, lo: &K, hi: &K) -> usize {
+        if lo >= hi {
+            return 0;
         }
+        self.rank(hi) - self.rank(lo)
     }
+}
 
-    /// remove val
-    fn remove(&mut self, key: i32) -> Option<i32> {
-        let mut ptr = self.0.as_mut();
-        let mut prev_ptrs = Vec::<*mut TreeNode>::new();
-        let mut target_val = None;
-
+impl<K: Ord, V> BST<K, V> for AVLTree<K, V> {
+    /// search val by key
+    fn search(&mut self, key: &K) -> Option<&V> {
+        let mut ptr = self.0.as_ref();
         while let Some(node) = ptr {
-            let node_key = node.get_key();
-            if key > node_key {
-                prev_ptrs.push(&mut **node);
-                ptr = node.right.as_mut();
-            } else if key < node_key {
-                prev_ptrs.push(&mut **node);
-                ptr = node.left.as_mut();
-            } else {
-                target_val = Some(&mut **node);
-                break;
+            match key.cmp(node.get_key()) {
+                Ordering::Greater => ptr = node.right.as_ref(),
+                Ordering::Less => ptr = node.left.as_ref(),
+                Ordering::Equal => return Some(node.get_val()),
             }
         }
+        None
+    }
 
-        match target_val {
-            None => None,
-            Some(node) => {
-                let mut _inner_val = 0;
-                // two children
-                if node.left.is_some() && node.right.is_some() {
-                    // if right.left.is_none(), min right child is right node, so
-                    // take right
-                    if node.right.as_ref().unwrap().left.is_none() {
-                        let mut right_node = node.right.take().unwrap();
-                        let inner_val = replace(&mut node.val, right_node.val);
-                        let _ = replace(&mut node.key, right_node.key);
-                        let _ = replace(&mut node.right, right_node.right.take());
-
-                        node.update_height();
-                        node.rebalance();
-
-                        while let Some(node_ptr) = prev_ptrs.pop() {
-                            let node = unsafe { &mut *node_ptr };
-                            node.update_height();
-                            node.rebalance();
-                        }
-                        return Some(inner_val);
-                    }
-
-                    let mut rightmin = node.right.as_mut();
-                    let mut inner_ptrs = Vec::<*mut TreeNode>::new();
-                    while let Some(next_node) = rightmin {
-                        if next_node.left.is_some() {
-                            inner_ptrs.push(&mut **next_node);
-                        }
-
-                        rightmin = next_node.left.as_mut();
-                    }
-
-                    let parent_left_node = unsafe { &mut *inner_ptrs.pop().unwrap() };
-                    let mut leftmost_node = parent_left_node.left.take().unwrap();
-
-                    // replace key, value
-                    _inner_val = replace(&mut node.val, leftmost_node.val);
-                    let _ = replace(&mut node.key, leftmost_node.key);
-                    let _ = replace(&mut parent_left_node.left, leftmost_node.right.take());
-                    while let Some(node_ptr) = inner_ptrs.pop() {
-                        let node = unsafe { &mut *node_ptr };
-                        node.update_height();
-                        node.rebalance();
-                    }
-
-                // one or zero children
-                } else if node.left.is_none() && node.right.is_some() {
-                    let right_node = node.right.take().unwrap();
-                    _inner_val = replace(node, *right_node).val;
-                } else if node.right.is_none() && node.left.is_some() {
-                    let left_node = node.left.take().unwrap();
-                    _inner_val = replace(node, *left_node).val;
-                } else if let Some(prev_ptr) = prev_ptrs.pop() {
-                    let prev_node = unsafe { &mut *prev_ptr };
-                    _inner_val = if let Some(left_node) = prev_node.left.as_ref() {
-                        if left_node.val == node.val {
-                            prev_node.left.take().unwrap().val
-                        } else {
-                            prev_node.right.take().unwrap().val
-                        }
-                    } else {
-                        prev_node.right.take().unwrap().val
-                    };
-
-                    prev_node.update_height();
-                    prev_node.rebalance();
-                } else {
-                    _inner_val = self.0.take().unwrap().val;
-                }
-                while let Some(node_ptr) = prev_ptrs.pop() {
-                    let node = unsafe { &mut *node_ptr };
-                    node.update_height();
-                    node.rebalance();
-                }
+    /// method rotated
+    fn insert(&mut self, key: K, val: V) {
+        insert_node(&mut self.0, key, val, &mut self.1);
+    }
 
-                Some(_inner_val)
-            }
+    /// remove val
+    fn remove(&mut self, key: &K) -> Option<V> {
+        remove_node(&mut self.0, key, &mut self.1)
+    }
+    /// key-val with the minimum key
+    fn first(&mut self) -> Option<(&K, &V)> {
+        self.0.as_ref().map(|root| root.first().entry())
+    }
+    /// key-val with the maximum key
+    fn last(&mut self) -> Option<(&K, &V)> {
+        self.0.as_ref().map(|root| root.last().entry())
+    }
+    /// key-val with the greatest key less than or equal to `key`
+    fn floor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.floor(key).map(TreeNode::entry)
+    }
+    /// key-val with the least key greater than or equal to `key`
+    fn ceiling(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.ceiling(key).map(TreeNode::entry)
+    }
+    /// key-val with the greatest key strictly less than `key`
+    fn predecessor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.predecessor(key).map(TreeNode::entry)
+    }
+    /// key-val with the least key strictly greater than `key`
+    fn successor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.successor(key).map(TreeNode::entry)
+    }
+    /// remove the leftmost node
+    fn pop_first(&mut self) -> Option<(K, V)> {
+        pop_min(&mut self.0, &mut self.1).map(|node| node.into_entry())
+    }
+    /// remove the rightmost node
+    fn pop_last(&mut self) -> Option<(K, V)> {
+        pop_max(&mut self.0, &mut self.1).map(|node| node.into_entry())
+    }
+}
+
+/// insert under `link`, rebalancing on the way back up
+fn insert_node<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    key: K,
+    val: V,
+    rotations: &mut usize,
+) -> Option<V> {
+    let node = match link.as_mut() {
+        Some(node) => node,
+        None => {
+            *link = Some(Box::new(TreeNode::new(key, val)));
+            return None;
         }
+    };
+    let old_val = match key.cmp(node.get_key()) {
+        Ordering::Greater => insert_node(&mut node.right, key, val, rotations),
+        Ordering::Less => insert_node(&mut node.left, key, val, rotations),
+        Ordering::Equal => return Some(node.set_val(val)),
+    };
+    node.update_height();
+    *rotations += node.rebalance();
+    old_val
+}
+
+/// remove `key` under `link`, rebalancing on the way back up
+fn remove_node<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    key: &K,
+    rotations: &mut usize,
+) -> Option<V> {
+    let node = link.as_mut()?;
+    let inner_val = match key.cmp(node.get_key()) {
+        Ordering::Greater => remove_node(&mut node.right, key, rotations)?,
+        Ordering::Less => remove_node(&mut node.left, key, rotations)?,
+        // two children, replace key, value with the min of right subtree
+        Ordering::Equal if node.left.is_some() && node.right.is_some() => {
+            let (min_key, min_val) = pop_min(&mut node.right, rotations)?.into_entry();
+            node.key = min_key;
+            replace(&mut node.val, min_val)
+        }
+        // one or zero children
+        Ordering::Equal => {
+            let mut node = link.take()?;
+            *link = node.left.take().or_else(|| node.right.take());
+            return Some(node.val);
+        }
+    };
+    node.update_height();
+    *rotations += node.rebalance();
+    Some(inner_val)
+}
+
+/// detach the leftmost node of the subtree, rebalancing on the way back up
+fn pop_min<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    rotations: &mut usize,
+) -> Option<Box<TreeNode<K, V>>> {
+    let node = link.as_mut()?;
+    if node.left.is_some() {
+        let min = pop_min(&mut node.left, rotations);
+        node.update_height();
+        *rotations += node.rebalance();
+        return min;
+    }
+    let mut min = link.take()?;
+    *link = min.right.take();
+    Some(min)
+}
+
+/// detach the rightmost node of the subtree, rebalancing on the way back up
+fn pop_max<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    rotations: &mut usize,
+) -> Option<Box<TreeNode<K, V>>> {
+    let node = link.as_mut()?;
+    if node.right.is_some() {
+        let max = pop_max(&mut node.right, rotations);
+        node.update_height();
+        *rotations += node.rebalance();
+        return max;
+    }
+    let mut max = link.take()?;
+    *link = max.left.take();
+    Some(max)
+}
+
+impl<K, V> IntoIterator for AVLTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = TreeIntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TreeIntoIter::new(self.0)
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a AVLTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = TreeIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut AVLTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = TreeIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
@@ -192,36 +308,36 @@ mod tests {
         bst.insert(22, 28494);
         bst.insert(23, 28499);
 
-        assert_eq!(bst.search(9), Some(512));
-        assert_eq!(bst.search(19), Some(2849));
+        assert_eq!(bst.search(&9), Some(&512));
+        assert_eq!(bst.search(&19), Some(&2849));
         bst.insert(19, -12849);
-        assert_eq!(bst.search(19), Some(-12849));
-        assert_eq!(bst.search(13), Some(2344));
-        assert_eq!(bst.search(4), Some(16));
-        assert_eq!(bst.search(-1), None);
-        assert_eq!(bst.search(0), None);
-        assert_eq!(bst.search(23), Some(28499));
-        assert_eq!(bst.remove(2), Some(4));
-        assert_eq!(bst.search(2), None);
-        assert_eq!(bst.remove(2), None);
-        assert_eq!(bst.remove(11), Some(234));
-        assert_eq!(bst.remove(11), None);
-        assert_eq!(bst.search(11), None);
-        assert_eq!(bst.remove(20), Some(12993));
-        assert_eq!(bst.remove(20), None);
-        assert_eq!(bst.search(20), None);
-        assert_eq!(bst.remove(8), Some(256));
-        assert_eq!(bst.remove(8), None);
-        assert_eq!(bst.search(8), None);
+        assert_eq!(bst.search(&19), Some(&-12849));
+        assert_eq!(bst.search(&13), Some(&2344));
+        assert_eq!(bst.search(&4), Some(&16));
+        assert_eq!(bst.search(&-1), None);
+        assert_eq!(bst.search(&0), None);
+        assert_eq!(bst.search(&23), Some(&28499));
+        assert_eq!(bst.remove(&2), Some(4));
+        assert_eq!(bst.search(&2), None);
+        assert_eq!(bst.remove(&2), None);
+        assert_eq!(bst.remove(&11), Some(234));
+        assert_eq!(bst.remove(&11), None);
+        assert_eq!(bst.search(&11), None);
+        assert_eq!(bst.remove(&20), Some(12993));
+        assert_eq!(bst.remove(&20), None);
+        assert_eq!(bst.search(&20), None);
+        assert_eq!(bst.remove(&8), Some(256));
+        assert_eq!(bst.remove(&8), None);
+        assert_eq!(bst.search(&8), None);
         bst.insert(17, 49);
         bst.insert(14, 149);
         bst.insert(16, 169);
-        assert_eq!(bst.remove(15), Some(12345));
-        assert_eq!(bst.remove(15), None);
-        assert_eq!(bst.search(15), None);
-        assert_eq!(bst.remove(3), Some(8));
+        assert_eq!(bst.remove(&15), Some(12345));
+        assert_eq!(bst.remove(&15), None);
+        assert_eq!(bst.search(&15), None);
+        assert_eq!(bst.remove(&3), Some(8));
         bst.insert(15, 12345);
-        //println!("{:#?}", bst);
+        assert_eq!(bst.check_invariants(), Ok(()));
         bst = AVLTree::new(10, 1024);
         bst.insert(2, 8);
         bst.insert(3, 8);
@@ -240,6 +356,84 @@ mod tests {
         bst.insert(23, 28499);
         bst.insert(24, 28499);
         bst.insert(25, 28499);
-        //println!("{:#?}", bst);
+        assert_eq!(bst.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn order_statistics() {
+        let keys = [10, 4, 8, 5, 9, 3, 2, 15, 13, 11, 18, 20, 19, 12, 21, 22, 23];
+        let mut bst = AVLTree::new(10, 0);
+        for &k in keys.iter() {
+            bst.insert(k, k * 2);
+        }
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        sorted.dedup();
+
+        assert_eq!(bst.len(), sorted.len());
+        for (i, k) in sorted.iter().enumerate() {
+            assert_eq!(bst.select(i), Some((k, &(k * 2))));
+            assert_eq!(bst.rank(k), i);
+        }
+        assert_eq!(bst.select(sorted.len()), None);
+        assert_eq!(bst.rank(&0), 0);
+        assert_eq!(bst.rank(&14), 10);
+        assert_eq!(bst.rank(&100), sorted.len());
+        assert_eq!(bst.count_range(&4, &12), 6);
+        assert_eq!(bst.count_range(&6, &8), 0);
+        assert_eq!(bst.count_range(&12, &4), 0);
+        assert_eq!(bst.count_range(&0, &100), sorted.len());
+
+        for &k in [2, 11, 20, 8, 15, 3, 10].iter() {
+            bst.remove(&k);
+            assert_eq!(bst.check_invariants(), Ok(()));
+            sorted.retain(|&x| x != k);
+            assert_eq!(bst.len(), sorted.len());
+            for (i, k) in sorted.iter().enumerate() {
+                assert_eq!(bst.select(i).map(|(k, _)| *k), Some(*k));
+                assert_eq!(bst.rank(k), i);
+            }
+        }
+    }
+
+    #[test]
+    fn broken_invariants() {
+        let mut bst = AVLTree::new(1, 1);
+        bst.insert(0, 0);
+        bst.insert(2, 2);
+        assert_eq!(bst.check_invariants(), Ok(()));
+
+        bst.0.as_mut().unwrap().height = 3;
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::Height {
+                index: 1,
+                stored: 3,
+                actual: 2
+            })
+        );
+
+        // hang 2 under 0 by hand, heights kept right
+        let mut root = bst.0.take().unwrap();
+        let mut left = root.left.take().unwrap();
+        left.right = root.right.take();
+        left.update_height();
+        root.left = Some(left);
+        root.key = 3;
+        root.update_height();
+        bst.0 = Some(root);
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::Unbalanced {
+                index: 2,
+                factor: 2
+            })
+        );
+
+        bst.0.as_mut().unwrap().key = 1;
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::KeyOrder { index: 2 })
+        );
     }
 }
@@ -1,140 +1,190 @@
 //! BinarySearchTree
 
-use crate::{TreeNode, BST};
+use crate::invariant::check_order;
+use crate::{
+    InvariantError, Keys, TreeIntoIter, TreeIter, TreeIterMut, TreeNode, TreeRange, Values, BST,
+};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::mem::replace;
+use std::ops::RangeBounds;
 
 /// BinarySearch Tree
 #[derive(Debug, PartialEq, Eq)]
-pub struct BinarySearchTree(Option<Box<TreeNode>>);
+pub struct BinarySearchTree<K, V>(Option<Box<TreeNode<K, V>>>);
 
-impl BinarySearchTree {
+impl<K: Ord, V> BinarySearchTree<K, V> {
     /// BinarySearch tree
-    pub fn new(root_key: i32, root_val: i32) -> Self {
+    pub fn new(root_key: K, root_val: V) -> Self {
         Self(Some(Box::new(TreeNode::new(root_key, root_val))))
     }
+
+    /// Gets an iterator over the entries of the tree, sorted by key
+    pub fn iter(&self) -> TreeIter<'_, K, V> {
+        TreeIter::new(self.0.as_deref())
+    }
+
+    /// Gets a mutable iterator over the entries of the tree, sorted by key
+    pub fn iter_mut(&mut self) -> TreeIterMut<'_, K, V> {
+        TreeIterMut::new(self.0.as_deref_mut())
+    }
+
+    /// Gets an iterator over the keys of the tree, in sorted order
+    pub fn keys(&self) -> Keys<TreeIter<'_, K, V>> {
+        Keys(self.iter())
+    }
+
+    /// Gets an iterator over the values of the tree, in order by key
+    pub fn values(&self) -> Values<TreeIter<'_, K, V>> {
+        Values(self.iter())
+    }
+
+    /// Constructs a double-ended iterator over a sub-range of entries in the tree
+    pub fn range<T, R>(&self, range: R) -> TreeRange<'_, K, V>
+    where
+        K: Borrow<T>,
+        T: Ord + ?Sized,
+        R: RangeBounds<T>,
+    {
+        TreeRange::new(self.0.as_deref(), range)
+    }
+
+    /// Checks that keys strictly increase in order
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        check_order(self.0.as_deref())
+    }
 }
 
-impl BST for BinarySearchTree {
+impl<K: Ord, V> BST<K, V> for BinarySearchTree<K, V> {
     /// search val by key
-    fn search(&mut self, key: i32) -> Option<i32> {
+    fn search(&mut self, key: &K) -> Option<&V> {
         let mut ptr = self.0.as_ref();
         while let Some(node) = ptr {
-            let node_key = node.get_key();
-            if key > node_key {
-                ptr = node.right.as_ref();
-            } else if key < node_key {
-                ptr = node.left.as_ref();
-            } else if key == node_key {
-                return Some(node.get_val());
+            match key.cmp(node.get_key()) {
+                Ordering::Greater => ptr = node.right.as_ref(),
+                Ordering::Less => ptr = node.left.as_ref(),
+                Ordering::Equal => return Some(node.get_val()),
             }
         }
         None
     }
     /// 3-4 insert method rotated
-    fn insert(&mut self, key: i32, val: i32) {
-        let mut ptr = self.0.as_mut();
-        while let Some(node) = ptr {
-            let node_key = node.get_key();
-            if key > node_key {
-                if node.right.is_none() {
-                    node.right = Some(Box::new(TreeNode::new(key, val)));
-                    break;
-                }
-                ptr = node.right.as_mut();
-            } else if key < node_key {
-                if node.left.is_none() {
-                    node.left = Some(Box::new(TreeNode::new(key, val)));
-                    break;
+    fn insert(&mut self, key: K, val: V) {
+        let mut link = &mut self.0;
+        while let Some(node) = link {
+            link = match key.cmp(node.get_key()) {
+                Ordering::Greater => &mut node.right,
+                Ordering::Less => &mut node.left,
+                Ordering::Equal => {
+                    node.set_val(val);
+                    return;
                 }
-                ptr = node.left.as_mut();
-            } else {
-                node.set_val(val);
-                break;
-            }
+            };
         }
+        *link = Some(Box::new(TreeNode::new(key, val)));
     }
     /// remove val
-    fn remove(&mut self, key: i32) -> Option<i32> {
-        let mut ptr = self.0.as_mut();
-        let mut prev_ptrs = Vec::<*mut TreeNode>::new();
-        let mut target_val = None;
-
-        while let Some(node) = ptr {
-            let node_key = node.get_key();
-            if key > node_key {
-                prev_ptrs.push(&mut **node);
-                ptr = node.right.as_mut();
-            } else if key < node_key {
-                prev_ptrs.push(&mut **node);
-                ptr = node.left.as_mut();
-            } else {
-                target_val = Some(&mut **node);
-                break;
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let mut link = &mut self.0;
+        loop {
+            match key.cmp(link.as_ref()?.get_key()) {
+                Ordering::Greater => link = &mut link.as_mut().unwrap().right,
+                Ordering::Less => link = &mut link.as_mut().unwrap().left,
+                Ordering::Equal => break,
             }
         }
 
-        match target_val {
-            None => None,
-            Some(node) => {
-                // two children
-                if node.left.is_some() && node.right.is_some() {
-                    // if right.left.is_none(), min right child is right node, so
-                    // take right
-                    if node.right.as_ref().unwrap().left.is_none() {
-                        let mut right_node = node.right.take().unwrap();
-                        let inner_val = replace(&mut node.val, right_node.val);
-                        let _ = replace(&mut node.key, right_node.key);
-                        let _ = replace(&mut node.right, right_node.right.take());
-                        return Some(inner_val);
-                    }
-
-                    let mut rightmin = node.right.as_mut();
-                    let mut inner_ptrs = Vec::<*mut TreeNode>::new();
-                    while let Some(next_node) = rightmin {
-                        if next_node.left.is_some() {
-                            inner_ptrs.push(&mut **next_node);
-                        }
-
-                        rightmin = next_node.left.as_mut();
-                    }
-
-                    let parent_left_node = unsafe { &mut *inner_ptrs.pop().unwrap() };
-                    let mut leftmost_node = parent_left_node.left.take().unwrap();
-
-                    // replace key, value
-                    let inner_val = replace(&mut node.val, leftmost_node.val);
-                    let _ = replace(&mut node.key, leftmost_node.key);
-                    let _ = replace(&mut parent_left_node.left, leftmost_node.right.take());
-                    return Some(inner_val);
-
-                // one or zero children
-                } else if node.left.is_none() && node.right.is_some() {
-                    let right_node = node.right.take().unwrap();
-                    let inner_val = replace(node, *right_node).val;
-                    return Some(inner_val);
-                } else if node.right.is_none() && node.left.is_some() {
-                    let left_node = node.left.take().unwrap();
-                    let inner_val = replace(node, *left_node).val;
-                    return Some(inner_val);
-                } else if let Some(prev_ptr) = prev_ptrs.pop() {
-                    let prev_node = unsafe { &mut *prev_ptr };
-                    let inner_val = if let Some(left_node) = prev_node.left.as_ref() {
-                        if left_node.val == node.val {
-                            prev_node.left.take().unwrap().val
-                        } else {
-                            prev_node.right.take().unwrap().val
-                        }
-                    } else {
-                        prev_node.right.take().unwrap().val
-                    };
-
-                    return Some(inner_val);
-                } else {
-                    let inner_val = self.0.take().unwrap().val;
-                    return Some(inner_val);
-                }
-            }
+        let node = link.as_mut()?;
+        // two children, replace key, value with the min of right subtree
+        if node.left.is_some() && node.right.is_some() {
+            let (min_key, min_val) = pop_min(&mut node.right)?.into_entry();
+            node.key = min_key;
+            return Some(replace(&mut node.val, min_val));
         }
+
+        // one or zero children
+        let mut node = link.take()?;
+        *link = node.left.take().or_else(|| node.right.take());
+        Some(node.val)
+    }
+    /// key-val with the minimum key
+    fn first(&mut self) -> Option<(&K, &V)> {
+        self.0.as_ref().map(|root| root.first().entry())
+    }
+    /// key-val with the maximum key
+    fn last(&mut self) -> Option<(&K, &V)> {
+        self.0.as_ref().map(|root| root.last().entry())
+    }
+    /// key-val with the greatest key less than or equal to `key`
+    fn floor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.floor(key).map(TreeNode::entry)
+    }
+    /// key-val with the least key greater than or equal to `key`
+    fn ceiling(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.ceiling(key).map(TreeNode::entry)
+    }
+    /// key-val with the greatest key strictly less than `key`
+    fn predecessor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.predecessor(key).map(TreeNode::entry)
+    }
+    /// key-val with the least key strictly greater than `key`
+    fn successor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.successor(key).map(TreeNode::entry)
+    }
+    /// remove the leftmost node
+    fn pop_first(&mut self) -> Option<(K, V)> {
+        pop_min(&mut self.0).map(|node| node.into_entry())
+    }
+    /// remove the rightmost node
+    fn pop_last(&mut self) -> Option<(K, V)> {
+        pop_max(&mut self.0).map(|node| node.into_entry())
+    }
+}
+
+/// detach the leftmost node of the subtree
+fn pop_min<K, V>(mut link: &mut Option<Box<TreeNode<K, V>>>) -> Option<Box<TreeNode<K, V>>> {
+    while link.as_ref()?.left.is_some() {
+        link = &mut link.as_mut().unwrap().left;
+    }
+    let mut min = link.take()?;
+    *link = min.right.take();
+    Some(min)
+}
+
+/// detach the rightmost node of the subtree
+fn pop_max<K, V>(mut link: &mut Option<Box<TreeNode<K, V>>>) -> Option<Box<TreeNode<K, V>>> {
+    while link.as_ref()?.right.is_some() {
+        link = &mut link.as_mut().unwrap().right;
+    }
+    let mut max = link.take()?;
+    *link = max.left.take();
+    Some(max)
+}
+
+impl<K, V> IntoIterator for BinarySearchTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = TreeIntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TreeIntoIter::new(self.0)
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a BinarySearchTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = TreeIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut BinarySearchTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = TreeIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
@@ -159,31 +209,54 @@ mod tests {
         bst.insert(19, 2849);
         bst.insert(12, 28499);
 
-        assert_eq!(bst.search(9), Some(512));
-        assert_eq!(bst.search(19), Some(2849));
+        assert_eq!(bst.search(&9), Some(&512));
+        assert_eq!(bst.search(&19), Some(&2849));
         bst.insert(19, -12849);
-        assert_eq!(bst.search(19), Some(-12849));
-        assert_eq!(bst.search(13), Some(2344));
-        assert_eq!(bst.search(4), Some(16));
-        assert_eq!(bst.search(-1), None);
-        assert_eq!(bst.search(0), None);
-        assert_eq!(bst.remove(2), Some(4));
-        assert_eq!(bst.search(2), None);
-        assert_eq!(bst.remove(2), None);
-        assert_eq!(bst.remove(11), Some(234));
-        assert_eq!(bst.remove(11), None);
-        assert_eq!(bst.search(11), None);
-        assert_eq!(bst.remove(20), Some(12993));
-        assert_eq!(bst.remove(20), None);
-        assert_eq!(bst.search(20), None);
-        assert_eq!(bst.remove(8), Some(256));
-        assert_eq!(bst.remove(8), None);
-        assert_eq!(bst.search(8), None);
+        assert_eq!(bst.search(&19), Some(&-12849));
+        assert_eq!(bst.search(&13), Some(&2344));
+        assert_eq!(bst.search(&4), Some(&16));
+        assert_eq!(bst.search(&-1), None);
+        assert_eq!(bst.search(&0), None);
+        assert_eq!(bst.remove(&2), Some(4));
+        assert_eq!(bst.search(&2), None);
+        assert_eq!(bst.remove(&2), None);
+        assert_eq!(bst.remove(&11), Some(234));
+        assert_eq!(bst.remove(&11), None);
+        assert_eq!(bst.search(&11), None);
+        assert_eq!(bst.remove(&20), Some(12993));
+        assert_eq!(bst.remove(&20), None);
+        assert_eq!(bst.search(&20), None);
+        assert_eq!(bst.remove(&8), Some(256));
+        assert_eq!(bst.remove(&8), None);
+        assert_eq!(bst.search(&8), None);
         bst.insert(17, 49);
         bst.insert(14, 149);
         bst.insert(16, 169);
-        assert_eq!(bst.remove(15), Some(12345));
-        assert_eq!(bst.remove(15), None);
-        assert_eq!(bst.search(15), None);
+        assert_eq!(bst.remove(&15), Some(12345));
+        assert_eq!(bst.remove(&15), None);
+        assert_eq!(bst.search(&15), None);
+        assert_eq!(bst.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn broken_invariants() {
+        let mut bst = BinarySearchTree::new(1, 1);
+        bst.insert(0, 0);
+        bst.insert(2, 2);
+        assert_eq!(bst.check_invariants(), Ok(()));
+
+        bst.0.as_mut().unwrap().key = 5;
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::KeyOrder { index: 2 })
+        );
+    }
+
+    #[test]
+    fn insert_after_emptying() {
+        let mut bst = BinarySearchTree::new(10, 1024);
+        assert_eq!(bst.remove(&10), Some(1024));
+        bst.insert(4, 16);
+        assert_eq!(bst.search(&4), Some(&16));
     }
 }
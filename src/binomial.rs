@@ -0,0 +1,266 @@
+//! Binomial heap, a forest of binomial trees
+//!
+//! A binomial tree of order `k` has a root whose children are binomial trees
+//! of orders `0` to `k - 1`, so it holds `2^k` items. The heap keeps at most
+//! one tree of each order, like the bits of its length, and melds two heaps
+//! by adding them as binary numbers: two trees of the same order link into
+//! one of the next, the lesser root becoming a child of the greater.
+
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use crate::heap::MergeableHeap;
+use crate::InvariantError;
+use std::mem::{swap, take};
+
+type Tree<T> = Option<Box<BinomialNode<T>>>;
+
+#[derive(Debug)]
+struct BinomialNode<T> {
+    item: T,
+    /// `children[i]` has order `i`
+    children: Vec<Box<BinomialNode<T>>>,
+}
+
+/// Binomial heap, a max-heap by default or ordered by any comparator `C`
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{BinomialHeap, MergeableHeap};
+///
+/// let mut heap = BinomialHeap::new();
+/// for i in 0..5 {
+///     heap.push(i);
+/// }
+/// // 5 items are a tree of order 0 and one of order 2
+/// assert_eq!(heap.orders(), [0, 2]);
+///
+/// let mut other = BinomialHeap::new();
+/// other.push(10);
+/// heap.meld(other);
+/// assert_eq!(heap.orders(), [1, 2]);
+/// assert_eq!(heap.pop(), Some(10));
+/// assert_eq!(heap.peek(), Some(&4));
+/// ```
+#[derive(Debug)]
+pub struct BinomialHeap<T, C = MaxComparator> {
+    /// `trees[k]` is the tree of order `k`, if any
+    trees: Vec<Tree<T>>,
+    len: usize,
+    cmp: C,
+}
+
+impl<T: Ord> BinomialHeap<T> {
+    /// Creates an empty `BinomialHeap` as a max-heap.
+    pub fn new() -> Self {
+        Self::with_comparator(MaxComparator)
+    }
+}
+
+impl<T: Ord> BinomialHeap<T, MinComparator> {
+    /// Creates an empty `BinomialHeap` as a min-heap.
+    pub fn new_min() -> Self {
+        Self::with_comparator(MinComparator)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for BinomialHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> BinomialHeap<T, C> {
+    /// Creates an empty `BinomialHeap` ordered by `cmp`, the greatest item by
+    /// `cmp` being on top.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            trees: Vec::new(),
+            len: 0,
+            cmp,
+        }
+    }
+
+    /// Returns the number of items in the heap.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the orders of the trees in the heap, in increasing order.
+    pub fn orders(&self) -> Vec<usize> {
+        (0..self.trees.len())
+            .filter(|&k| self.trees[k].is_some())
+            .collect()
+    }
+
+    /// link two trees of the same order into one of the next
+    fn link(
+        &self,
+        mut a: Box<BinomialNode<T>>,
+        mut b: Box<BinomialNode<T>>,
+    ) -> Box<BinomialNode<T>> {
+        if self.cmp.lt(&a.item, &b.item) {
+            swap(&mut a, &mut b);
+        }
+        a.children.push(b);
+        a
+    }
+
+    /// add the forest `other` to the heap's, carrying linked trees up
+    fn add(&mut self, mut other: Vec<Tree<T>>) {
+        let orders = self.trees.len().max(other.len());
+        self.trees.resize_with(orders, || None);
+        let mut carry = None;
+        for k in 0..orders {
+            let theirs = other.get_mut(k).and_then(Option::take);
+            let trees = [self.trees[k].take(), theirs, carry.take()];
+            let mut trees = IntoIterator::into_iter(trees).flatten();
+            match (trees.next(), trees.next(), trees.next()) {
+                (Some(a), Some(b), rest) => {
+                    self.trees[k] = rest;
+                    carry = Some(self.link(a, b));
+                }
+                (one, ..) => self.trees[k] = one,
+            }
+        }
+        if carry.is_some() {
+            self.trees.push(carry);
+        }
+    }
+
+    /// order of the tree with the greatest root
+    fn top(&self) -> Option<usize> {
+        let mut top: Option<(usize, &T)> = None;
+        for (k, tree) in self.trees.iter().enumerate() {
+            if let Some(tree) = tree {
+                if top.is_none_or(|(_, item)| self.cmp.gt(&tree.item, item)) {
+                    top = Some((k, &tree.item));
+                }
+            }
+        }
+        top.map(|(k, _)| k)
+    }
+
+    /// Checks heap order, and that every tree has the shape of its order
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let mut count = 0;
+        for (k, tree) in self.trees.iter().enumerate() {
+            if let Some(tree) = tree {
+                count += self.check_tree(tree, k, 0)?;
+            }
+        }
+        if count != self.len {
+            return Err(InvariantError::Size {
+                stored: self.len,
+                actual: count,
+            });
+        }
+        Ok(())
+    }
+
+    /// check a tree expected to be of order `k`, return its size
+    fn check_tree(
+        &self,
+        node: &BinomialNode<T>,
+        k: usize,
+        depth: usize,
+    ) -> Result<usize, InvariantError> {
+        let mut size = 1;
+        for (order, child) in node.children.iter().enumerate() {
+            if self.cmp.gt(&child.item, &node.item) {
+                return Err(InvariantError::HeapOrder { depth: depth + 1 });
+            }
+            size += self.check_tree(child, order, depth + 1)?;
+        }
+        if size != 1 << k {
+            return Err(InvariantError::Size {
+                stored: 1 << k,
+                actual: size,
+            });
+        }
+        Ok(size)
+    }
+}
+
+impl<T, C: Compare<T>> MergeableHeap<T> for BinomialHeap<T, C> {
+    type Peek<'a>
+        = &'a T
+    where
+        Self: 'a;
+
+    fn push(&mut self, item: T) {
+        let node = Box::new(BinomialNode {
+            item,
+            children: Vec::new(),
+        });
+        self.add(vec![Some(node)]);
+        self.len += 1;
+    }
+
+    /// *O*(log(*n*)), scanning the roots
+    fn peek(&self) -> Option<&T> {
+        let top = self.top()?;
+        self.trees[top].as_ref().map(|tree| &tree.item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let top = self.top()?;
+        let tree = self.trees[top].take()?;
+        while let Some(None) = self.trees.last() {
+            self.trees.pop();
+        }
+        let BinomialNode { item, children } = *tree;
+        self.add(children.into_iter().map(Some).collect());
+        self.len -= 1;
+        Some(item)
+    }
+
+    /// *O*(log(*n*))
+    fn meld(&mut self, mut other: Self) {
+        self.add(take(&mut other.trees));
+        self.len += other.len;
+    }
+
+    fn is_empty(&self) -> bool {
+        BinomialHeap::is_empty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binomial_push_pop_meld() {
+        let mut a = BinomialHeap::new_min();
+        let mut b = BinomialHeap::new_min();
+        for i in 0..300 {
+            a.push(i * 7 % 300);
+            assert_eq!(a.orders().len(), a.len().count_ones() as usize);
+        }
+        for i in 0..77 {
+            b.push(i);
+        }
+        assert_eq!(a.check_invariants(), Ok(()));
+        a.meld(b);
+        assert_eq!(a.len(), 377);
+        assert_eq!(a.orders().len(), 377usize.count_ones() as usize);
+        assert_eq!(a.check_invariants(), Ok(()));
+
+        let mut popped = Vec::new();
+        while let Some(item) = a.pop() {
+            popped.push(item);
+            if popped.len() % 50 == 0 {
+                assert_eq!(a.check_invariants(), Ok(()));
+            }
+        }
+        let mut expected: Vec<_> = (0..300).chain(0..77).collect();
+        expected.sort_unstable();
+        assert_eq!(popped, expected);
+        assert!(a.orders().is_empty());
+    }
+}
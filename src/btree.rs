@@ -1,13 +1,21 @@
 //! BTree
 //! M way balance search tree
 
-use crate::{BTNode, BST};
+use crate::{
+    BTNode, BTreeIntoIter, BTreeIter, BTreeIterMut, BTreeRange, InvariantError, Keys, Values, BST,
+};
+use std::borrow::Borrow;
+use std::mem::replace;
+use std::ops::{Bound, RangeBounds};
+
+/// picks a key in a node by `Ok(idx)`, or the child to go down by `Err(idx)`
+type Locate<'a, K, V> = dyn Fn(&BTNode<K, V>) -> Result<usize, usize> + 'a;
 
 /// B Tree
 #[derive(Debug, PartialEq, Eq)]
-pub struct BTree {
+pub struct BTree<K, V> {
     /// Root node
-    pub root: Option<Box<BTNode>>,
+    pub root: Option<Box<BTNode<K, V>>>,
     /// key code size
     pub size: usize,
     /// order size must >= 2
@@ -16,7 +24,7 @@ pub struct BTree {
     pub lower_bound: usize,
 }
 
-impl BTree {
+impl<K: Ord, V> BTree<K, V> {
     /// Create a upper bound [order / 2 - 1, order] B tree
     pub fn new(size: usize, order: usize) -> Self {
         assert!(order > 2, "order must greater than 2");
@@ -41,47 +49,135 @@ impl BTree {
     pub fn get_order(&self) -> usize {
         self.order
     }
-    /// Overflow algorithm
-    pub fn overflow(&mut self, mut ptrs: Vec<*mut BTNode>, mut node: &mut BTNode) {
-        while let Some(parent_ptr) = ptrs.pop() {
-            let median = node.keys.len() / 2;
-            let right_keys = node.keys.split_off(median + 1);
-            let right_vals = node.vals.split_off(median + 1);
-            let right_children = node.children.split_off(median + 1);
 
-            let (up_key, up_val) = (node.keys.pop().unwrap(), node.vals.pop().unwrap());
+    /// Gets an iterator over the entries of the tree, sorted by key
+    pub fn iter(&self) -> BTreeIter<'_, K, V> {
+        BTreeIter::new(self.root.as_deref())
+    }
 
-            let right_node = Some(Box::new(BTNode::new(
-                right_keys,
-                right_vals,
-                right_children,
-            )));
+    /// Gets a mutable iterator over the entries of the tree, sorted by key
+    pub fn iter_mut(&mut self) -> BTreeIterMut<'_, K, V> {
+        BTreeIterMut::new(self.root.as_deref_mut())
+    }
 
-            let parent_node = unsafe { &mut *parent_ptr };
-            let idx = parent_node.keys.binary_search(&up_key).unwrap_err();
+    /// Gets an iterator over the keys of the tree, in sorted order
+    pub fn keys(&self) -> Keys<BTreeIter<'_, K, V>> {
+        Keys(self.iter())
+    }
 
-            parent_node.keys.insert(idx, up_key);
-            parent_node.vals.insert(idx, up_val);
-            parent_node.children.insert(idx + 1, right_node);
+    /// Gets an iterator over the values of the tree, in order by key
+    pub fn values(&self) -> Values<BTreeIter<'_, K, V>> {
+        Values(self.iter())
+    }
 
-            if parent_node.keys.len() <= self.order {
-                break;
-            }
-            node = parent_node;
+    /// Constructs a double-ended iterator over a sub-range of entries in the tree
+    pub fn range<T, R>(&self, range: R) -> BTreeRange<'_, K, V>
+    where
+        K: Borrow<T>,
+        T: Ord + ?Sized,
+        R: RangeBounds<T>,
+    {
+        BTreeRange::new(self.root.as_deref(), range)
+    }
+
+    /// Checks key ordering, that every node but the root holds
+    /// [lower_bound - 1, order - 1] keys and one more child than keys, that
+    /// all leaves are on the same level, and that `size` is accurate
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let mut walk = Walk {
+            prev: None,
+            seen: 0,
+            leaf_depth: None,
+        };
+        if let Some(root) = self.root.as_deref() {
+            self.check_node(root, 0, &mut walk)?;
+        }
+        if self.size != walk.seen {
+            return Err(InvariantError::Size {
+                stored: self.size,
+                actual: walk.seen,
+            });
         }
+        Ok(())
+    }
 
-        // root node split
-        let root = self.root.as_mut().unwrap();
-        if root.keys.len() < self.order {
-            return;
+    /// check the subtree of `node`, visiting its keys in order
+    fn check_node<'a>(
+        &self,
+        node: &'a BTNode<K, V>,
+        depth: usize,
+        walk: &mut Walk<'a, K>,
+    ) -> Result<(), InvariantError> {
+        let count = node.keys.len();
+        let is_leaf = node.children.iter().all(Option::is_none);
+        // root may run down to a single key, or none in an empty tree
+        let min = match depth {
+            0 if is_leaf => 0,
+            0 => 1,
+            _ => self.lower_bound - 1,
+        };
+        if count < min || count >= self.order {
+            return Err(InvariantError::KeyCount {
+                depth,
+                count,
+                min,
+                max: self.order - 1,
+            });
+        }
+        if node.vals.len() != count {
+            return Err(InvariantError::ValueCount {
+                depth,
+                keys: count,
+                vals: node.vals.len(),
+            });
         }
+        // a branch must have every child present, a leaf none of them
+        let children = match is_leaf || node.children.len() != count + 1 {
+            true => node.children.len(),
+            false => node.children.iter().flatten().count(),
+        };
+        if children != count + 1 {
+            return Err(InvariantError::ChildCount {
+                depth,
+                keys: count,
+                children,
+            });
+        }
+
+        if is_leaf {
+            let expected = *walk.leaf_depth.get_or_insert(depth);
+            if expected != depth {
+                return Err(InvariantError::LeafDepth {
+                    expected,
+                    actual: depth,
+                });
+            }
+        }
+        for (idx, key) in node.keys.iter().enumerate() {
+            if let Some(child) = node.children[idx].as_deref() {
+                self.check_node(child, depth + 1, walk)?;
+            }
+            if walk.prev.is_some_and(|prev| prev >= key) {
+                return Err(InvariantError::KeyOrder { index: walk.seen });
+            }
+            walk.prev = Some(key);
+            walk.seen += 1;
+        }
+        match node.children[count].as_deref() {
+            Some(child) => self.check_node(child, depth + 1, walk),
+            None => Ok(()),
+        }
+    }
 
-        let median = root.keys.len() / 2;
-        let right_keys = root.keys.split_off(median + 1);
-        let right_vals = root.vals.split_off(median + 1);
-        let right_children = root.children.split_off(median + 1);
+    /// split the overflowed child `idx` of `parent` at its median key
+    fn overflow(parent: &mut BTNode<K, V>, idx: usize) {
+        let node = parent.children[idx].as_mut().unwrap();
+        let median = node.keys.len() / 2;
+        let right_keys = node.keys.split_off(median + 1);
+        let right_vals = node.vals.split_off(median + 1);
+        let right_children = node.children.split_off(median + 1);
 
-        let (up_key, up_val) = (root.keys.pop().unwrap(), root.vals.pop().unwrap());
+        let (up_key, up_val) = (node.keys.pop().unwrap(), node.vals.pop().unwrap());
 
         let right_node = Some(Box::new(BTNode::new(
             right_keys,
@@ -89,160 +185,173 @@ impl BTree {
             right_children,
         )));
 
-        let mut new_root = Box::new(BTNode::default());
-        new_root.keys.push(up_key);
-        new_root.vals.push(up_val);
-        new_root.children.push(self.root.take());
-        new_root.children.push(right_node);
-
-        self.root = Some(new_root);
-    }
-
-    /// Underflow algorithm
-    pub fn underflow(&mut self, mut ptrs: Vec<*mut BTNode>, mut node: &mut BTNode) {
-        while let Some(parent_ptr) = ptrs.pop() {
-            let parent_node = unsafe { &mut *parent_ptr };
-            let idx = parent_node
-                .children
-                .iter()
-                .position(|child| {
-                    if let Some(c) = child.as_ref() {
-                        &**c == node
-                    } else {
-                        false
-                    }
-                })
-                .unwrap();
-
-            if idx == 0 {
-                if let Some(right_sibling) = parent_node.children[idx + 1].as_mut() {
-                    // rotate
-                    if right_sibling.keys.len() + 1 > self.lower_bound {
-                        node.keys.push(parent_node.keys[idx]);
-                        node.vals.push(parent_node.vals[idx]);
-
-                        parent_node.keys[idx] = right_sibling.keys.remove(0);
-                        parent_node.vals[idx] = right_sibling.vals.remove(0);
-                        // wrong
-                        node.children.push(right_sibling.children.remove(0));
-                    } else {
-                        // merge
-                        node.keys.push(parent_node.keys.remove(idx));
-                        node.vals.push(parent_node.vals.remove(idx));
-
-                        node.keys.append(&mut right_sibling.keys);
-                        node.vals.append(&mut right_sibling.vals);
-                        node.children.append(&mut right_sibling.children);
-
-                        parent_node.children.remove(idx + 1);
-                    }
+        parent.keys.insert(idx, up_key);
+        parent.vals.insert(idx, up_val);
+        parent.children.insert(idx + 1, right_node);
+    }
+
+    /// fix the underflowed child `idx` of `parent`, rotate a key from a
+    /// sibling if it can spare one, or merge with a sibling
+    fn underflow(parent: &mut BTNode<K, V>, idx: usize, lower_bound: usize) {
+        let can_spare = |child: &Option<Box<BTNode<K, V>>>| {
+            child
+                .as_ref()
+                .is_some_and(|sibling| sibling.keys.len() >= lower_bound)
+        };
+
+        if idx > 0 && can_spare(&parent.children[idx - 1]) {
+            // rotate right from the left sibling
+            let (left, right) = parent.children.split_at_mut(idx);
+            let left_sibling = left[idx - 1].as_mut().unwrap();
+            let node = right[0].as_mut().unwrap();
+
+            let down_key = replace(&mut parent.keys[idx - 1], left_sibling.keys.pop().unwrap());
+            let down_val = replace(&mut parent.vals[idx - 1], left_sibling.vals.pop().unwrap());
+            node.keys.insert(0, down_key);
+            node.vals.insert(0, down_val);
+            node.children
+                .insert(0, left_sibling.children.pop().unwrap());
+        } else if idx + 1 < parent.children.len() && can_spare(&parent.children[idx + 1]) {
+            // rotate left from the right sibling
+            let (left, right) = parent.children.split_at_mut(idx + 1);
+            let node = left[idx].as_mut().unwrap();
+            let right_sibling = right[0].as_mut().unwrap();
+
+            let down_key = replace(&mut parent.keys[idx], right_sibling.keys.remove(0));
+            let down_val = replace(&mut parent.vals[idx], right_sibling.vals.remove(0));
+            node.keys.push(down_key);
+            node.vals.push(down_val);
+            node.children.push(right_sibling.children.remove(0));
+        } else {
+            // merge with the left sibling if any, or else the right one
+            let idx = if idx > 0 { idx - 1 } else { idx };
+            let mut right_node = parent.children.remove(idx + 1).unwrap();
+            let left_node = parent.children[idx].as_mut().unwrap();
+
+            left_node.keys.push(parent.keys.remove(idx));
+            left_node.vals.push(parent.vals.remove(idx));
+
+            left_node.keys.append(&mut right_node.keys);
+            left_node.vals.append(&mut right_node.vals);
+            left_node.children.append(&mut right_node.children);
+        }
+    }
+
+    /// insert key-val into the subtree of `node`, split children overflowed
+    fn insert_node(node: &mut BTNode<K, V>, key: K, val: V, order: usize) -> Option<V> {
+        match node.keys.binary_search(&key) {
+            Ok(idx) => Some(replace(&mut node.vals[idx], val)),
+            Err(idx) => match node.children[idx].as_mut() {
+                // node is leaf node
+                None => {
+                    node.keys.insert(idx, key);
+                    node.vals.insert(idx, val);
+                    node.children.push(None);
+                    None
                 }
-            } else if idx == parent_node.children.len() - 1 {
-                if let Some(left_sibling) = parent_node.children[idx - 1].as_mut() {
-                    // rotate
-                    if left_sibling.keys.len() + 1 > self.lower_bound {
-                        node.keys.insert(0, parent_node.keys[idx - 1]);
-                        node.vals.insert(0, parent_node.vals[idx - 1]);
-
-                        parent_node.keys[idx - 1] = left_sibling.keys.pop().unwrap();
-                        parent_node.vals[idx - 1] = left_sibling.vals.pop().unwrap();
-                        node.children.push(left_sibling.children.pop().unwrap());
-                    } else {
-                        // merge
-                        left_sibling.keys.push(parent_node.keys.remove(idx - 1));
-                        left_sibling.vals.push(parent_node.vals.remove(idx - 1));
-
-                        left_sibling.keys.append(&mut node.keys);
-                        left_sibling.vals.append(&mut node.vals);
-                        left_sibling.children.append(&mut node.children);
-
-                        parent_node.children.remove(idx);
+                Some(child) => {
+                    let old_val = Self::insert_node(child, key, val, order);
+                    if child.keys.len() >= order {
+                        Self::overflow(node, idx);
                     }
+                    old_val
                 }
-            } else if let Some(left_sibling) = parent_node.children[idx - 1].as_mut() {
-                // rotate
-                if left_sibling.keys.len() + 1 > self.lower_bound {
-                    node.keys.insert(0, parent_node.keys[idx - 1]);
-                    node.vals.insert(0, parent_node.vals[idx - 1]);
-
-                    parent_node.keys[idx - 1] = left_sibling.keys.pop().unwrap();
-                    parent_node.vals[idx - 1] = left_sibling.vals.pop().unwrap();
-                    node.children.push(left_sibling.children.pop().unwrap());
-                } else {
-                    // merge
-                    left_sibling.keys.push(parent_node.keys.remove(idx - 1));
-                    left_sibling.vals.push(parent_node.vals.remove(idx - 1));
-
-                    left_sibling.keys.append(&mut node.keys);
-                    left_sibling.vals.append(&mut node.vals);
-                    left_sibling.children.append(&mut node.children);
-
-                    parent_node.children.remove(idx);
-                }
-            // after left [19] [18,14,]
-            // rotate
-            } else if let Some(right_sibling) = parent_node.children[idx + 1].as_mut() {
-                // rotate
-                if right_sibling.keys.len() + 1 > self.lower_bound {
-                    node.keys.push(parent_node.keys[idx - 1]);
-                    node.vals.push(parent_node.vals[idx - 1]);
-
-                    parent_node.keys[idx - 1] = right_sibling.keys.remove(0);
-                    parent_node.vals[idx - 1] = right_sibling.vals.remove(0);
-                    node.children.push(right_sibling.children.remove(0));
-                } else {
-                    // merge
-                    node.keys.push(parent_node.keys.remove(idx - 1));
-                    node.vals.push(parent_node.vals.remove(idx - 1));
-
-                    node.keys.append(&mut right_sibling.keys);
-                    node.vals.append(&mut right_sibling.vals);
-                    node.children.append(&mut right_sibling.children);
-
-                    parent_node.children.remove(idx);
+            },
+        }
+    }
+
+    /// remove the key-val located by `locate` from the subtree of `node`,
+    /// `locate` gives `Ok(idx)` on the target key or `Err(idx)` for the next
+    /// child, underflowed children are fixed on the way back
+    fn remove_node(
+        node: &mut BTNode<K, V>,
+        locate: &Locate<'_, K, V>,
+        lower_bound: usize,
+    ) -> Option<(K, V)> {
+        match locate(node) {
+            Ok(idx) => {
+                let child = match node.children[idx + 1].as_mut() {
+                    Some(child) => child,
+                    // node is leaf node
+                    None => {
+                        node.children.pop();
+                        return Some((node.keys.remove(idx), node.vals.remove(idx)));
+                    }
+                };
+
+                // node is a branch, swap with the leftmost key of the right subtree
+                let (key, val) = Self::remove_node(child, &locate_first, lower_bound)?;
+                let underflowed = child.keys.len() + 1 < lower_bound;
+                let entry = (
+                    replace(&mut node.keys[idx], key),
+                    replace(&mut node.vals[idx], val),
+                );
+                if underflowed {
+                    Self::underflow(node, idx + 1, lower_bound);
                 }
+                Some(entry)
             }
-
-            if parent_node.keys.len() + 1 >= self.lower_bound {
-                break;
+            Err(idx) => {
+                let child = node.children[idx].as_mut()?;
+                let entry = Self::remove_node(child, locate, lower_bound)?;
+                if child.keys.len() + 1 < lower_bound {
+                    Self::underflow(node, idx, lower_bound);
+                }
+                Some(entry)
             }
-
-            node = parent_node;
         }
+    }
 
-        let root = self.root.as_mut().unwrap();
-        if root.keys.len() == 0 {
-            let new_root = root.children[0].take();
-            self.root = new_root;
+    /// remove the key-val located by `locate`, see `remove_node`
+    fn remove_by(&mut self, locate: &Locate<'_, K, V>) -> Option<(K, V)> {
+        let root = self.root.as_mut()?;
+        let entry = Self::remove_node(root, locate, self.lower_bound)?;
+        self.size -= 1;
+
+        // root node merged away
+        if root.keys.is_empty() && root.children[0].is_some() {
+            self.root = root.children[0].take();
         }
+        Some(entry)
     }
+}
 
-    /// return a vector of pointer from root to key path
-    fn searchin(&mut self, key: i32) -> Vec<*mut BTNode> {
-        let mut ptr = self.root.as_mut();
-        let mut prev_ptrs = Vec::<*mut BTNode>::new();
-
-        while let Some(node) = ptr {
-            prev_ptrs.push(&mut **node);
+/// in-order state of `BTree::check_invariants`
+struct Walk<'a, K> {
+    /// last key visited
+    prev: Option<&'a K>,
+    /// number of keys visited
+    seen: usize,
+    /// depth of the first leaf met
+    leaf_depth: Option<usize>,
+}
 
-            let next_idx = match node.keys.binary_search(&key) {
-                Ok(_) => break,
-                Err(idx) => idx,
-            };
-            ptr = node.children[next_idx].as_mut();
-        }
+/// locate the leftmost key of the leftmost leaf
+fn locate_first<K, V>(node: &BTNode<K, V>) -> Result<usize, usize> {
+    match node.children.first() {
+        Some(Some(_)) => Err(0),
+        _ if node.keys.is_empty() => Err(0),
+        _ => Ok(0),
+    }
+}
 
-        prev_ptrs
+/// locate the rightmost key of the rightmost leaf
+fn locate_last<K, V>(node: &BTNode<K, V>) -> Result<usize, usize> {
+    match node.children.last() {
+        Some(Some(_)) => Err(node.keys.len()),
+        _ if node.keys.is_empty() => Err(0),
+        _ => Ok(node.keys.len() - 1),
     }
 }
 
-impl BST for BTree {
+impl<K: Ord, V> BST<K, V> for BTree<K, V> {
     /// search val by key
-    fn search(&mut self, key: i32) -> Option<i32> {
-        if let Some(node_ptr) = self.searchin(key).pop() {
-            let node = unsafe { &mut *node_ptr };
-            if let Ok(idx) = node.keys.binary_search(&key) {
-                return Some(node.vals[idx]);
+    fn search(&mut self, key: &K) -> Option<&V> {
+        let mut ptr = self.root.as_deref();
+        while let Some(node) = ptr {
+            match node.keys.binary_search(key) {
+                Ok(idx) => return Some(&node.vals[idx]),
+                Err(idx) => ptr = node.children[idx].as_deref(),
             }
         }
 
@@ -250,87 +359,92 @@ impl BST for BTree {
     }
 
     /// insert key, value. if children more than order will split and overflow
-    fn insert(&mut self, key: i32, val: i32) {
-        let mut prev_ptrs = self.searchin(key);
-        if let Some(node_ptr) = prev_ptrs.pop() {
-            let node = unsafe { &mut *node_ptr };
-            match node.keys.binary_search(&key) {
-                Ok(idx) => {
-                    node.vals[idx] = val;
-                    return;
-                }
-                Err(idx) => {
-                    node.keys.insert(idx, key);
-                    node.vals.insert(idx, val);
+    fn insert(&mut self, key: K, val: V) {
+        let root = self.root.as_mut().unwrap();
+        if Self::insert_node(root, key, val, self.order).is_none() {
+            self.size += 1;
+        }
 
-                    node.children.push(None);
-                    self.size += 1;
-                    // if key size more equal than order
-                    // need to overflow
-                    if self.order == node.keys.len() {
-                        self.overflow(prev_ptrs, node);
-                    }
-                }
-            }
+        // root node split
+        if root.keys.len() >= self.order {
+            let mut new_root = Box::new(BTNode::default());
+            new_root.children.push(self.root.take());
+            Self::overflow(&mut new_root, 0);
+            self.root = Some(new_root);
         }
     }
 
     /// remove val
-    fn remove(&mut self, key: i32) -> Option<i32> {
-        let mut prev_ptrs = self.searchin(key);
-        if let Some(node_ptr) = prev_ptrs.pop() {
-            let node = unsafe { &mut *node_ptr };
-            if let Ok(idx) = node.keys.binary_search(&key) {
-                let mut ptr = node.children[idx + 1].as_mut();
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_by(&|node| node.keys.binary_search(key))
+            .map(|(_, val)| val)
+    }
 
-                // node is leaf node
-                if ptr.is_none() {
-                    node.keys.remove(idx);
-                    node.children.pop();
-                    let inner_val = node.vals.remove(idx);
+    /// leftmost key of the leftmost leaf
+    fn first(&mut self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
 
-                    if self.root.as_ref().unwrap().keys.is_empty() {
-                        return Some(inner_val);
-                    }
+    /// rightmost key of the rightmost leaf
+    fn last(&mut self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
 
-                    if self.lower_bound == node.keys.len() + 2 {
-                        self.underflow(prev_ptrs, node);
-                    }
+    /// key-val with the greatest key less than or equal to `key`
+    fn floor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.range(..=key).next_back()
+    }
 
-                    return Some(inner_val);
-                }
+    /// key-val with the least key greater than or equal to `key`
+    fn ceiling(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.range(key..).next()
+    }
 
-                // node is a branch, need to find leaf
-                prev_ptrs.push(node_ptr);
-                while let Some(inner_node) = ptr {
-                    prev_ptrs.push(&mut **inner_node);
-                    ptr = inner_node.children[0].as_mut();
-                }
+    /// key-val with the greatest key strictly less than `key`
+    fn predecessor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.range(..key).next_back()
+    }
 
-                if let Some(inner_ptr) = prev_ptrs.pop() {
-                    let inner_node = unsafe { &mut *inner_ptr };
-                    let inner_val = Some(node.vals[idx]);
+    /// key-val with the least key strictly greater than `key`
+    fn successor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.range((Bound::Excluded(key), Bound::Unbounded)).next()
+    }
 
-                    node.keys[idx] = inner_node.keys[0];
-                    node.vals[idx] = inner_node.vals[0];
+    /// remove the leftmost key of the leftmost leaf
+    fn pop_first(&mut self) -> Option<(K, V)> {
+        self.remove_by(&locate_first)
+    }
 
-                    inner_node.keys.remove(0);
-                    inner_node.vals.remove(0);
-                    inner_node.children.pop();
-                    self.size -= 1;
+    /// remove the rightmost key of the rightmost leaf
+    fn pop_last(&mut self) -> Option<(K, V)> {
+        self.remove_by(&locate_last)
+    }
+}
 
-                    if self.root.as_ref().unwrap().keys.is_empty() {
-                        return inner_val;
-                    }
+impl<K, V> IntoIterator for BTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = BTreeIntoIter<K, V>;
 
-                    if self.lower_bound == inner_node.keys.len() + 2 {
-                        self.underflow(prev_ptrs, inner_node);
-                    }
-                    return inner_val;
-                }
-            }
-        }
-        None
+    fn into_iter(self) -> Self::IntoIter {
+        BTreeIntoIter::new(self.root)
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a BTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = BTreeIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut BTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = BTreeIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
@@ -342,8 +456,8 @@ mod tests {
         for order in 3..10 {
             let mut bst = BTree::new(0, order);
             bst.insert(4, 16);
-            assert_eq!(bst.search(4), Some(16));
-            assert_eq!(bst.remove(4), Some(16));
+            assert_eq!(bst.search(&4), Some(&16));
+            assert_eq!(bst.remove(&4), Some(16));
             bst.insert(8, 256);
 
             bst.insert(5, 32);
@@ -373,61 +487,128 @@ mod tests {
             bst.insert(28, 28499);
             bst.insert(22, 28499);
             bst.insert(29, 28499);
-
-            assert_eq!(bst.search(8), Some(256));
-            assert_eq!(bst.search(5), Some(32));
-            assert_eq!(bst.search(9), Some(512));
-            assert_eq!(bst.search(3), Some(8));
-            assert_eq!(bst.search(2), Some(4));
-            assert_eq!(bst.search(15), Some(12345));
-            assert_eq!(bst.search(13), Some(2344));
-            assert_eq!(bst.search(11), Some(234));
-            assert_eq!(bst.search(18), Some(1994));
-            assert_eq!(bst.search(20), Some(12993));
-            assert_eq!(bst.search(19), Some(2849));
-            assert_eq!(bst.search(12), Some(28499));
-            assert_eq!(bst.search(10), Some(28499));
-            assert_eq!(bst.search(1), Some(28499));
-            assert_eq!(bst.search(6), Some(28499));
-            assert_eq!(bst.search(14), Some(28499));
-            assert_eq!(bst.search(24), Some(28499));
-            assert_eq!(bst.search(28), Some(28499));
-            assert_eq!(bst.search(22), Some(28499));
-            assert_eq!(bst.search(23), Some(28499));
-            assert_eq!(bst.search(25), Some(28499));
-            assert_eq!(bst.search(26), Some(28499));
-            assert_eq!(bst.remove(2), Some(4));
-            assert_eq!(bst.remove(11), Some(234));
-            assert_eq!(bst.remove(20), Some(12993));
-            assert_eq!(bst.remove(8), Some(256));
-            assert_eq!(bst.remove(15), Some(12345));
-
-            assert_eq!(bst.search(9), Some(512));
-            assert_eq!(bst.search(19), Some(2849));
-            assert_eq!(bst.remove(10), Some(28499));
-            assert_eq!(bst.remove(5), Some(32));
-            assert_eq!(bst.remove(9), Some(512));
-            assert_eq!(bst.remove(3), Some(8));
-            assert_eq!(bst.search(22), Some(28499));
+            assert_eq!(bst.check_invariants(), Ok(()));
+
+            assert_eq!(bst.search(&8), Some(&256));
+            assert_eq!(bst.search(&5), Some(&32));
+            assert_eq!(bst.search(&9), Some(&512));
+            assert_eq!(bst.search(&3), Some(&8));
+            assert_eq!(bst.search(&2), Some(&4));
+            assert_eq!(bst.search(&15), Some(&12345));
+            assert_eq!(bst.search(&13), Some(&2344));
+            assert_eq!(bst.search(&11), Some(&234));
+            assert_eq!(bst.search(&18), Some(&1994));
+            assert_eq!(bst.search(&20), Some(&12993));
+            assert_eq!(bst.search(&19), Some(&2849));
+            assert_eq!(bst.search(&12), Some(&28499));
+            assert_eq!(bst.search(&10), Some(&28499));
+            assert_eq!(bst.search(&1), Some(&28499));
+            assert_eq!(bst.search(&6), Some(&28499));
+            assert_eq!(bst.search(&14), Some(&28499));
+            assert_eq!(bst.search(&24), Some(&28499));
+            assert_eq!(bst.search(&28), Some(&28499));
+            assert_eq!(bst.search(&22), Some(&28499));
+            assert_eq!(bst.search(&23), Some(&28499));
+            assert_eq!(bst.search(&25), Some(&28499));
+            assert_eq!(bst.search(&26), Some(&28499));
+            assert_eq!(bst.remove(&2), Some(4));
+            assert_eq!(bst.remove(&11), Some(234));
+            assert_eq!(bst.remove(&20), Some(12993));
+            assert_eq!(bst.remove(&8), Some(256));
+            assert_eq!(bst.remove(&15), Some(12345));
+
+            assert_eq!(bst.search(&9), Some(&512));
+            assert_eq!(bst.search(&19), Some(&2849));
+            assert_eq!(bst.remove(&10), Some(28499));
+            assert_eq!(bst.remove(&5), Some(32));
+            assert_eq!(bst.remove(&9), Some(512));
+            assert_eq!(bst.remove(&3), Some(8));
+            assert_eq!(bst.search(&22), Some(&28499));
             bst.insert(19, -12849);
-            assert_eq!(bst.remove(1), Some(28499));
-            assert_eq!(bst.remove(6), Some(28499));
-            assert_eq!(bst.remove(13), Some(2344));
-            assert_eq!(bst.remove(12), Some(28499));
-            assert_eq!(bst.remove(19), Some(-12849));
-            assert_eq!(bst.remove(18), Some(1994));
-            assert_eq!(bst.remove(22), Some(28499));
-            assert_eq!(bst.remove(23), Some(28499));
-            assert_eq!(bst.remove(24), Some(28499));
-            assert_eq!(bst.remove(25), Some(28499));
-            assert_eq!(bst.remove(26), Some(28499));
-            assert_eq!(bst.remove(27), Some(28499));
-            assert_eq!(bst.remove(28), Some(28499));
-            assert_eq!(bst.remove(29), Some(28499));
-            assert_eq!(bst.remove(14), Some(28499));
-            assert_eq!(bst.remove(30), Some(28499));
-            assert_eq!(bst.remove(31), Some(28499));
-            assert_eq!(bst.remove(32), Some(28499));
+            assert_eq!(bst.remove(&1), Some(28499));
+            assert_eq!(bst.remove(&6), Some(28499));
+            assert_eq!(bst.remove(&13), Some(2344));
+            assert_eq!(bst.remove(&12), Some(28499));
+            assert_eq!(bst.remove(&19), Some(-12849));
+            assert_eq!(bst.remove(&18), Some(1994));
+            assert_eq!(bst.remove(&22), Some(28499));
+            assert_eq!(bst.remove(&23), Some(28499));
+            assert_eq!(bst.remove(&24), Some(28499));
+            assert_eq!(bst.remove(&25), Some(28499));
+            assert_eq!(bst.remove(&26), Some(28499));
+            assert_eq!(bst.remove(&27), Some(28499));
+            assert_eq!(bst.remove(&28), Some(28499));
+            assert_eq!(bst.remove(&29), Some(28499));
+            assert_eq!(bst.remove(&14), Some(28499));
+            assert_eq!(bst.remove(&30), Some(28499));
+            assert_eq!(bst.remove(&31), Some(28499));
+            assert_eq!(bst.remove(&32), Some(28499));
+            assert_eq!(bst.check_invariants(), Ok(()));
         }
     }
+
+    #[test]
+    fn broken_invariants() {
+        let leaf = |key: i32| {
+            Some(Box::new(BTNode::new(
+                vec![key],
+                vec![key],
+                vec![None, None],
+            )))
+        };
+        let mut bst = BTree::new(0, 3);
+        for k in 1..4 {
+            bst.insert(k, k);
+        }
+        // [2] over [1] and [3]
+        assert_eq!(bst.check_invariants(), Ok(()));
+
+        bst.size += 1;
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::Size {
+                stored: 4,
+                actual: 3
+            })
+        );
+        bst.size -= 1;
+
+        let root = bst.root.as_mut().unwrap();
+        root.children[1] = Some(Box::new(BTNode::new(
+            vec![4],
+            vec![4],
+            vec![leaf(3), leaf(5)],
+        )));
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::LeafDepth {
+                expected: 1,
+                actual: 2
+            })
+        );
+
+        let root = bst.root.as_mut().unwrap();
+        root.children[1] = Some(Box::new(BTNode::new(vec![], vec![], vec![None])));
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::KeyCount {
+                depth: 1,
+                count: 0,
+                min: 1,
+                max: 2
+            })
+        );
+
+        let root = bst.root.as_mut().unwrap();
+        root.children[1] = leaf(3);
+        root.children.push(None);
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::ChildCount {
+                depth: 0,
+                keys: 1,
+                children: 3
+            })
+        );
+    }
 }
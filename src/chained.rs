@@ -0,0 +1,274 @@
+//! Separate chaining table for `HashMap`
+//!
+//! Every bucket heads a singly linked list of entries. Entries live in one
+//! arena of slots linked by index, and removed slots are kept on a free list
+//! for reuse, so nothing moves until the table grows. The table grows once
+//! there are more entries than buckets.
+
+use crate::hashmap::{sealed::Sealed, table_size, HashMapDrain, Slot, Table};
+use crate::stats::HashMapStats;
+use crate::{HashMap, RandomState};
+use core::mem::replace;
+
+/// end of a chain or of the free list
+const NIL: usize = usize::MAX;
+
+/// `HashMap` resolving collisions by separate chaining
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{Chained, ChainedHashMap, HashMap};
+///
+/// let mut map: ChainedHashMap<&str, i32> = HashMap::with_table(Chained::default());
+/// map.insert("a", 1);
+/// assert_eq!(map["a"], 1);
+/// ```
+pub type ChainedHashMap<K, V, S = RandomState> = HashMap<K, V, S, Chained<K, V>>;
+
+/// Separate chaining, one linked list of entries per bucket
+#[derive(Debug)]
+pub struct Chained<K, V> {
+    /// arena of entries, holes are on the free list
+    slots: Vec<Slot<K, V>>,
+    /// next entry in the same chain, or next hole on the free list
+    next: Vec<usize>,
+    /// first entry of every bucket
+    heads: Vec<usize>,
+    /// first hole in the arena
+    free: usize,
+    len: usize,
+}
+
+impl<K, V> Default for Chained<K, V> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            next: Vec::new(),
+            heads: Vec::new(),
+            free: NIL,
+            len: 0,
+        }
+    }
+}
+
+impl<K, V> Chained<K, V> {
+    /// bucket of `hash`
+    fn bucket(&self, hash: u64) -> usize {
+        (hash % self.heads.len() as u64) as usize
+    }
+
+    /// push an entry known to be absent at the front of its chain, return its
+    /// slot index
+    fn insert_new(&mut self, hash: u64, k: K, v: V) -> usize {
+        let bucket = self.bucket(hash);
+        let idx = match self.free {
+            NIL => {
+                self.slots.push(Slot::Full(hash, k, v));
+                self.next.push(self.heads[bucket]);
+                self.slots.len() - 1
+            }
+            idx => {
+                self.free = replace(&mut self.next[idx], self.heads[bucket]);
+                self.slots[idx] = Slot::Full(hash, k, v);
+                idx
+            }
+        };
+        self.heads[bucket] = idx;
+        idx
+    }
+
+    /// rebuild the chains over `buckets` buckets, closing the holes
+    fn rehash(&mut self, buckets: usize) {
+        self.heads = vec![NIL; buckets];
+        let old_slots = replace(&mut self.slots, Vec::with_capacity(buckets));
+        self.next.clear();
+        self.free = NIL;
+        for slot in old_slots {
+            if let Slot::Full(hash, k, v) = slot {
+                self.insert_new(hash, k, v);
+            }
+        }
+    }
+}
+
+impl<K, V> Sealed for Chained<K, V> {}
+
+impl<K, V> Table<K, V> for Chained<K, V> {
+    fn with_capacity(capacity: usize) -> Self {
+        let mut table = Self::default();
+        if capacity > 0 {
+            table.rehash(table_size(capacity));
+        }
+        table
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        self.heads.len()
+    }
+
+    fn slots(&self) -> &[Slot<K, V>] {
+        &self.slots
+    }
+
+    fn slots_mut(&mut self) -> &mut [Slot<K, V>] {
+        &mut self.slots
+    }
+
+    fn into_slots(self) -> Vec<Slot<K, V>> {
+        self.slots
+    }
+
+    fn find(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        if self.heads.is_empty() {
+            return None;
+        }
+        let mut idx = self.heads[self.bucket(hash)];
+        while idx != NIL {
+            match &self.slots[idx] {
+                Slot::Full(h, key, _) if *h == hash && eq(key) => return Some(idx),
+                _ => idx = self.next[idx],
+            }
+        }
+        None
+    }
+
+    fn insert(&mut self, hash: u64, k: K, v: V) -> usize {
+        // an empty arena only holds holes, left by removals or a drain
+        if self.len == 0 {
+            self.slots.clear();
+            self.next.clear();
+            self.free = NIL;
+        }
+        if self.len + 1 > self.heads.len() {
+            self.rehash(table_size(self.heads.len() * 2 + 1));
+        }
+        self.len += 1;
+        self.insert_new(hash, k, v)
+    }
+
+    fn remove(&mut self, idx: usize) -> (K, V) {
+        let bucket = self.bucket(self.slots[idx].hash());
+        if self.heads[bucket] == idx {
+            self.heads[bucket] = self.next[idx];
+        } else {
+            let mut prev = self.heads[bucket];
+            while self.next[prev] != idx {
+                prev = self.next[prev];
+            }
+            self.next[prev] = self.next[idx];
+        }
+        self.next[idx] = replace(&mut self.free, idx);
+        self.len -= 1;
+        self.slots[idx].take(Slot::Empty)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for idx in 0..self.slots.len() {
+            if let Slot::Full(_, k, v) = &mut self.slots[idx] {
+                if !f(k, v) {
+                    self.remove(idx);
+                }
+            }
+        }
+    }
+
+    fn drain(&mut self) -> HashMapDrain<'_, K, V> {
+        let len = replace(&mut self.len, 0);
+        self.heads.iter_mut().for_each(|head| *head = NIL);
+        self.free = NIL;
+        HashMapDrain {
+            slots: self.slots.iter_mut(),
+            len,
+        }
+    }
+
+    /// the probe distance of an entry is its place in its chain, and every
+    /// non-empty chain is a cluster
+    fn stats(&self) -> HashMapStats {
+        let (mut distances, mut chains) = (Vec::with_capacity(self.len), Vec::new());
+        for &head in &self.heads {
+            let mut idx = head;
+            let mut length = 0;
+            while idx != NIL {
+                distances.push(length);
+                length += 1;
+                idx = self.next[idx];
+            }
+            if length > 0 {
+                chains.push(length);
+            }
+        }
+        HashMapStats::new(self.len, self.heads.len(), 0, distances, chains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Colliding;
+
+    #[test]
+    fn chained_grow_and_remove() {
+        let mut map = HashMap::with_table(Chained::default());
+        for i in 0..5000 {
+            assert_eq!(map.insert(i, -i), None);
+            assert!(map.len() <= map.table.heads.len());
+        }
+        assert_eq!(map.insert(42, 42), Some(-42));
+        for i in (0..5000).step_by(2) {
+            assert_eq!(map.remove(&i), if i == 42 { Some(42) } else { Some(-i) });
+        }
+        assert_eq!(map.len(), 2500);
+        for i in 0..5000 {
+            let expected = if i % 2 == 0 { None } else { Some(&-i) };
+            assert_eq!(map.get(&i), expected);
+        }
+
+        // holes are reused before the arena grows
+        let arena = map.table.slots.len();
+        for i in (0..2000).step_by(2) {
+            map.insert(i, i);
+        }
+        assert_eq!(map.table.slots.len(), arena);
+    }
+
+    #[test]
+    fn chained_colliding() {
+        let mut map = HashMap::with_table(Chained::default());
+        for i in 0..300 {
+            map.insert(Colliding(i), i);
+        }
+        // unlink from the middle, the head and the tail of the chains
+        for i in (0..300).filter(|i| i % 7 == 0) {
+            assert_eq!(map.remove(&Colliding(i)), Some(i));
+        }
+        for i in 0..300 {
+            let expected = if i % 7 == 0 { None } else { Some(&i) };
+            assert_eq!(map.get(&Colliding(i)), expected);
+        }
+        map.retain(|k, _| k.0 % 2 == 0);
+        assert_eq!(
+            map.len(),
+            (0..300).filter(|i| i % 7 != 0 && i % 2 == 0).count()
+        );
+
+        let stats = map.stats();
+        assert_eq!(stats.probe_histogram.iter().sum::<usize>(), map.len());
+        // all keys share one of three chains
+        assert!(stats.clusters() <= 3);
+        assert_eq!(stats.max_probe_distance() + 1, stats.max_cluster_size());
+
+        let len = map.len();
+        assert_eq!(map.drain().count(), len);
+        assert_eq!(map.get(&Colliding(2)), None);
+        // the drained arena is dropped on the next insert
+        map.insert(Colliding(2), 2);
+        assert_eq!(map.table.slots.len(), 1);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(&Colliding(2), &2)]);
+    }
+}
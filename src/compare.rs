@@ -0,0 +1,109 @@
+//! Comparators ordering the elements of the heaps
+//!
+//! A heap keeps the greatest element by its comparator on top, so the natural
+//! order gives a max-heap and the reversed one a min-heap. Any
+//! `Fn(&T, &T) -> Ordering` closure is a comparator, and [`ByKey`] orders by
+//! a key taken from each element, which spares wrapping every element in
+//! `Reverse` or a newtype.
+
+use std::cmp::Ordering;
+
+/// A total order on `T`
+pub trait Compare<T: ?Sized> {
+    /// Compares `a` to `b`.
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+
+    /// `a` is less than `b`
+    fn lt(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) == Ordering::Less
+    }
+
+    /// `a` is less than or equal to `b`
+    fn le(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) != Ordering::Greater
+    }
+
+    /// `a` is greater than `b`
+    fn gt(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) == Ordering::Greater
+    }
+
+    /// `a` is greater than or equal to `b`
+    fn ge(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) != Ordering::Less
+    }
+}
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// The natural order of `Ord`, making a max-heap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaxComparator;
+
+impl<T: Ord + ?Sized> Compare<T> for MaxComparator {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// The reversed order of `Ord`, making a min-heap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MinComparator;
+
+impl<T: Ord + ?Sized> Compare<T> for MinComparator {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// The natural order of a key taken from each element
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{BinaryHeap, ByKey};
+/// use std::cmp::Reverse;
+///
+/// struct Job {
+///     name: &'static str,
+///     deadline: u32,
+/// }
+///
+/// // earliest deadline first
+/// let mut jobs = BinaryHeap::with_comparator(ByKey(|job: &Job| Reverse(job.deadline)));
+/// jobs.push(Job { name: "report", deadline: 5 });
+/// jobs.push(Job { name: "deploy", deadline: 2 });
+/// jobs.push(Job { name: "review", deadline: 9 });
+/// assert_eq!(jobs.pop().map(|job| job.name), Some("deploy"));
+/// assert_eq!(jobs.pop().map(|job| job.name), Some("report"));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByKey<F>(pub F);
+
+impl<T, K: Ord, F: Fn(&T) -> K> Compare<T> for ByKey<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparators() {
+        assert_eq!(MaxComparator.compare(&1, &2), Ordering::Less);
+        assert_eq!(MinComparator.compare(&1, &2), Ordering::Greater);
+        assert!(MinComparator.gt(&1, &2) && MinComparator.ge(&1, &1));
+        assert!(MaxComparator.lt("a", "b") && MaxComparator.le("b", "b"));
+
+        let by_len = ByKey(|s: &&str| s.len());
+        assert_eq!(by_len.compare(&"abc", &"de"), Ordering::Greater);
+        let by_abs = |a: &i32, b: &i32| a.abs().cmp(&b.abs());
+        assert!(by_abs.lt(&1, &-2));
+    }
+}
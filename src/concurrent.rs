@@ -0,0 +1,411 @@
+//! A hash map shared between threads, split into independently locked shards
+//!
+//! The key space is split across a power of two of `HashMap`s, each behind
+//! its own `Mutex`, by the high bits of the key's hash, so threads working on
+//! different shards never wait on each other. Values are handed out by clone
+//! or through closures run under the shard lock, as no reference may outlive
+//! it.
+
+use crate::{Entry, HashMap, RandomState};
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// A `HashMap` split into shards behind their own locks, `Send` whenever
+/// keys, values and the hasher are `Send`, and `Sync` when the hasher, shared
+/// outside the locks, is `Sync` as well
+#[derive(Debug)]
+pub struct ConcurrentHashMap<K, V, S = RandomState> {
+    shards: Box<[Mutex<HashMap<K, V, S>>]>,
+    hash_builder: S,
+}
+
+impl<K: Eq + Hash, V> ConcurrentHashMap<K, V, RandomState> {
+    /// Creates an empty map with four shards per available CPU.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::ConcurrentHashMap;
+    ///
+    /// let map = ConcurrentHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get(&1), Some("a"));
+    /// ```
+    pub fn new() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(cpus * 4)
+    }
+
+    /// Creates an empty map with `shards` shards, rounded up to a power of
+    /// two.
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Clone> ConcurrentHashMap<K, V, S> {
+    /// Creates an empty map with `shards` shards, rounded up to a power of
+    /// two, hashing keys with `hash_builder`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::{ConcurrentHashMap, FxBuildHasher};
+    ///
+    /// let map = ConcurrentHashMap::with_shards_and_hasher(3, FxBuildHasher::default());
+    /// assert_eq!(map.shards(), 4);
+    /// map.insert("a", 1);
+    /// assert_eq!(map.get("a"), Some(1));
+    /// ```
+    pub fn with_shards_and_hasher(shards: usize, hash_builder: S) -> Self {
+        let shards = (0..shards.max(1).next_power_of_two())
+            .map(|_| Mutex::new(HashMap::with_hasher(hash_builder.clone())))
+            .collect();
+        Self {
+            shards,
+            hash_builder,
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> ConcurrentHashMap<K, V, S> {
+    /// Returns the number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// lock the shard of `k`, picked by the high bits of its hash as the
+    /// shard's own table uses the low ones
+    fn shard<Q>(&self, k: &Q) -> MutexGuard<'_, HashMap<K, V, S>>
+    where
+        Q: Hash + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(k);
+        let idx = (hash >> 32) as usize & (self.shards.len() - 1);
+        Self::lock(&self.shards[idx])
+    }
+
+    /// a closure that panicked under the lock poisons it, but leaves the
+    /// shard a valid map, so go on using it
+    fn lock(shard: &Mutex<HashMap<K, V, S>>) -> MutexGuard<'_, HashMap<K, V, S>> {
+        shard.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Inserts a key-value pair into the map, returning the old value if the
+    /// key was present.
+    pub fn insert(&self, k: K, v: V) -> Option<V> {
+        self.shard(&k).insert(k, v)
+    }
+
+    /// Removes a key from the map, returning its value if the key was
+    /// present.
+    pub fn remove<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(k).remove(k)
+    }
+
+    /// Returns a clone of the value corresponding to the key.
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shard(k).get(k).cloned()
+    }
+
+    /// Calls `f` on the value corresponding to the key, under the shard lock,
+    /// and returns its result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::ConcurrentHashMap;
+    ///
+    /// let map = ConcurrentHashMap::new();
+    /// map.insert("a", vec![1, 2, 3]);
+    /// assert_eq!(map.get_with("a", |v| v.len()), Some(3));
+    /// assert_eq!(map.get_with("b", |v| v.len()), None);
+    /// ```
+    pub fn get_with<Q, R>(&self, k: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(k).get(k).map(f)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(k).contains_key(k)
+    }
+
+    /// Calls `f` on the value corresponding to the key, if any, under the
+    /// shard lock, and returns its result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::ConcurrentHashMap;
+    ///
+    /// let map = ConcurrentHashMap::new();
+    /// map.insert("a", 1);
+    /// assert_eq!(map.update("a", |v| { *v += 1; *v }), Some(2));
+    /// assert_eq!(map.update("b", |v| { *v += 1; *v }), None);
+    /// ```
+    pub fn update<Q, R>(&self, k: &Q, f: impl FnOnce(&mut V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(k).get_mut(k).map(f)
+    }
+
+    /// Calls `f` on the key's entry, under the shard lock, and returns its
+    /// result. The whole read-modify-write is atomic with respect to other
+    /// threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::ConcurrentHashMap;
+    ///
+    /// let counts = ConcurrentHashMap::new();
+    /// for word in "a b a c a".split(' ') {
+    ///     counts.entry_with(word, |entry| *entry.or_insert(0) += 1);
+    /// }
+    /// assert_eq!(counts.get("a"), Some(3));
+    /// ```
+    pub fn entry_with<R>(&self, key: K, f: impl FnOnce(Entry<'_, K, V, S>) -> R) -> R {
+        f(self.shard(&key).entry(key))
+    }
+
+    /// Returns a clone of the value of the key, inserting the result of
+    /// `default` first if the key is absent.
+    pub fn get_or_insert_with(&self, key: K, default: impl FnOnce() -> V) -> V
+    where
+        V: Clone,
+    {
+        self.entry_with(key, |entry| entry.or_insert_with(default).clone())
+    }
+
+    /// Retains only the elements specified by the predicate, one shard at a
+    /// time.
+    pub fn retain(&self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for shard in self.shards.iter() {
+            Self::lock(shard).retain(&mut f);
+        }
+    }
+
+    /// Removes every entry, one shard at a time.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            Self::lock(shard).clear();
+        }
+    }
+
+    /// Returns the number of elements in the map, summed one shard at a
+    /// time, so only exact while no other thread modifies the map.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| Self::lock(shard).len())
+            .sum()
+    }
+
+    /// Returns `true` if the map contains no elements, with the same caveat
+    /// as [`len`](Self::len).
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| Self::lock(shard).is_empty())
+    }
+
+    /// Consumes the map, returning every key-value pair.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        Vec::from(self.shards)
+            .into_iter()
+            .flat_map(|shard| shard.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .collect()
+    }
+}
+
+impl<K: Eq + Hash, V> Default for ConcurrentHashMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+
+    const THREADS: usize = 8;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_and_sync() {
+        assert_send_sync::<ConcurrentHashMap<String, Vec<u8>>>();
+        assert_send_sync::<ConcurrentHashMap<u64, u64, crate::FxBuildHasher>>();
+    }
+
+    #[test]
+    fn shard_counts() {
+        assert_eq!(ConcurrentHashMap::<i32, i32>::with_shards(0).shards(), 1);
+        assert_eq!(ConcurrentHashMap::<i32, i32>::with_shards(5).shards(), 8);
+        assert!(ConcurrentHashMap::<i32, i32>::new()
+            .shards()
+            .is_power_of_two());
+
+        // every shard gets a share of the keys
+        let map = ConcurrentHashMap::with_shards(8);
+        for i in 0..1000 {
+            map.insert(i, i);
+        }
+        assert!(map
+            .shards
+            .iter()
+            .all(|shard| shard.lock().unwrap().len() > 50));
+    }
+
+    #[test]
+    fn disjoint_inserts() {
+        let map = Arc::new(ConcurrentHashMap::new());
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    for i in 0..2000 {
+                        assert_eq!(map.insert(t * 10_000 + i, t), None);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(map.len(), THREADS * 2000);
+        for t in 0..THREADS {
+            for i in 0..2000 {
+                assert_eq!(map.get(&(t * 10_000 + i)), Some(t));
+            }
+        }
+    }
+
+    #[test]
+    fn contended_counters() {
+        const KEYS: usize = 16;
+        const ROUNDS: usize = 4096;
+        let map = ConcurrentHashMap::with_shards(4);
+        let barrier = Barrier::new(THREADS);
+        thread::scope(|scope| {
+            for t in 0..THREADS {
+                let (map, barrier) = (&map, &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    for round in 0..ROUNDS {
+                        let key = (round + t) % KEYS;
+                        map.entry_with(key, |entry| *entry.or_insert(0) += 1);
+                    }
+                });
+            }
+        });
+
+        let total: usize = (0..KEYS).map(|k| map.get(&k).unwrap()).sum();
+        assert_eq!(total, THREADS * ROUNDS);
+        for k in 0..KEYS {
+            assert_eq!(map.get(&k), Some(THREADS * ROUNDS / KEYS));
+        }
+    }
+
+    #[test]
+    fn mixed_operations() {
+        let map = ConcurrentHashMap::with_shards(8);
+        let removed = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for t in 0..THREADS {
+                let (map, removed) = (&map, &removed);
+                scope.spawn(move || {
+                    // every thread owns the keys equal to `t` mod `THREADS`
+                    let keys = (0..4000).map(|i| i * THREADS + t);
+                    for key in keys.clone() {
+                        map.insert(key, key);
+                        assert_eq!(map.get(&key), Some(key));
+                    }
+                    for key in keys.clone().filter(|key| key % 3 == 0) {
+                        assert_eq!(map.remove(&key), Some(key));
+                        removed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    for key in keys {
+                        let doubled = map.update(&key, |v| {
+                            *v *= 2;
+                            *v
+                        });
+                        assert_eq!(doubled, (key % 3 != 0).then(|| key * 2));
+                        assert_eq!(map.contains_key(&key), key % 3 != 0);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), THREADS * 4000 - removed.load(Ordering::Relaxed));
+        let mut entries = map.into_vec();
+        entries.sort_unstable();
+        assert!(entries.iter().all(|&(k, v)| k % 3 != 0 && v == k * 2));
+    }
+
+    #[test]
+    fn readers_and_writers() {
+        let map = ConcurrentHashMap::new();
+        for i in 0..100 {
+            map.insert(i, vec![i; 4]);
+        }
+        thread::scope(|scope| {
+            for t in 0..THREADS {
+                let map = &map;
+                scope.spawn(move || {
+                    for round in 0..2000 {
+                        let key = (round * 7 + t) % 100;
+                        if t % 2 == 0 {
+                            map.insert(key, vec![key; 4]);
+                        } else {
+                            // values are replaced whole, never seen half-written
+                            let seen = map.get_with(&key, |v| v.iter().all(|&x| x == key));
+                            assert_eq!(seen, Some(true));
+                        }
+                    }
+                });
+            }
+        });
+
+        map.retain(|&k, _| k < 50);
+        assert_eq!(map.len(), 50);
+        assert_eq!(map.get_or_insert_with(200, || vec![0]), vec![0]);
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn poisoned_shard_stays_usable() {
+        let map = ConcurrentHashMap::with_shards(1);
+        map.insert(1, 1);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            map.update(&1, |_| panic!("closure panics under the lock"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.get(&1), Some(1));
+        map.insert(2, 2);
+        assert_eq!(map.len(), 2);
+    }
+}
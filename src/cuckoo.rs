@@ -0,0 +1,394 @@
+//! Cuckoo hashing table for `HashMap`
+//!
+//! The slots are split into two halves, and an entry may only sit in one
+//! place in each, picked by mixing its hash with one of two seeds, so a
+//! lookup reads at most two slots. An entry finding both taken kicks out one
+//! of the residents, which moves to its other place, and so on. A chain of
+//! kicks too long to end is cut by a stash of at most [`STASH`] entries
+//! after the two halves, scanned on every lookup. The table grows once it is
+//! half full.
+//!
+//! An insert finding the stash full first moves every entry to places picked
+//! with new seeds, growing the table if a few seeds in a row still overflow
+//! it. Entries whose hashes are all equal share both places under any seed,
+//! so past a few of them the stash has to take the rest: the table then
+//! lets the stash grow to twice its size before trying new seeds again.
+
+use crate::hashmap::{sealed::Sealed, table_size, HashMapDrain, Slot, Table};
+use crate::stats::{clusters, HashMapStats};
+use crate::{HashMap, RandomState};
+use core::mem::{replace, take};
+
+/// kicks tried before an entry goes to the stash
+const MAX_KICKS: usize = 64;
+
+/// entries the stash holds before the table picks new places
+const STASH: usize = 8;
+
+/// seeds tried by a rebuild before it lets the stash overflow
+const MAX_RESEEDS: usize = 6;
+
+/// murmur3 finalizer, so that every bit of the hash moves both places
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^ (x >> 33)
+}
+
+/// `HashMap` resolving collisions by cuckoo hashing
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{Cuckoo, CuckooHashMap, HashMap};
+///
+/// let mut map: CuckooHashMap<&str, i32> = HashMap::with_table(Cuckoo::default());
+/// map.insert("a", 1);
+/// assert_eq!(map["a"], 1);
+/// ```
+pub type CuckooHashMap<K, V, S = RandomState> = HashMap<K, V, S, Cuckoo<K, V>>;
+
+/// Cuckoo hashing with two choices and a stash
+#[derive(Debug)]
+pub struct Cuckoo<K, V> {
+    /// two halves of `half` slots, then the stash
+    slots: Vec<Slot<K, V>>,
+    half: usize,
+    len: usize,
+    /// mixed into the hash to pick the place in each half
+    seeds: [u64; 2],
+    /// stash length from which an insert rebuilds first, `STASH` unless
+    /// entries sharing a hash overflowed it
+    stash_limit: usize,
+}
+
+impl<K, V> Default for Cuckoo<K, V> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            half: 0,
+            len: 0,
+            seeds: [0x9e37_79b9_7f4a_7c15, 0xbf58_476d_1ce4_e5b9],
+            stash_limit: STASH,
+        }
+    }
+}
+
+impl<K, V> Cuckoo<K, V> {
+    /// the two places of `hash`, one in each half
+    fn places(&self, hash: u64) -> [usize; 2] {
+        let half = self.half as u64;
+        let [first, second] = self.seeds;
+        [
+            (mix(hash ^ first) % half) as usize,
+            (mix(hash ^ second) % half) as usize + self.half,
+        ]
+    }
+
+    /// number of entries in the stash
+    fn stash_len(&self) -> usize {
+        self.slots.len() - self.half * 2
+    }
+
+    /// the place of `hash` other than `idx`
+    fn other_place(&self, hash: u64, idx: usize) -> usize {
+        match self.places(hash) {
+            [first, second] if first == idx => second,
+            [first, _] => first,
+        }
+    }
+
+    /// place an entry known to be absent, kicking residents out to their
+    /// other place, return the slot index it ends up in
+    fn insert_new(&mut self, hash: u64, k: K, v: V) -> usize {
+        let [first, second] = self.places(hash);
+        let mut idx = match self.slots[first].is_full() && !self.slots[second].is_full() {
+            true => second,
+            false => first,
+        };
+
+        let mut entry = Slot::Full(hash, k, v);
+        // where the new entry sits, and whether `entry` is the new one
+        let (mut landed, mut carrying) = (idx, true);
+        for _ in 0..MAX_KICKS {
+            entry = replace(&mut self.slots[idx], entry);
+            if carrying {
+                landed = idx;
+                carrying = false;
+            } else if idx == landed {
+                carrying = true;
+            }
+            match &entry {
+                Slot::Full(hash, ..) => idx = self.other_place(*hash, idx),
+                _ => return landed,
+            }
+        }
+
+        self.slots.push(entry);
+        match carrying {
+            true => self.slots.len() - 1,
+            false => landed,
+        }
+    }
+
+    /// move every entry into a new table of two halves of at least `half`
+    /// slots, with new seeds until the stash has room for one more entry
+    fn rebuild(&mut self, mut half: usize) {
+        let mut entries = full_entries(take(&mut self.slots));
+        for reseeds in 0.. {
+            let mut slots = Vec::with_capacity(half * 2);
+            slots.resize_with(half * 2, || Slot::Empty);
+            self.slots = slots;
+            self.half = half;
+
+            let give_up = reseeds == MAX_RESEEDS;
+            let mut rest = entries.into_iter();
+            let mut overflow = false;
+            for (hash, k, v) in rest.by_ref() {
+                self.insert_new(hash, k, v);
+                if !give_up && self.stash_len() >= STASH {
+                    overflow = true;
+                    break;
+                }
+            }
+            if !overflow {
+                self.stash_limit = STASH.max(2 * self.stash_len());
+                return;
+            }
+
+            entries = full_entries(take(&mut self.slots));
+            entries.extend(rest);
+            self.seeds = [mix(self.seeds[0] ^ 1), mix(self.seeds[1] ^ 2)];
+            // unlucky seeds are likely on a table at least a quarter full only
+            if reseeds % 2 == 1 && entries.len() * 2 >= half {
+                half = table_size(half * 2 + 1);
+            }
+        }
+    }
+}
+
+/// every entry of `slots`
+fn full_entries<K, V>(slots: Vec<Slot<K, V>>) -> Vec<(u64, K, V)> {
+    slots
+        .into_iter()
+        .filter_map(|slot| match slot {
+            Slot::Full(hash, k, v) => Some((hash, k, v)),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<K, V> Sealed for Cuckoo<K, V> {}
+
+impl<K, V> Table<K, V> for Cuckoo<K, V> {
+    fn with_capacity(capacity: usize) -> Self {
+        let mut table = Self::default();
+        if capacity > 0 {
+            table.rebuild(table_size(capacity));
+        }
+        table
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        self.half
+    }
+
+    fn slots(&self) -> &[Slot<K, V>] {
+        &self.slots
+    }
+
+    fn slots_mut(&mut self) -> &mut [Slot<K, V>] {
+        &mut self.slots
+    }
+
+    fn into_slots(self) -> Vec<Slot<K, V>> {
+        self.slots
+    }
+
+    fn find(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        if self.half == 0 {
+            return None;
+        }
+        let stash = self.half * 2..self.slots.len();
+        self.places(hash)
+            .iter()
+            .copied()
+            .chain(stash)
+            .find(|&idx| matches!(&self.slots[idx], Slot::Full(h, key, _) if *h == hash && eq(key)))
+    }
+
+    fn insert(&mut self, hash: u64, k: K, v: V) -> usize {
+        // an empty stash may still hold the slots emptied by a drain
+        if self.len == 0 {
+            self.slots.truncate(self.half * 2);
+            self.stash_limit = STASH;
+        }
+        if self.len + 1 > self.half {
+            self.rebuild(table_size(self.half * 2 + 1));
+        } else if self.stash_len() >= self.stash_limit {
+            self.rebuild(self.half);
+        }
+        self.len += 1;
+        self.insert_new(hash, k, v)
+    }
+
+    fn remove(&mut self, idx: usize) -> (K, V) {
+        self.len -= 1;
+        match idx < self.half * 2 {
+            true => self.slots[idx].take(Slot::Empty),
+            false => match self.slots.swap_remove(idx) {
+                Slot::Full(_, k, v) => (k, v),
+                _ => unreachable!("slot {} is not full", idx),
+            },
+        }
+    }
+
+    /// walk backwards, so that stash entries swapped into a removed one's
+    /// place have been visited already
+    fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for idx in (0..self.slots.len()).rev() {
+            if let Slot::Full(_, k, v) = &mut self.slots[idx] {
+                if !f(k, v) {
+                    self.remove(idx);
+                }
+            }
+        }
+    }
+
+    fn drain(&mut self) -> HashMapDrain<'_, K, V> {
+        let len = replace(&mut self.len, 0);
+        HashMapDrain {
+            slots: self.slots.iter_mut(),
+            len,
+        }
+    }
+
+    /// the probe distance of an entry is 0 in its first place, 1 in its
+    /// second and 2 plus its position in the stash, clusters span the halves
+    fn stats(&self) -> HashMapStats {
+        let tables = self.half * 2;
+        let distances = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| match slot {
+                Slot::Full(hash, ..) if idx < tables => Some(
+                    self.places(*hash)
+                        .iter()
+                        .position(|&place| place == idx)
+                        .unwrap(),
+                ),
+                Slot::Full(..) => Some(2 + idx - tables),
+                _ => None,
+            });
+        HashMapStats::new(
+            self.len,
+            self.slots.len(),
+            0,
+            distances,
+            clusters(tables, |idx| self.slots[idx].is_full()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Colliding;
+    use crate::FxBuildHasher;
+
+    /// every entry outside the stash sits in one of its two places
+    fn check_places<K, V>(table: &Cuckoo<K, V>) {
+        for (idx, slot) in table.slots[..table.half * 2].iter().enumerate() {
+            if slot.is_full() {
+                assert!(table.places(slot.hash()).contains(&idx));
+            }
+        }
+        assert_eq!(
+            table.slots.iter().filter(|slot| slot.is_full()).count(),
+            table.len
+        );
+    }
+
+    #[test]
+    fn cuckoo_grow_and_remove() {
+        let mut map = HashMap::with_table(Cuckoo::default());
+        for i in 0..5000 {
+            assert_eq!(map.insert(i, -i), None);
+            assert!(map.len() <= map.capacity());
+        }
+        check_places(&map.table);
+        for i in (0..5000).step_by(2) {
+            assert_eq!(map.remove(&i), Some(-i));
+        }
+        for i in 0..5000 {
+            let expected = if i % 2 == 0 { None } else { Some(&-i) };
+            assert_eq!(map.get(&i), expected);
+        }
+        map.retain(|&k, _| k % 3 == 0);
+        check_places(&map.table);
+        for i in 0..5000 {
+            assert_eq!(map.contains_key(&i), i % 6 == 3);
+        }
+
+        let stats = map.stats();
+        assert_eq!(stats.probe_histogram.iter().sum::<usize>(), map.len());
+        assert!(stats.load_factor() <= 0.5);
+    }
+
+    #[test]
+    fn cuckoo_stash_bounded() {
+        // multiplicative hashing of regular keys must not crowd the stash
+        for &shift in [0, 32].iter() {
+            let mut map =
+                HashMap::with_table_and_hasher(Cuckoo::default(), FxBuildHasher::default());
+            for i in 0..50_000u64 {
+                map.insert(i << shift, i);
+                assert!(map.table.stash_len() <= STASH);
+            }
+            check_places(&map.table);
+            assert!(map.stats().max_probe_distance() < 2 + STASH);
+            assert!((0..50_000u64).all(|i| map.get(&(i << shift)) == Some(&i)));
+            assert!(map.stats().load_factor() > 0.1);
+        }
+    }
+
+    #[test]
+    fn cuckoo_stash() {
+        // keys sharing a hash share both places under any seeds, all but six
+        // are stashed, without the table growing for nothing
+        let mut map = HashMap::with_table(Cuckoo::default());
+        for i in 0..300 {
+            *map.entry(Colliding(i)).or_insert(0) += i;
+        }
+        check_places(&map.table);
+        let stash = map.table.stash_len();
+        assert_eq!(stash, 300 - 6);
+        assert!(map.capacity() <= 4 * 300);
+        assert_eq!(map.stats().max_probe_distance(), 2 + stash - 1);
+        for i in (0..300).filter(|i| i % 7 == 0) {
+            assert_eq!(map.remove(&Colliding(i)), Some(i));
+        }
+        map.retain(|k, _| k.0 % 2 == 0);
+        check_places(&map.table);
+        for i in 0..300 {
+            let expected = if i % 7 != 0 && i % 2 == 0 {
+                Some(&i)
+            } else {
+                None
+            };
+            assert_eq!(map.get(&Colliding(i)), expected);
+        }
+
+        let len = map.len();
+        assert_eq!(map.drain().count(), len);
+        map.insert(Colliding(0), 0);
+        assert_eq!(map.table.slots.len(), map.table.half * 2);
+    }
+}
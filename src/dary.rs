@@ -0,0 +1,283 @@
+//! d-ary heap, a priority queue whose nodes have `D` children
+//!
+//! The children of index `i` are `D * i + 1` to `D * i + D`. A larger arity
+//! makes the tree shallower, so pushing and sifting up cross fewer levels,
+//! while sifting down compares more children per level, which sit next to
+//! each other in memory. Arities of 4 or 8 often beat the binary heap on
+//! large queues.
+
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use crate::InvariantError;
+
+/// A priority queue implemented with a d-ary heap, a max-heap by default or
+/// ordered by any comparator `C`
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::DaryHeap;
+///
+/// let mut heap = DaryHeap::<_, 4>::new();
+/// heap.push(1);
+/// heap.push(5);
+/// heap.push(2);
+///
+/// assert_eq!(heap.peek(), Some(&5));
+/// assert_eq!(heap.pop(), Some(5));
+/// assert_eq!(heap.into_sorted_vec(), [1, 2]);
+/// ```
+///
+/// # Panics
+///
+/// Creating a heap panics if `D` is less than 2.
+#[derive(Debug, Clone)]
+pub struct DaryHeap<T, const D: usize, C = MaxComparator> {
+    data: Vec<T>,
+    cmp: C,
+}
+
+impl<T: Ord, const D: usize> DaryHeap<T, D> {
+    /// Creates an empty `DaryHeap` as a max-heap.
+    pub fn new() -> Self {
+        Self::with_comparator(MaxComparator)
+    }
+
+    /// Creates an empty `DaryHeap` as a max-heap, with a specific capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_comparator(capacity, MaxComparator)
+    }
+}
+
+impl<T: Ord, const D: usize> DaryHeap<T, D, MinComparator> {
+    /// Creates an empty `DaryHeap` as a min-heap.
+    pub fn new_min() -> Self {
+        Self::with_comparator(MinComparator)
+    }
+}
+
+impl<T, const D: usize, C: Compare<T> + Default> Default for DaryHeap<T, D, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord, const D: usize> From<Vec<T>> for DaryHeap<T, D> {
+    /// Converts a `Vec<T>` into a `DaryHeap`, in-place and in *O*(*n*).
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_vec_with_comparator(vec, MaxComparator)
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> DaryHeap<T, D, C> {
+    /// Creates an empty `DaryHeap` ordered by `cmp`, the greatest item by
+    /// `cmp` being on top.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_capacity_and_comparator(0, cmp)
+    }
+
+    /// Creates an empty `DaryHeap` ordered by `cmp`, with a specific
+    /// capacity.
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        assert!(D >= 2, "a d-ary heap needs at least 2 children per node");
+        Self {
+            data: Vec::with_capacity(capacity),
+            cmp,
+        }
+    }
+
+    /// Converts a `Vec<T>` into a `DaryHeap` ordered by `cmp`, in-place and
+    /// in *O*(*n*).
+    pub fn from_vec_with_comparator(vec: Vec<T>, cmp: C) -> Self {
+        let mut heap = Self::with_comparator(cmp);
+        heap.data = vec;
+        heapify::<D, _, _>(&mut heap.data, &heap.cmp);
+        heap
+    }
+
+    /// Returns the number of items in the heap.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Checks if the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the greatest item in the heap, or `None` if it is empty.
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    /// Pushes an item onto the heap, in *O*(log_D(*n*)).
+    pub fn push(&mut self, item: T) {
+        self.data.push(item);
+        let mut pos = self.data.len() - 1;
+        while pos > 0 {
+            let parent = (pos - 1) / D;
+            if self.cmp.ge(&self.data[parent], &self.data[pos]) {
+                break;
+            }
+            self.data.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    /// Removes the greatest item from the heap and returns it, or `None` if
+    /// it is empty, in *O*(D log_D(*n*)).
+    pub fn pop(&mut self) -> Option<T> {
+        let last = self.data.len().checked_sub(1)?;
+        self.data.swap(0, last);
+        let item = self.data.pop();
+        sift_down::<D, _, _>(&mut self.data, 0, &self.cmp);
+        item
+    }
+
+    /// Consumes the heap and returns its items in ascending order by the
+    /// comparator, sorting in-place.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut end = self.data.len();
+        while end > 1 {
+            end -= 1;
+            self.data.swap(0, end);
+            sift_down::<D, _, _>(&mut self.data[..end], 0, &self.cmp);
+        }
+        self.data
+    }
+
+    /// Checks heap order
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        for pos in 1..self.data.len() {
+            if self.cmp.gt(&self.data[pos], &self.data[(pos - 1) / D]) {
+                let mut depth = 0;
+                let mut up = pos;
+                while up > 0 {
+                    up = (up - 1) / D;
+                    depth += 1;
+                }
+                return Err(InvariantError::HeapOrder { depth });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Take an element at `pos` and move it all the way down the d-ary heap
+/// `heap`, swapping it with its greatest child
+fn sift_down<const D: usize, T, C: Compare<T>>(heap: &mut [T], mut pos: usize, cmp: &C) {
+    let end = heap.len();
+    loop {
+        let first = D * pos + 1;
+        if first >= end {
+            break;
+        }
+        let mut child = first;
+        for other in first + 1..(first + D).min(end) {
+            if cmp.gt(&heap[other], &heap[child]) {
+                child = other;
+            }
+        }
+        if cmp.ge(&heap[pos], &heap[child]) {
+            break;
+        }
+        heap.swap(pos, child);
+        pos = child;
+    }
+}
+
+/// Floyd's bottom-up build of a d-ary heap
+fn heapify<const D: usize, T, C: Compare<T>>(heap: &mut [T], cmp: &C) {
+    if heap.len() < 2 {
+        return;
+    }
+    for pos in (0..=(heap.len() - 2) / D).rev() {
+        sift_down::<D, _, _>(heap, pos, cmp);
+    }
+}
+
+/// heap sort of `seq` by `cmp` on a d-ary heap
+pub(crate) fn dary_heap_sort_by<const D: usize, T, C: Compare<T>>(seq: &mut [T], cmp: &C) {
+    assert!(D >= 2, "a d-ary heap needs at least 2 children per node");
+    heapify::<D, _, _>(seq, cmp);
+    for end in (1..seq.len()).rev() {
+        seq.swap(0, end);
+        sift_down::<D, _, _>(&mut seq[..end], 0, cmp);
+    }
+}
+
+/// Heap sort algorithm on a d-ary heap
+/// half-open inclusive
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::dary_heap_sort;
+///
+/// let mut array = [5, 1, 4, 2, 3, 9, 0];
+/// dary_heap_sort::<4, _>(&mut array, 1, 6);
+/// assert_eq!(array, [5, 1, 2, 3, 4, 9, 0]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `D` is less than 2.
+pub fn dary_heap_sort<const D: usize, T: Ord>(seq: &mut [T], lo: usize, hi: usize) {
+    dary_heap_sort_by::<D, _, _>(&mut seq[lo..hi], &MaxComparator);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pseudo_random;
+
+    fn check_arity<const D: usize>() {
+        let items = pseudo_random(500, 1000);
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+
+        let mut heap = DaryHeap::<_, D>::new();
+        for &item in items.iter() {
+            heap.push(item);
+        }
+        assert_eq!(heap.check_invariants(), Ok(()));
+        let popped: Vec<_> = std::iter::from_fn(|| heap.pop()).collect();
+        assert!(popped.iter().rev().eq(sorted.iter()));
+
+        let heap = DaryHeap::<_, D, _>::from_vec_with_comparator(items.clone(), MinComparator);
+        assert_eq!(heap.check_invariants(), Ok(()));
+        assert!(heap.into_sorted_vec().iter().rev().eq(sorted.iter()));
+
+        let mut array = items.clone();
+        dary_heap_sort::<D, _>(&mut array, 0, items.len());
+        assert_eq!(array, sorted);
+    }
+
+    #[test]
+    fn arities() {
+        check_arity::<2>();
+        check_arity::<3>();
+        check_arity::<4>();
+        check_arity::<8>();
+        check_arity::<16>();
+    }
+
+    #[test]
+    fn sort_ranges() {
+        for len in 0..20 {
+            let items = pseudo_random(len, 1000);
+            for lo in 0..=len {
+                let mut array = items.clone();
+                dary_heap_sort::<3, _>(&mut array, lo, len);
+                let mut expected = items.clone();
+                expected[lo..].sort_unstable();
+                assert_eq!(array, expected);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn unary_heap() {
+        DaryHeap::<i32, 1>::new();
+    }
+}
@@ -0,0 +1,511 @@
+//! Fibonacci heap with decrease-key through handles
+//!
+//! A lazy forest of heap-ordered trees. Pushing and melding only add roots
+//! to a doubly linked root list, in *O*(1), and popping links the roots of
+//! equal degree, the number of children, until no two share one. A node
+//! moved up above its parent is cut to the root list, and a parent losing a
+//! second child is cut too, which keeps a tree of degree `k` at least the
+//! `k + 2`th Fibonacci number in size. Nodes are shared with the handles
+//! given out by `push`, as `Weak` pointers, so a handle follows its item
+//! through melds and stops working once the item is popped.
+
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use crate::heap::MergeableHeap;
+use crate::InvariantError;
+use std::cell::{Ref, RefCell};
+use std::mem::{replace, take};
+use std::rc::{Rc, Weak};
+
+type Link<T> = Rc<RefCell<FibonacciNode<T>>>;
+
+#[derive(Debug)]
+struct FibonacciNode<T> {
+    item: T,
+    /// number of children
+    degree: usize,
+    /// lost a child since it became a child itself
+    marked: bool,
+    parent: Weak<RefCell<FibonacciNode<T>>>,
+    child: Option<Link<T>>,
+    /// siblings, or the other roots for a root
+    next: Option<Link<T>>,
+    prev: Weak<RefCell<FibonacciNode<T>>>,
+}
+
+/// Handle to an item of a [`FibonacciHeap`], valid until the item is popped
+#[derive(Debug)]
+pub struct FibonacciHandle<T>(Weak<RefCell<FibonacciNode<T>>>);
+
+impl<T> Clone for FibonacciHandle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Fibonacci heap, a max-heap by default or ordered by any comparator `C`
+///
+/// It is a logic error to use a handle with a heap that does not hold its
+/// item.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{FibonacciHeap, MergeableHeap};
+///
+/// let mut heap = FibonacciHeap::new_min();
+/// let a = heap.push(10);
+/// heap.push(5);
+///
+/// let mut other = FibonacciHeap::new_min();
+/// let b = other.push(8);
+/// heap.meld(other);
+///
+/// assert_eq!(heap.decrease_key(&b, 1), Ok(8));
+/// assert_eq!(heap.pop(), Some(1));
+/// assert_eq!(heap.decrease_key(&a, 2), Ok(10));
+/// assert_eq!(heap.peek().as_deref(), Some(&2));
+/// assert_eq!(heap.len(), 2);
+/// ```
+///
+/// # Time complexity
+///
+/// | push, meld, peek | decrease_key | pop |
+/// |------------------|--------------|-----|
+/// | *O*(1)             | *O*(1)~        | *O*(log(*n*))~ |
+///
+/// Costs marked with ~ are amortized.
+#[derive(Debug)]
+pub struct FibonacciHeap<T, C = MaxComparator> {
+    /// first and last of the root list
+    head: Option<Link<T>>,
+    tail: Weak<RefCell<FibonacciNode<T>>>,
+    /// the greatest root
+    top: Option<Link<T>>,
+    len: usize,
+    cmp: C,
+}
+
+impl<T: Ord> FibonacciHeap<T> {
+    /// Creates an empty `FibonacciHeap` as a max-heap.
+    pub fn new() -> Self {
+        Self::with_comparator(MaxComparator)
+    }
+}
+
+impl<T: Ord> FibonacciHeap<T, MinComparator> {
+    /// Creates an empty `FibonacciHeap` as a min-heap.
+    pub fn new_min() -> Self {
+        Self::with_comparator(MinComparator)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for FibonacciHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> FibonacciHeap<T, C> {
+    /// Creates an empty `FibonacciHeap` ordered by `cmp`, the greatest item
+    /// by `cmp` being on top.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            head: None,
+            tail: Weak::new(),
+            top: None,
+            len: 0,
+            cmp,
+        }
+    }
+
+    /// Returns the number of items in the heap.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes an item onto the heap in *O*(1), returning its handle.
+    pub fn push(&mut self, item: T) -> FibonacciHandle<T> {
+        let node = Rc::new(RefCell::new(FibonacciNode {
+            item,
+            degree: 0,
+            marked: false,
+            parent: Weak::new(),
+            child: None,
+            next: None,
+            prev: Weak::new(),
+        }));
+        let handle = FibonacciHandle(Rc::downgrade(&node));
+        self.update_top(&node);
+        self.push_root(node);
+        self.len += 1;
+        handle
+    }
+
+    /// Returns `true` if the item of `handle` has not been popped.
+    pub fn contains(&self, handle: &FibonacciHandle<T>) -> bool {
+        handle.0.strong_count() > 0
+    }
+
+    /// Returns a clone of the item of `handle`, or `None` if it has been
+    /// popped.
+    pub fn get(&self, handle: &FibonacciHandle<T>) -> Option<T>
+    where
+        T: Clone,
+    {
+        handle.0.upgrade().map(|node| node.borrow().item.clone())
+    }
+
+    /// Replaces the item of `handle` by `item`, which must not be less than
+    /// it, moving it up in amortized *O*(1), and returns the old item. Gives
+    /// `item` back if it is less, or if the old item has been popped.
+    pub fn decrease_key(&mut self, handle: &FibonacciHandle<T>, item: T) -> Result<T, T> {
+        let node = match handle.0.upgrade() {
+            Some(node) => node,
+            None => return Err(item),
+        };
+        if self.cmp.lt(&item, &node.borrow().item) {
+            return Err(item);
+        }
+        let old = replace(&mut node.borrow_mut().item, item);
+        let parent = node.borrow().parent.upgrade();
+        if let Some(parent) = parent {
+            if self.cmp.gt(&node.borrow().item, &parent.borrow().item) {
+                self.cut(&node, &parent);
+                self.cascading_cut(parent);
+            }
+        }
+        self.update_top(&node);
+        Ok(old)
+    }
+
+    /// make `node` the top if it is greater than the current one
+    fn update_top(&mut self, node: &Link<T>) {
+        let greater = match &self.top {
+            Some(top) => self.cmp.gt(&node.borrow().item, &top.borrow().item),
+            None => true,
+        };
+        if greater {
+            self.top = Some(node.clone());
+        }
+    }
+
+    /// add a detached node at the front of the root list
+    fn push_root(&mut self, node: Link<T>) {
+        let next = self.head.take();
+        match &next {
+            Some(next) => next.borrow_mut().prev = Rc::downgrade(&node),
+            None => self.tail = Rc::downgrade(&node),
+        }
+        {
+            let mut n = node.borrow_mut();
+            n.parent = Weak::new();
+            n.marked = false;
+            n.prev = Weak::new();
+            n.next = next;
+        }
+        self.head = Some(node);
+    }
+
+    /// take a root out of the root list
+    fn unlink_root(&mut self, node: &Link<T>) {
+        let (prev, next) = {
+            let mut n = node.borrow_mut();
+            (take(&mut n.prev), n.next.take())
+        };
+        match &next {
+            Some(next) => next.borrow_mut().prev = prev.clone(),
+            None => self.tail = prev.clone(),
+        }
+        match prev.upgrade() {
+            Some(prev) => prev.borrow_mut().next = next,
+            None => self.head = next,
+        }
+    }
+
+    /// link two roots, the lesser becoming the first child of the greater
+    fn link(&self, a: Link<T>, b: Link<T>) -> Link<T> {
+        let (parent, child) = if self.cmp.lt(&a.borrow().item, &b.borrow().item) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        {
+            let mut p = parent.borrow_mut();
+            let mut c = child.borrow_mut();
+            c.parent = Rc::downgrade(&parent);
+            c.marked = false;
+            c.prev = Weak::new();
+            c.next = p.child.take();
+            if let Some(next) = &c.next {
+                next.borrow_mut().prev = Rc::downgrade(&child);
+            }
+            p.degree += 1;
+        }
+        parent.borrow_mut().child = Some(child);
+        parent
+    }
+
+    /// move `node` from the children of `parent` to the root list
+    fn cut(&mut self, node: &Link<T>, parent: &Link<T>) {
+        let (prev, next) = {
+            let mut n = node.borrow_mut();
+            (take(&mut n.prev), n.next.take())
+        };
+        if let Some(next) = &next {
+            next.borrow_mut().prev = prev.clone();
+        }
+        match prev.upgrade() {
+            Some(prev) => prev.borrow_mut().next = next,
+            None => parent.borrow_mut().child = next,
+        }
+        parent.borrow_mut().degree -= 1;
+        self.push_root(node.clone());
+    }
+
+    /// mark `node` for its first lost child, cut it for the second and go on
+    /// with its parent
+    fn cascading_cut(&mut self, mut node: Link<T>) {
+        loop {
+            let parent = node.borrow().parent.upgrade();
+            let parent = match parent {
+                Some(parent) => parent,
+                None => return,
+            };
+            if !node.borrow().marked {
+                node.borrow_mut().marked = true;
+                return;
+            }
+            self.cut(&node, &parent);
+            node = parent;
+        }
+    }
+
+    /// link roots of equal degree until every degree is unique, and find the
+    /// new top
+    fn consolidate(&mut self) {
+        let mut by_degree: Vec<Option<Link<T>>> = Vec::new();
+        let mut next = self.head.take();
+        self.tail = Weak::new();
+        while let Some(root) = next {
+            next = {
+                let mut r = root.borrow_mut();
+                r.prev = Weak::new();
+                r.next.take()
+            };
+            let mut tree = root;
+            loop {
+                let degree = tree.borrow().degree;
+                if by_degree.len() <= degree {
+                    by_degree.resize_with(degree + 1, || None);
+                }
+                match by_degree[degree].take() {
+                    Some(other) => tree = self.link(tree, other),
+                    None => {
+                        by_degree[degree] = Some(tree);
+                        break;
+                    }
+                }
+            }
+        }
+        self.top = None;
+        for tree in by_degree.into_iter().flatten() {
+            self.update_top(&tree);
+            self.push_root(tree);
+        }
+    }
+
+    /// Checks heap order, degrees, the back links and the length
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let mut count = 0;
+        // lists of siblings, with their parent and depth
+        let mut lists = vec![(self.head.clone(), None::<Link<T>>, 0)];
+        while let Some((head, parent, depth)) = lists.pop() {
+            let (mut prev, mut node, mut degree) = (None::<Link<T>>, head, 0);
+            while let Some(n) = node {
+                count += 1;
+                degree += 1;
+                let b = n.borrow();
+                if !back(&b.prev, &prev) || !back(&b.parent, &parent) {
+                    return Err(InvariantError::BackLink { depth });
+                }
+                let above = match &parent {
+                    Some(parent) => self.cmp.gt(&b.item, &parent.borrow().item),
+                    None => self
+                        .cmp
+                        .gt(&b.item, &self.top.as_ref().unwrap().borrow().item),
+                };
+                if above {
+                    return Err(InvariantError::HeapOrder { depth });
+                }
+                lists.push((b.child.clone(), Some(n.clone()), depth + 1));
+                node = b.next.clone();
+                drop(b);
+                prev = Some(n);
+            }
+            if parent.is_none() && !back(&self.tail, &prev) {
+                return Err(InvariantError::BackLink { depth });
+            }
+            if let Some(parent) = parent {
+                let stored = parent.borrow().degree;
+                if stored != degree {
+                    return Err(InvariantError::Size {
+                        stored,
+                        actual: degree,
+                    });
+                }
+            }
+        }
+        if count != self.len {
+            return Err(InvariantError::Size {
+                stored: self.len,
+                actual: count,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// `weak` points to `to`, or nowhere for `None`
+fn back<T>(weak: &Weak<T>, to: &Option<Rc<T>>) -> bool {
+    match to {
+        Some(to) => weak.ptr_eq(&Rc::downgrade(to)),
+        None => weak.upgrade().is_none(),
+    }
+}
+
+impl<T, C: Compare<T>> MergeableHeap<T> for FibonacciHeap<T, C> {
+    type Peek<'a>
+        = Ref<'a, T>
+    where
+        Self: 'a;
+
+    fn push(&mut self, item: T) {
+        FibonacciHeap::push(self, item);
+    }
+
+    fn peek(&self) -> Option<Ref<'_, T>> {
+        self.top
+            .as_ref()
+            .map(|top| Ref::map(top.borrow(), |node| &node.item))
+    }
+
+    /// amortized *O*(log(*n*))
+    fn pop(&mut self) -> Option<T> {
+        let top = self.top.take()?;
+        self.unlink_root(&top);
+        let mut child = top.borrow_mut().child.take();
+        while let Some(c) = child {
+            child = c.borrow_mut().next.take();
+            self.push_root(c);
+        }
+        self.consolidate();
+        self.len -= 1;
+        match Rc::try_unwrap(top) {
+            Ok(node) => Some(node.into_inner().item),
+            Err(_) => unreachable!("only weak handles point to a detached root"),
+        }
+    }
+
+    /// *O*(1), splicing the root lists
+    fn meld(&mut self, mut other: Self) {
+        if let Some(head) = other.head.take() {
+            match self.tail.upgrade() {
+                Some(tail) => {
+                    head.borrow_mut().prev = Rc::downgrade(&tail);
+                    tail.borrow_mut().next = Some(head);
+                }
+                None => self.head = Some(head),
+            }
+            self.tail = take(&mut other.tail);
+        }
+        if let Some(top) = other.top.take() {
+            self.update_top(&top);
+        }
+        self.len += other.len;
+    }
+
+    fn is_empty(&self) -> bool {
+        FibonacciHeap::is_empty(self)
+    }
+}
+
+impl<T, C> Drop for FibonacciHeap<T, C> {
+    /// free the nodes one at a time, as the root list may be long
+    fn drop(&mut self) {
+        self.top = None;
+        let mut stack: Vec<_> = self.head.take().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            let mut node = node.borrow_mut();
+            stack.extend(node.child.take());
+            stack.extend(node.next.take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fibonacci_decrease_key() {
+        let mut heap = FibonacciHeap::new_min();
+        let handles: Vec<_> = (0..300).map(|i| heap.push(1000 + i * 7 % 300)).collect();
+        assert_eq!(heap.check_invariants(), Ok(()));
+        assert_eq!(MergeableHeap::pop(&mut heap), Some(1000));
+        assert_eq!(heap.check_invariants(), Ok(()));
+
+        // cuts and cascading cuts all over the consolidated trees
+        for (i, handle) in handles.iter().enumerate().skip(1).step_by(3) {
+            let old = heap.get(handle).unwrap();
+            assert_eq!(heap.decrease_key(handle, old - 1000), Ok(old));
+            assert_eq!(heap.check_invariants(), Ok(()));
+            assert_eq!(heap.get(handle), Some(i as i32 * 7 % 300));
+            if i % 5 == 0 {
+                MergeableHeap::pop(&mut heap);
+                assert_eq!(heap.check_invariants(), Ok(()));
+            }
+        }
+        let bigger = heap.get(&handles[2]).unwrap() + 1;
+        assert_eq!(heap.decrease_key(&handles[2], bigger), Err(bigger));
+
+        let mut popped = Vec::new();
+        while let Some(item) = MergeableHeap::pop(&mut heap) {
+            popped.push(item);
+        }
+        assert!(popped.windows(2).all(|w| w[0] <= w[1]));
+        assert!(handles.iter().all(|handle| !heap.contains(handle)));
+    }
+
+    #[test]
+    fn fibonacci_meld() {
+        let mut a = FibonacciHeap::new();
+        let mut b = FibonacciHeap::new();
+        let ha: Vec<_> = (0..100).map(|i| a.push(i)).collect();
+        MergeableHeap::pop(&mut a);
+        let hb: Vec<_> = (100..200).map(|i| b.push(i)).collect();
+        a.meld(b);
+        assert_eq!(a.len(), 199);
+        assert_eq!(a.check_invariants(), Ok(()));
+        assert_eq!(a.decrease_key(&ha[5], 500), Ok(5));
+        assert_eq!(a.decrease_key(&hb[0], 400), Ok(100));
+        assert_eq!(a.check_invariants(), Ok(()));
+        assert_eq!(MergeableHeap::pop(&mut a), Some(500));
+        assert_eq!(MergeableHeap::pop(&mut a), Some(400));
+        assert_eq!(a.peek().as_deref(), Some(&199));
+        assert_eq!(a.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn long_root_list() {
+        let mut heap = FibonacciHeap::new();
+        for i in 0..200_000 {
+            heap.push(i);
+        }
+        assert_eq!(heap.peek().as_deref(), Some(&199_999));
+    }
+}
@@ -0,0 +1,332 @@
+//! Hashers for `HashMap`
+//!
+//! [`SipHasher`] is SipHash-2-4, a keyed hash: without the 128-bit key an
+//! attacker cannot pick keys that collide, so [`RandomState`] seeds a fresh
+//! key for every map. [`FxHasher`] is the multiply-rotate hash of rustc, much
+//! faster on short keys but trivial to attack, for trusted input only.
+
+use core::cell::Cell;
+use core::convert::TryInto;
+use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+/// SipHash-2-4 keyed with 128 bits
+#[derive(Debug, Clone)]
+pub struct SipHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    /// bytes not yet compressed, little-endian
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+impl SipHasher {
+    /// Creates a `SipHasher` keyed with `k0` and `k1`.
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    #[inline]
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    /// absorb one 8-byte word with two compression rounds
+    #[inline]
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        self.round();
+        self.round();
+        self.v0 ^= m;
+    }
+}
+
+impl Hasher for SipHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.length += bytes.len();
+        let mut bytes = bytes;
+
+        // top up the pending word first
+        if self.ntail > 0 {
+            let fill = bytes.len().min(8 - self.ntail);
+            for (i, &byte) in bytes[..fill].iter().enumerate() {
+                self.tail |= u64::from(byte) << (8 * (self.ntail + i));
+            }
+            self.ntail += fill;
+            bytes = &bytes[fill..];
+            if self.ntail < 8 {
+                return;
+            }
+            self.compress(self.tail);
+            self.tail = 0;
+            self.ntail = 0;
+        }
+
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.compress(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        for (i, &byte) in words.remainder().iter().enumerate() {
+            self.tail |= u64::from(byte) << (8 * i);
+        }
+        self.ntail = words.remainder().len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        let last = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(last);
+        state.v2 ^= 0xff;
+        for _ in 0..4 {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
+
+thread_local! {
+    /// per-thread SipHash keys, seeded from the OS randomness behind std's
+    /// `RandomState` and bumped for every new `RandomState`
+    static KEYS: Cell<(u64, u64)> = Cell::new({
+        let std_state = std::collections::hash_map::RandomState::new();
+        (std_state.hash_one(0u8), std_state.hash_one(1u8))
+    });
+}
+
+/// Builds [`SipHasher`]s with a key picked at random for each `RandomState`,
+/// the default hash builder of `HashMap`
+#[derive(Debug, Clone)]
+pub struct RandomState {
+    k0: u64,
+    k1: u64,
+}
+
+impl RandomState {
+    /// Creates a `RandomState` with fresh random keys.
+    pub fn new() -> Self {
+        KEYS.with(|keys| {
+            let (k0, k1) = keys.get();
+            keys.set((k0.wrapping_add(1), k1));
+            Self { k0, k1 }
+        })
+    }
+
+    /// Creates a `RandomState` with fixed keys, for reproducible hashing.
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        Self { k0, k1 }
+    }
+}
+
+impl Default for RandomState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for RandomState {
+    type Hasher = SipHasher;
+
+    fn build_hasher(&self) -> SipHasher {
+        SipHasher::new_with_keys(self.k0, self.k1)
+    }
+}
+
+/// multiplier of the Fx hash, the fractional part of the golden ratio
+const FX_SEED: u64 = 0x517c_c1b7_2722_0a95;
+
+/// FxHash, one rotate, xor and multiply per word, not resistant to collisions
+/// chosen on purpose
+#[derive(Debug, Clone, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    #[inline]
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.add_to_hash(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let mut rest = words.remainder();
+        if rest.len() >= 4 {
+            self.add_to_hash(u64::from(u32::from_le_bytes(rest[..4].try_into().unwrap())));
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            self.add_to_hash(u64::from(byte));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(u64::from(i));
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_to_hash(u64::from(i));
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_to_hash(u64::from(i));
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Builds [`FxHasher`]s, all hashing alike
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashMap;
+    use std::collections::HashSet;
+    use std::hash::Hash;
+
+    /// the reference vectors of the SipHash paper, key 00..0f and messages
+    /// 00, 00 01, 00 01 02, ...
+    #[test]
+    fn siphash_vectors() {
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let message: Vec<u8> = (0..64).collect();
+        let expected = [
+            (0, 0x726f_db47_dd0e_0e31),
+            (1, 0x74f8_39c5_93dc_67fd),
+            (8, 0x93f5_f579_9a93_2462),
+            (15, 0xa129_ca61_49be_45e5),
+            (63, 0x958a_324c_eb06_4572),
+        ];
+        for &(len, hash) in &expected {
+            let mut hasher = SipHasher::new_with_keys(k0, k1);
+            hasher.write(&message[..len]);
+            assert_eq!(hasher.finish(), hash, "message of {} bytes", len);
+
+            // fed in uneven pieces
+            let mut hasher = SipHasher::new_with_keys(k0, k1);
+            for piece in message[..len].chunks(3) {
+                hasher.write(piece);
+            }
+            assert_eq!(hasher.finish(), hash, "message of {} bytes in pieces", len);
+        }
+    }
+
+    #[test]
+    fn random_states_differ() {
+        let (a, b) = (RandomState::new(), RandomState::new());
+        assert_ne!(a.hash_one("key"), b.hash_one("key"));
+        assert_eq!(a.hash_one("key"), a.hash_one("key"));
+
+        let fixed = RandomState::with_keys(1, 2);
+        assert_eq!(
+            fixed.hash_one(42),
+            RandomState::with_keys(1, 2).hash_one(42)
+        );
+    }
+
+    /// colliding pairs when `keys` are hashed into `buckets` slots, beside the
+    /// number expected of a uniform hash, n (n - 1) / 2m
+    fn collisions<T: Hash>(
+        hash_builder: &impl BuildHasher,
+        keys: impl Iterator<Item = T>,
+        buckets: usize,
+    ) -> (usize, f64) {
+        let mut counts = vec![0usize; buckets];
+        let mut n = 0;
+        for key in keys {
+            counts[(hash_builder.hash_one(key) % buckets as u64) as usize] += 1;
+            n += 1;
+        }
+        let pairs = counts.iter().map(|c| c * c.saturating_sub(1) / 2).sum();
+        (pairs, (n * (n - 1)) as f64 / (2 * buckets) as f64)
+    }
+
+    #[test]
+    fn collision_statistics() {
+        const N: usize = 20_000;
+        // a prime of the form 4k + 3, as `HashMap` tables are
+        const BUCKETS: usize = 26_683;
+        let sip = RandomState::new();
+        let fx = FxBuildHasher::default();
+
+        // full 64-bit hashes of distinct keys are distinct
+        let full: HashSet<u64> = (0..N).map(|i| sip.hash_one(i)).collect();
+        assert_eq!(full.len(), N);
+
+        let words = || (0..N).map(|i| format!("key{}", i));
+        let strides = || (0..N as u64).map(|i| i << 32);
+        for (what, (pairs, expected)) in [
+            ("sip ints", collisions(&sip, 0..N, BUCKETS)),
+            ("sip words", collisions(&sip, words(), BUCKETS)),
+            ("sip strides", collisions(&sip, strides(), BUCKETS)),
+            ("fx ints", collisions(&fx, 0..N, BUCKETS)),
+            ("fx words", collisions(&fx, words(), BUCKETS)),
+            ("fx strides", collisions(&fx, strides(), BUCKETS)),
+        ] {
+            assert!(
+                (pairs as f64) < expected * 1.25,
+                "{}: {} colliding pairs, {:.0} expected",
+                what,
+                pairs,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn map_with_hashers() {
+        let mut fx = HashMap::with_hasher(FxBuildHasher::default());
+        let mut sip = HashMap::with_capacity_and_hasher(100, RandomState::with_keys(3, 4));
+        assert!(sip.capacity() >= 100);
+        for i in 0..1000 {
+            fx.insert(format!("{}", i), i);
+            sip.insert(i, format!("{}", i));
+        }
+        for i in 0..1000 {
+            assert_eq!(fx[&format!("{}", i)], i);
+            assert_eq!(sip[&i], format!("{}", i));
+        }
+        assert_eq!(
+            sip.hasher().hash_one(7),
+            RandomState::with_keys(3, 4).hash_one(7)
+        );
+
+        let collected: HashMap<i32, i32, FxBuildHasher> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(collected.len(), 10);
+    }
+}
@@ -1,65 +1,165 @@
-//! A hash map implemented with two-way quadratic probing with random algorithm
-//! HashMap len is fixed size by created
+//! A hash map, resolving collisions with a pluggable [`Table`]
+//!
+//! The default [`Quadratic`] table uses two-way quadratic probing. Table size
+//! is always a prime of the form 4k + 3, so that probing with offsets +1, -1,
+//! +4, -4, ... visits every slot, and grows by rehashing once the load factor
+//! crosses 3/4
+//!
+//! Removed entries leave a tombstone, so that probing goes on past them, and
+//! the table is compacted once tombstones fill a quarter of it
+//!
+//! Other strategies are [`Chained`](crate::Chained), [`RobinHood`](crate::RobinHood)
+//! and [`Cuckoo`](crate::Cuckoo)
+//!
+//! Keys are hashed by any [`BuildHasher`], by default a [`RandomState`] whose
+//! SipHash keys are picked per map, so that collisions cannot be forced
 
+use crate::stats::{clusters, HashMapStats};
+use crate::{Keys, RandomState, Values};
 use core::borrow::Borrow;
-use core::hash::{BuildHasher, Hash, Hasher};
-use core::mem::{replace, take};
+use core::hash::{BuildHasher, Hash};
+use core::iter;
+use core::iter::FromIterator;
+use core::marker::PhantomData;
+use core::mem::replace;
 use core::ops::Index;
+use std::{slice, vec};
 
-#[derive(Default, Debug)]
-pub struct RandomState;
-
-#[derive(Default, Debug)]
-pub struct MyHasher {
-    buffer: u64,
+fn make_hash<K: Hash + ?Sized>(hash_builder: &impl BuildHasher, val: &K) -> u64 {
+    hash_builder.hash_one(val)
 }
 
-impl Hasher for MyHasher {
-    fn write(&mut self, bytes: &[u8]) {
-        let mut buffer_array: [u8; 8] = [97, 107, 131, 211, 227, 241, 179, 151];
-        let mut i = 0;
-        for byte in bytes {
-            buffer_array[i] ^= byte;
-            i = i + 1 % 8;
-        }
+/// load factor threshold as `LOAD_NUM / LOAD_DEN`, over which the table grows
+const LOAD_NUM: usize = 3;
+const LOAD_DEN: usize = 4;
 
-        self.buffer = unsafe {
-            let mut result: u64 = core::mem::zeroed();
-            core::ptr::copy_nonoverlapping(
-                &buffer_array as *const [u8; 8] as *const u8,
-                &mut result as *mut u64 as *mut u8,
-                core::mem::size_of::<u64>(),
-            );
-            result
-        };
+/// whether `n` is a prime
+pub(crate) fn is_prime(n: usize) -> bool {
+    if n < 4 {
+        return n > 1;
+    }
+    if n % 2 == 0 {
+        return false;
     }
+    let mut d = 3;
+    while d * d <= n {
+        if n % d == 0 {
+            return false;
+        }
+        d += 2;
+    }
+    true
+}
 
-    fn finish(&self) -> u64 {
-        self.buffer
+/// the least prime of the form 4k + 3 not less than `n`
+pub(crate) fn table_size(n: usize) -> usize {
+    let mut size = n.max(3);
+    size += (3 + 4 - size % 4) % 4;
+    while !is_prime(size) {
+        size += 4;
     }
+    size
+}
+
+/// two-way quadratic probing sequence of `hash` in a table of `size` slots,
+/// `offset`, `offset + 1`, `offset - 1`, `offset + 4`, `offset - 4`, ...
+fn probe(hash: u64, size: usize) -> impl Iterator<Item = usize> {
+    let offset = (hash % size as u64) as usize;
+    let steps = (1..=size / 2).flat_map(move |i| {
+        let delta = i * i % size;
+        iter::once((offset + delta) % size).chain(iter::once((offset + size - delta) % size))
+    });
+    iter::once(offset).chain(steps)
+}
+
+/// table slot
+#[derive(Debug)]
+pub enum Slot<K, V> {
+    /// never used, ends a probe sequence
+    Empty,
+    /// tombstone of a removed entry, probing goes on past it
+    Deleted,
+    /// entry with the hash of its key
+    Full(u64, K, V),
 }
 
-impl BuildHasher for RandomState {
-    type Hasher = MyHasher;
-    fn build_hasher(&self) -> MyHasher {
-        MyHasher::default()
+impl<K, V> Slot<K, V> {
+    /// whether the slot holds an entry
+    pub(crate) fn is_full(&self) -> bool {
+        matches!(self, Slot::Full(..))
+    }
+
+    /// hash of the entry in a full slot
+    pub(crate) fn hash(&self) -> u64 {
+        match self {
+            Slot::Full(hash, ..) => *hash,
+            _ => unreachable!("slot is not full"),
+        }
+    }
+
+    /// replace a full slot with `with`, return its entry
+    pub(crate) fn take(&mut self, with: Self) -> (K, V) {
+        match replace(self, with) {
+            Slot::Full(_, k, v) => (k, v),
+            _ => unreachable!("slot is not full"),
+        }
     }
 }
 
-fn make_hash<K: Hash + ?Sized>(hash_builder: &impl BuildHasher, val: &K) -> u64 {
-    let mut state = hash_builder.build_hasher();
-    val.hash(&mut state);
-    state.finish()
+pub(crate) mod sealed {
+    /// supertrait of `Table`, which only the tables of this crate implement
+    pub trait Sealed {}
+}
+
+/// Collision resolution strategy of a `HashMap`, owning its entries
+///
+/// A table keeps every entry in one vector of slots, along with the hash of
+/// its key so that growing needs no hasher, and hands out slot indices that
+/// stay valid until the table is next modified.
+///
+/// This trait is sealed: it is implemented by [`Quadratic`],
+/// [`Chained`](crate::Chained), [`RobinHood`](crate::RobinHood) and
+/// [`Cuckoo`](crate::Cuckoo), and cannot be implemented outside this crate.
+pub trait Table<K, V>: Default + sealed::Sealed {
+    /// empty table able to hold `capacity` entries without growing
+    fn with_capacity(capacity: usize) -> Self;
+    /// number of entries
+    fn len(&self) -> usize;
+    /// whether there are no entries
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// number of entries the table can hold without growing
+    fn capacity(&self) -> usize;
+    /// every slot, in storage order
+    fn slots(&self) -> &[Slot<K, V>];
+    /// every slot, in storage order, to update values in place
+    fn slots_mut(&mut self) -> &mut [Slot<K, V>];
+    /// every slot, consuming the table
+    fn into_slots(self) -> Vec<Slot<K, V>>;
+    /// index of the slot holding an entry with `hash` whose key satisfies `eq`
+    fn find(&self, hash: u64, eq: impl FnMut(&K) -> bool) -> Option<usize>;
+    /// add an entry known to be absent, growing if needed, return its index
+    fn insert(&mut self, hash: u64, k: K, v: V) -> usize;
+    /// remove the entry at `idx`, other entries may move
+    fn remove(&mut self, idx: usize) -> (K, V);
+    /// remove every entry for which `f` returns `false`
+    fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool);
+    /// forget every entry, leaving them in the slots for the drain to take
+    fn drain(&mut self) -> HashMapDrain<'_, K, V>;
+    /// occupancy and probing statistics
+    fn stats(&self) -> HashMapStats;
 }
 
 /// HashMap
 #[derive(Debug)]
-pub struct HashMap<K, V, S = RandomState> {
-    table: Vec<Option<(K, V)>>,
+pub struct HashMap<K, V, S = RandomState, T = Quadratic<K, V>> {
+    pub(crate) table: T,
     hash_builder: S,
+    marker: PhantomData<(K, V)>,
 }
 
-impl<K: Eq + Hash + Clone, V: Clone + Default> HashMap<K, V> {
+impl<K: Eq + Hash, V> HashMap<K, V, RandomState> {
     /// Create an empty `HashMap`
     /// The hash map is initially created with a capacity of 0, so it will not allocate until it
     /// is first inserted into.
@@ -73,14 +173,105 @@ impl<K: Eq + Hash + Clone, V: Clone + Default> HashMap<K, V> {
     /// let mut map: HashMap<&str, i32> = HashMap::new();
     /// ```
     pub fn new() -> Self {
-        // 4k + 3 , such as 2048 + 3 = 2051
-        let table = vec![None; 2051];
+        Self::with_hasher(RandomState::new())
+    }
+
+    /// Creates an empty `HashMap` with the specified capacity.
+    ///
+    /// The hash map will be able to hold at least `capacity` elements without
+    /// reallocating. If `capacity` is 0, the hash map will not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    /// let mut map: HashMap<&str, i32> = HashMap::with_capacity(10);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> HashMap<K, V, S> {
+    /// Creates an empty `HashMap` which will use the given hash builder to
+    /// hash keys. It will not allocate until it is first inserted into.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::{FxBuildHasher, HashMap};
+    ///
+    /// let mut map = HashMap::with_hasher(FxBuildHasher::default());
+    /// map.insert(1, 2);
+    /// assert_eq!(map[&1], 2);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_table_and_hasher(Quadratic::default(), hash_builder)
+    }
+
+    /// Creates an empty `HashMap` with the specified capacity, using
+    /// `hash_builder` to hash the keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::{HashMap, RandomState};
+    ///
+    /// let mut map = HashMap::with_capacity_and_hasher(10, RandomState::new());
+    /// map.insert(1, 2);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self::with_table_and_hasher(Quadratic::with_capacity(capacity), hash_builder)
+    }
+}
+
+impl<K: Eq + Hash, V, T: Table<K, V>> HashMap<K, V, RandomState, T> {
+    /// Creates a `HashMap` keeping its entries in `table`, which picks the
+    /// collision strategy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::{Chained, HashMap, Table};
+    ///
+    /// let mut map = HashMap::with_table(Chained::with_capacity(10));
+    /// map.insert(1, 2);
+    /// assert_eq!(map[&1], 2);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    pub fn with_table(table: T) -> Self {
+        Self::with_table_and_hasher(table, RandomState::new())
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher, T: Table<K, V>> HashMap<K, V, S, T> {
+    /// Creates a `HashMap` keeping its entries in `table`, using
+    /// `hash_builder` to hash the keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::{FxBuildHasher, HashMap, RobinHood};
+    ///
+    /// let mut map = HashMap::with_table_and_hasher(RobinHood::default(), FxBuildHasher::default());
+    /// map.insert(1, 2);
+    /// assert_eq!(map[&1], 2);
+    /// ```
+    pub fn with_table_and_hasher(table: T, hash_builder: S) -> Self {
         Self {
             table,
-            hash_builder: RandomState::default(),
+            hash_builder,
+            marker: PhantomData,
         }
     }
 
+    /// Returns a reference to the map's [`BuildHasher`].
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
     /// Inserts a key-value pair into the map.
     ///
     /// If the map did not have this key present, [`None`] is returned.
@@ -105,64 +296,40 @@ impl<K: Eq + Hash + Clone, V: Clone + Default> HashMap<K, V> {
     /// assert_eq!(map[&37], "c");
     /// ```
     pub fn insert(&mut self, k: K, v: V) -> Option<V> {
-        let hash = make_hash(&self.hash_builder, &k);
-        let offset = (hash % self.len() as u64) as usize;
-
-        if self.table.get(offset).unwrap().is_none() {
-            self.table[offset] = Some((k, v));
-            return None;
-        }
-
-        // two ways square probing
-        for i in 0..=self.table.len() / 2 {
-            let idx = ((offset as i64 + (i * i) as i64) % self.len() as i64) as usize;
-            if let Some(elem_ref) = self.table.get_mut(idx) {
-                if let Some((key, item)) = elem_ref.as_mut() {
-                    if k.eq(key) {
-                        return Some(replace(item, v));
-                    }
-                } else {
-                    self.table[idx] = Some((k, v));
-                    return None;
-                }
-            }
-
-            let idx = ((((offset as i64 - (i * i) as i64) % self.len() as i64) + self.len() as i64)
-                % self.len() as i64) as usize;
-            if let Some(elem_ref) = self.table.get_mut(idx) {
-                if let Some((key, item)) = elem_ref.as_mut() {
-                    if k.eq(key) {
-                        return Some(replace(item, v));
-                    }
-                } else {
-                    self.table[idx] = Some((k, v));
-                    return None;
-                }
+        match self.find(&k) {
+            Some(idx) => Some(replace(self.slot_mut(idx).1, v)),
+            None => {
+                let hash = make_hash(&self.hash_builder, &k);
+                self.table.insert(hash, k, v);
+                None
             }
         }
-
-        None
     }
 
-    /// Creates an empty `HashMap` with the specified capacity.
-    ///
-    /// The hash map will be able to hold at least `capacity` elements without
-    /// reallocating. If `capacity` is 0, the hash map will not allocate.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use tsinghua_ds::HashMap;
-    /// let mut map: HashMap<&str, i32> = HashMap::with_capacity(10);
-    /// ```
-    pub fn with_capacity(capacity: usize) -> Self {
-        let capacity = capacity / 4 + 3;
-        let table = vec![None; capacity];
+    /// entry in the full slot `idx`
+    fn slot_mut(&mut self, idx: usize) -> (&K, &mut V) {
+        match &mut self.table.slots_mut()[idx] {
+            Slot::Full(_, k, v) => (k, v),
+            _ => unreachable!("slot {} is not full", idx),
+        }
+    }
 
-        Self {
-            table,
-            hash_builder: RandomState::default(),
+    /// slot index holding `k`
+    fn find<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.table.is_empty() {
+            return None;
         }
+        let hash = make_hash(&self.hash_builder, k);
+        self.table.find(hash, |key| k.eq(key.borrow()))
+    }
+
+    /// Returns the number of elements the map can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
     }
 
     /// Returns `true` if the map contains no elements.
@@ -171,6 +338,17 @@ impl<K: Eq + Hash + Clone, V: Clone + Default> HashMap<K, V> {
     }
 
     /// Returns the number of elements in the map
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// assert_eq!(map.len(), 0);
+    /// map.insert(1, "a");
+    /// assert_eq!(map.len(), 1);
+    /// ```
     pub fn len(&self) -> usize {
         self.table.len()
     }
@@ -192,35 +370,34 @@ impl<K: Eq + Hash + Clone, V: Clone + Default> HashMap<K, V> {
     /// assert_eq!(map.remove(&1), Some("a"));
     /// assert_eq!(map.remove(&1), None);
     /// ```
-    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
     where
         K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
-        let hash = make_hash(&self.hash_builder, k);
-        let offset = (hash % self.len() as u64) as usize;
-
-        // two ways square probing
-        for i in 0..=self.table.len() / 2 {
-            let idx = ((offset as i64 + (i * i) as i64) % self.len() as i64) as usize;
-            if let Some(Some(elem_ref)) = self.table.get_mut(idx) {
-                if k.eq(elem_ref.0.borrow()) {
-                    let result = take(&mut elem_ref.1);
-                    self.table[idx].take();
-                    return Some(result);
-                }
-            }
+        self.remove_entry(k).map(|(_, v)| v)
+    }
 
-            let idx = ((((offset as i64 - (i * i) as i64) % self.len() as i64) + self.len() as i64)
-                % self.len() as i64) as usize;
-            if let Some(Some(elem_ref)) = self.table.get_mut(idx) {
-                if k.eq(elem_ref.0.borrow()) {
-                    let result = take(&mut elem_ref.1);
-                    return Some(result);
-                }
-            }
-        }
-        None
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.remove_entry(&1), Some((1, "a")));
+    /// assert_eq!(map.remove_entry(&1), None);
+    /// ```
+    pub fn remove_entry<Q>(&mut self, k: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.find(k)?;
+        Some(self.table.remove(idx))
     }
 
     /// Returns a reference to the value corresponding to the key.
@@ -239,87 +416,1052 @@ impl<K: Eq + Hash + Clone, V: Clone + Default> HashMap<K, V> {
     /// assert_eq!(map.get(&1), Some(&"a"));
     /// assert_eq!(map.get(&2), None);
     /// ```
-    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
-        let hash = make_hash(&self.hash_builder, k);
-        let offset = (hash % self.len() as u64) as usize;
-
-        // two ways square probing
-        for i in 0..=self.table.len() / 2 {
-            let idx = ((offset as i64 + (i * i) as i64) % self.len() as i64) as usize;
-            if let Some(Some(elem_ref)) = self.table.get(idx) {
-                if k.eq(elem_ref.0.borrow()) {
-                    return Some(&elem_ref.1);
-                }
-            }
+        self.get_key_value(k).map(|(_, v)| v)
+    }
 
-            let idx = ((((offset as i64 - (i * i) as i64) % self.len() as i64) + self.len() as i64)
-                % self.len() as i64) as usize;
-            if let Some(Some(elem_ref)) = self.table.get(idx) {
-                if k.eq(elem_ref.0.borrow()) {
-                    return Some(&elem_ref.1);
-                }
-            }
+    /// Returns the key-value pair corresponding to the supplied key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get_key_value(&1), Some((&1, &"a")));
+    /// assert_eq!(map.get_key_value(&2), None);
+    /// ```
+    pub fn get_key_value<Q>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &self.table.slots()[self.find(k)?] {
+            Slot::Full(_, k, v) => Some((k, v)),
+            _ => None,
         }
-        None
     }
-}
-
-impl<K, Q: ?Sized, V: Clone + Default> Index<&Q> for HashMap<K, V>
-where
-    K: Eq + Hash + Borrow<Q> + Clone,
-    Q: Eq + Hash,
-{
-    type Output = V;
 
-    /// Returns a reference to the value corresponding to the supplied key.
+    /// Returns a mutable reference to the value corresponding to the key.
     ///
-    /// # Panics
+    /// # Examples
     ///
-    /// Panics if the key is not present in the `HashMap`.
-    #[inline]
-    fn index(&self, key: &Q) -> &V {
-        self.get(key).expect("no entry found for key")
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.insert(1, "a");
+    /// if let Some(x) = map.get_mut(&1) {
+    ///     *x = "b";
+    /// }
+    /// assert_eq!(map[&1], "b");
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.find(k)?;
+        Some(self.slot_mut(idx).1)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn hash_insert() {
-        let mut map: HashMap<i32, String> = HashMap::new();
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(k).is_some()
+    }
 
-        for i in 0..2000 {
-            let idx = 23338812 + i * i + 2 * i;
-            let b = format!("{}", i);
-            map.insert(idx, b.clone());
-            assert_eq!(map.get(&idx), Some(&b));
+    /// Gets the given key's corresponding entry in the map for in-place
+    /// manipulation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut letters = HashMap::new();
+    /// for ch in "a short treatise on fungi".chars() {
+    ///     *letters.entry(ch).or_insert(0) += 1;
+    /// }
+    ///
+    /// assert_eq!(letters[&'s'], 2);
+    /// assert_eq!(letters[&'t'], 3);
+    /// assert_eq!(letters[&'u'], 1);
+    /// assert_eq!(letters.get(&'y'), None);
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S, T> {
+        match self.find(&key) {
+            Some(idx) => Entry::Occupied(OccupiedEntry { map: self, idx }),
+            None => {
+                let hash = make_hash(&self.hash_builder, &key);
+                Entry::Vacant(VacantEntry {
+                    map: self,
+                    hash,
+                    key,
+                })
+            }
         }
+    }
 
-        for i in 0..2000 {
-            let idx = 23338812 + i * i + 2 * i;
-            let b = format!("{}", i);
-            assert_eq!(map.get(&idx), Some(&b));
-        }
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map: HashMap<i32, i32> = (0..8).map(|x| (x, x * 10)).collect();
+    /// map.retain(|&k, _| k % 2 == 0);
+    /// assert_eq!(map.len(), 4);
+    /// ```
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.table.retain(f);
     }
-    #[test]
-    fn hash_remove() {
-        let mut map: HashMap<i32, String> = HashMap::new();
 
-        for i in 0..2000 {
-            let idx = 23338812 + i * i + 2 * i;
-            let b = format!("{}", i);
-            map.insert(idx, b);
-        }
+    /// Clears the map, returning all key-value pairs as an iterator. Keeps
+    /// the allocated memory for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut a = HashMap::new();
+    /// a.insert(1, "a");
+    /// a.insert(2, "b");
+    ///
+    /// let mut drained: Vec<_> = a.drain().collect();
+    /// drained.sort();
+    /// assert_eq!(drained, [(1, "a"), (2, "b")]);
+    /// assert!(a.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> HashMapDrain<'_, K, V> {
+        self.table.drain()
+    }
 
-        for i in 0..2000 {
-            let idx = 23338812 + i * i + 2 * i;
-            let b = format!("{}", i);
-            assert_eq!(map.remove(&idx), Some(b));
-        }
+    /// Clears the map, removing all key-value pairs. Keeps the allocated
+    /// memory for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut a = HashMap::new();
+    /// a.insert(1, "a");
+    /// a.clear();
+    /// assert!(a.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.drain();
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let map: HashMap<_, _> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    /// let mut pairs: Vec<_> = map.iter().collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [(&"a", &1), (&"b", &2)]);
+    /// ```
+    pub fn iter(&self) -> HashMapIter<'_, K, V> {
+        HashMapIter {
+            slots: self.table.slots().iter(),
+            len: self.table.len(),
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, with
+    /// mutable references to the values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map: HashMap<_, _> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    /// for (_, val) in map.iter_mut() {
+    ///     *val *= 2;
+    /// }
+    /// assert_eq!(map[&"b"], 4);
+    /// ```
+    pub fn iter_mut(&mut self) -> HashMapIterMut<'_, K, V> {
+        let len = self.table.len();
+        HashMapIterMut {
+            slots: self.table.slots_mut().iter_mut(),
+            len,
+        }
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    pub fn keys(&self) -> Keys<HashMapIter<'_, K, V>> {
+        Keys(self.iter())
+    }
+
+    /// An iterator visiting all values in arbitrary order.
+    pub fn values(&self) -> Values<HashMapIter<'_, K, V>> {
+        Values(self.iter())
+    }
+
+    /// Reports occupancy, load factor, probe distances and cluster sizes of
+    /// the table, walking every slot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+    /// let stats = map.stats();
+    /// assert_eq!(stats.len, 100);
+    /// assert!(stats.load_factor() <= 0.75);
+    /// assert_eq!(stats.probe_histogram.iter().sum::<usize>(), 100);
+    /// println!("{}", stats);
+    /// ```
+    pub fn stats(&self) -> HashMapStats {
+        self.table.stats()
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Default, T: Table<K, V>> Default for HashMap<K, V, S, T> {
+    fn default() -> Self {
+        Self::with_table_and_hasher(T::default(), S::default())
+    }
+}
+
+impl<K, Q: ?Sized, V, S, T> Index<&Q> for HashMap<K, V, S, T>
+where
+    K: Eq + Hash + Borrow<Q>,
+    S: BuildHasher,
+    T: Table<K, V>,
+    Q: Eq + Hash,
+{
+    type Output = V;
+
+    /// Returns a reference to the value corresponding to the supplied key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the `HashMap`.
+    #[inline]
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher, T: Table<K, V>> Extend<(K, V)> for HashMap<K, V, S, T> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Default, T: Table<K, V>> FromIterator<(K, V)>
+    for HashMap<K, V, S, T>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S, T: Table<K, V>> IntoIterator for HashMap<K, V, S, T> {
+    type Item = (K, V);
+    type IntoIter = HashMapIntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let len = self.table.len();
+        HashMapIntoIter {
+            slots: self.table.into_slots().into_iter(),
+            len,
+        }
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher, T: Table<K, V>> IntoIterator for &'a HashMap<K, V, S, T> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = HashMapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher, T: Table<K, V>> IntoIterator
+    for &'a mut HashMap<K, V, S, T>
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = HashMapIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// A view into a single entry in a map, which may either be vacant or occupied.
+///
+/// This `enum` is constructed from the [`entry`] method on [`HashMap`].
+///
+/// [`entry`]: HashMap::entry
+#[derive(Debug)]
+pub enum Entry<'a, K, V, S = RandomState, T = Quadratic<K, V>> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, K, V, S, T>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, K, V, S, T>),
+}
+
+/// A view into an occupied entry in a `HashMap`, part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, K, V, S = RandomState, T = Quadratic<K, V>> {
+    map: &'a mut HashMap<K, V, S, T>,
+    idx: usize,
+}
+
+/// A view into a vacant entry in a `HashMap`, part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct VacantEntry<'a, K, V, S = RandomState, T = Quadratic<K, V>> {
+    map: &'a mut HashMap<K, V, S, T>,
+    hash: u64,
+    key: K,
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher, T: Table<K, V>> Entry<'a, K, V, S, T> {
+    /// Ensures a value is in the entry by inserting the default if empty, and
+    /// returns a mutable reference to the value in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map: HashMap<&str, u32> = HashMap::new();
+    ///
+    /// map.entry("poneyland").or_insert(3);
+    /// assert_eq!(map["poneyland"], 3);
+    ///
+    /// *map.entry("poneyland").or_insert(10) *= 2;
+    /// assert_eq!(map["poneyland"], 6);
+    /// ```
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default
+    /// function if empty, and returns a mutable reference to the value in the
+    /// entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map: HashMap<&str, String> = HashMap::new();
+    /// let s = "hoho".to_string();
+    ///
+    /// map.entry("poneyland").or_insert_with(|| s);
+    /// assert_eq!(map["poneyland"], "hoho".to_string());
+    /// ```
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of
+    /// the default function called with the entry's key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map: HashMap<&str, usize> = HashMap::new();
+    ///
+    /// map.entry("poneyland").or_insert_with_key(|key| key.chars().count());
+    /// assert_eq!(map["poneyland"], 9);
+    /// ```
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(&entry.key);
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::HashMap;
+    ///
+    /// let mut map: HashMap<&str, u32> = HashMap::new();
+    ///
+    /// map.entry("poneyland").and_modify(|e| *e += 1).or_insert(42);
+    /// assert_eq!(map["poneyland"], 42);
+    ///
+    /// map.entry("poneyland").and_modify(|e| *e += 1).or_insert(42);
+    /// assert_eq!(map["poneyland"], 43);
+    /// ```
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            entry => entry,
+        }
+    }
+}
+
+impl<'a, K: Eq + Hash, V: Default, S: BuildHasher, T: Table<K, V>> Entry<'a, K, V, S, T> {
+    /// Ensures a value is in the entry by inserting the default value if
+    /// empty, and returns a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher, T: Table<K, V>> OccupiedEntry<'a, K, V, S, T> {
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        match &self.map.table.slots()[self.idx] {
+            Slot::Full(_, k, _) => k,
+            _ => unreachable!("slot {} is not full", self.idx),
+        }
+    }
+
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        match &self.map.table.slots()[self.idx] {
+            Slot::Full(_, _, v) => v,
+            _ => unreachable!("slot {} is not full", self.idx),
+        }
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        self.map.slot_mut(self.idx).1
+    }
+
+    /// Converts the entry into a mutable reference to the value in the entry
+    /// with a lifetime bound to the map itself.
+    pub fn into_mut(self) -> &'a mut V {
+        self.map.slot_mut(self.idx).1
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value.
+    pub fn insert(&mut self, value: V) -> V {
+        replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the entry, and returns it.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Take the ownership of the key and value from the map.
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.table.remove(self.idx)
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher, T: Table<K, V>> VacantEntry<'a, K, V, S, T> {
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through the `VacantEntry`.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Take ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns
+    /// a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let idx = self.map.table.insert(self.hash, self.key, value);
+        self.map.slot_mut(idx).1
+    }
+}
+
+/// An iterator over the entries of a `HashMap`, in arbitrary order
+#[derive(Debug)]
+pub struct HashMapIter<'a, K, V> {
+    slots: slice::Iter<'a, Slot<K, V>>,
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for HashMapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in &mut self.slots {
+            if let Slot::Full(_, k, v) = slot {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for HashMapIter<'_, K, V> {}
+
+/// A mutable iterator over the entries of a `HashMap`, in arbitrary order
+#[derive(Debug)]
+pub struct HashMapIterMut<'a, K, V> {
+    slots: slice::IterMut<'a, Slot<K, V>>,
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for HashMapIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in &mut self.slots {
+            if let Slot::Full(_, k, v) = slot {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for HashMapIterMut<'_, K, V> {}
+
+/// An owning iterator over the entries of a `HashMap`, in arbitrary order
+#[derive(Debug)]
+pub struct HashMapIntoIter<K, V> {
+    slots: vec::IntoIter<Slot<K, V>>,
+    len: usize,
+}
+
+impl<K, V> Iterator for HashMapIntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in &mut self.slots {
+            if let Slot::Full(_, k, v) = slot {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for HashMapIntoIter<K, V> {}
+
+/// A draining iterator over the entries of a `HashMap`, slots not yet
+/// reached are emptied on drop
+#[derive(Debug)]
+pub struct HashMapDrain<'a, K, V> {
+    pub(crate) slots: slice::IterMut<'a, Slot<K, V>>,
+    pub(crate) len: usize,
+}
+
+impl<K, V> Iterator for HashMapDrain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in &mut self.slots {
+            if let Slot::Full(_, k, v) = replace(slot, Slot::Empty) {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for HashMapDrain<'_, K, V> {}
+
+impl<K, V> Drop for HashMapDrain<'_, K, V> {
+    fn drop(&mut self) {
+        for slot in &mut self.slots {
+            *slot = Slot::Empty;
+        }
+    }
+}
+
+/// Two-way quadratic probing with tombstones, the default table of `HashMap`
+#[derive(Debug)]
+pub struct Quadratic<K, V> {
+    slots: Vec<Slot<K, V>>,
+    len: usize,
+    deleted: usize,
+}
+
+impl<K, V> Default for Quadratic<K, V> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            len: 0,
+            deleted: 0,
+        }
+    }
+}
+
+impl<K, V> Quadratic<K, V> {
+    /// put an entry known to be absent into the first free slot, reusing
+    /// a tombstone if met first, return its slot index
+    fn insert_new(&mut self, hash: u64, k: K, v: V) -> usize {
+        let idx = probe(hash, self.slots.len())
+            .find(|&idx| !self.slots[idx].is_full())
+            .expect("two-way quadratic probing covers every slot");
+        if let Slot::Deleted = replace(&mut self.slots[idx], Slot::Full(hash, k, v)) {
+            self.deleted -= 1;
+        }
+        idx
+    }
+
+    /// leave a tombstone in the full slot `idx`, return the entry
+    fn take_slot(&mut self, idx: usize) -> (K, V) {
+        let entry = self.slots[idx].take(Slot::Deleted);
+        self.len -= 1;
+        self.deleted += 1;
+        entry
+    }
+
+    /// compact once tombstones fill a quarter of the table
+    fn compact(&mut self) {
+        if self.deleted * 4 > self.slots.len() {
+            self.resize(self.slots.len());
+        }
+    }
+
+    /// move every entry into a new table of `size` slots, dropping tombstones
+    fn resize(&mut self, size: usize) {
+        let mut slots = Vec::with_capacity(size);
+        slots.resize_with(size, || Slot::Empty);
+        let old_slots = replace(&mut self.slots, slots);
+        self.deleted = 0;
+        for slot in old_slots {
+            if let Slot::Full(hash, k, v) = slot {
+                self.insert_new(hash, k, v);
+            }
+        }
+    }
+}
+
+impl<K, V> sealed::Sealed for Quadratic<K, V> {}
+
+impl<K, V> Table<K, V> for Quadratic<K, V> {
+    fn with_capacity(capacity: usize) -> Self {
+        let mut table = Self::default();
+        if capacity > 0 {
+            table.resize(table_size(capacity * LOAD_DEN / LOAD_NUM + 1));
+        }
+        table
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.len() * LOAD_NUM / LOAD_DEN
+    }
+
+    fn slots(&self) -> &[Slot<K, V>] {
+        &self.slots
+    }
+
+    fn slots_mut(&mut self) -> &mut [Slot<K, V>] {
+        &mut self.slots
+    }
+
+    fn into_slots(self) -> Vec<Slot<K, V>> {
+        self.slots
+    }
+
+    /// probing stops at the first empty slot
+    fn find(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        for idx in probe(hash, self.slots.len()) {
+            match &self.slots[idx] {
+                Slot::Full(h, key, _) if *h == hash && eq(key) => return Some(idx),
+                Slot::Empty => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn insert(&mut self, hash: u64, k: K, v: V) -> usize {
+        // tombstones count, they are only cleared by rehashing
+        if (self.len + self.deleted + 1) * LOAD_DEN > self.slots.len() * LOAD_NUM {
+            let size = match self.deleted > self.len {
+                true => self.slots.len(),
+                false => table_size(self.slots.len() * 2 + 1),
+            };
+            self.resize(size);
+        }
+        self.len += 1;
+        self.insert_new(hash, k, v)
+    }
+
+    fn remove(&mut self, idx: usize) -> (K, V) {
+        let entry = self.take_slot(idx);
+        self.compact();
+        entry
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for idx in 0..self.slots.len() {
+            if let Slot::Full(_, k, v) = &mut self.slots[idx] {
+                if !f(k, v) {
+                    self.take_slot(idx);
+                }
+            }
+        }
+        self.compact();
+    }
+
+    fn drain(&mut self) -> HashMapDrain<'_, K, V> {
+        let len = replace(&mut self.len, 0);
+        self.deleted = 0;
+        HashMapDrain {
+            slots: self.slots.iter_mut(),
+            len,
+        }
+    }
+
+    /// the probe distance of an entry is its place in the probe sequence
+    /// of its hash, and tombstones are part of clusters
+    fn stats(&self) -> HashMapStats {
+        let size = self.slots.len();
+        let distances = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| match slot {
+                Slot::Full(hash, ..) => probe(*hash, size).position(|probed| probed == idx),
+                _ => None,
+            });
+        HashMapStats::new(
+            self.len,
+            size,
+            self.deleted,
+            distances,
+            clusters(size, |idx| !matches!(self.slots[idx], Slot::Empty)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Colliding;
+    #[test]
+    fn hash_insert() {
+        let mut map: HashMap<i32, String> = HashMap::new();
+
+        for i in 0..2000 {
+            let idx = 23338812 + i * i + 2 * i;
+            let b = format!("{}", i);
+            map.insert(idx, b.clone());
+            assert_eq!(map.get(&idx), Some(&b));
+        }
+
+        for i in 0..2000 {
+            let idx = 23338812 + i * i + 2 * i;
+            let b = format!("{}", i);
+            assert_eq!(map.get(&idx), Some(&b));
+        }
+    }
+    #[test]
+    fn hash_remove() {
+        let mut map: HashMap<i32, String> = HashMap::new();
+
+        for i in 0..2000 {
+            let idx = 23338812 + i * i + 2 * i;
+            let b = format!("{}", i);
+            map.insert(idx, b);
+        }
+
+        for i in 0..2000 {
+            let idx = 23338812 + i * i + 2 * i;
+            let b = format!("{}", i);
+            assert_eq!(map.remove(&idx), Some(b));
+        }
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn hash_grow() {
+        let mut map: HashMap<i32, i32> = HashMap::new();
+        assert_eq!(map.table.slots.len(), 0);
+
+        for i in 0..5000 {
+            assert_eq!(map.insert(i, -i), None);
+            assert_eq!(map.len(), i as usize + 1);
+            let size = map.table.slots.len();
+            assert!(is_prime(size) && size % 4 == 3);
+            assert!(map.len() * LOAD_DEN <= size * LOAD_NUM);
+        }
+        for i in 0..5000 {
+            assert_eq!(map.get(&i), Some(&-i));
+        }
+        assert_eq!(map.insert(42, 42), Some(-42));
+        assert_eq!(map.len(), 5000);
+
+        for i in (0..5000).step_by(2) {
+            assert_eq!(map.remove(&i), if i == 42 { Some(42) } else { Some(-i) });
+        }
+        assert_eq!(map.len(), 2500);
+        for i in 0..5000 {
+            let expected = if i % 2 == 0 { None } else { Some(&-i) };
+            assert_eq!(map.get(&i), expected);
+        }
+    }
+
+    #[test]
+    fn colliding_insert_remove() {
+        let mut map = HashMap::new();
+        let mut model = std::collections::HashMap::new();
+
+        for round in 0..20u32 {
+            for i in 0..50 {
+                let key = round * 7 + i;
+                assert_eq!(map.insert(Colliding(key), key), model.insert(key, key));
+            }
+            for i in (0..60).step_by(3) {
+                let key = round * 5 + i;
+                assert_eq!(map.remove(&Colliding(key)), model.remove(&key));
+            }
+            // keys after a removed one in the chain must stay reachable
+            for key in 0..round * 7 + 60 {
+                assert_eq!(map.get(&Colliding(key)), model.get(&key));
+            }
+            assert_eq!(map.len(), model.len());
+            assert!((map.len() + map.table.deleted) * LOAD_DEN <= map.table.slots.len() * LOAD_NUM);
+        }
+    }
+
+    #[test]
+    fn tombstones_compacted() {
+        let mut map = HashMap::with_capacity(100);
+        let size = map.table.slots.len();
+        for round in 0..100 {
+            for i in 0..50 {
+                map.insert(Colliding(i), round);
+            }
+            for i in 0..50 {
+                assert_eq!(map.remove(&Colliding(i)), Some(round));
+            }
+            assert!(map.table.deleted * 4 <= map.table.slots.len());
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.table.slots.len(), size);
+
+        // reinserting over a tombstone does not duplicate the key
+        map.insert(Colliding(1), 1);
+        map.insert(Colliding(4), 4);
+        map.remove(&Colliding(1));
+        assert_eq!(map.insert(Colliding(4), 5), Some(4));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&Colliding(4)), Some(&5));
+    }
+
+    #[test]
+    fn entry_api() {
+        let text = "the quick brown fox jumps over the lazy dog the end";
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for word in text.split_whitespace() {
+            *counts.entry(word).or_insert_with(|| 0) += 1;
+        }
+        assert_eq!(counts.len(), 9);
+        assert_eq!(counts["the"], 3);
+        assert_eq!(counts["fox"], 1);
+
+        match counts.entry("fox") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &"fox");
+                assert_eq!(entry.insert(7), 1);
+                assert_eq!(entry.remove_entry(), ("fox", 7));
+            }
+            Entry::Vacant(_) => panic!("fox is present"),
+        }
+        match counts.entry("cat") {
+            Entry::Vacant(entry) => assert_eq!(entry.into_key(), "cat"),
+            Entry::Occupied(_) => panic!("cat is absent"),
+        }
+        assert!(!counts.contains_key("fox"));
+        assert!(!counts.contains_key("cat"));
+        assert_eq!(*counts.entry("cat").or_default(), 0);
+        assert_eq!(counts.len(), 9);
+
+        let mut groups: HashMap<usize, Vec<&str>> = HashMap::new();
+        for word in text.split_whitespace() {
+            groups.entry(word.len()).or_default().push(word);
+        }
+        assert_eq!(groups[&5], ["quick", "brown", "jumps"]);
+    }
+
+    #[test]
+    fn iterate_and_drain() {
+        let mut map: HashMap<i32, i32> = (0..100).map(|i| (i, i * i)).collect();
+        assert_eq!(map.iter().len(), 100);
+        assert_eq!(map.keys().sum::<i32>(), (0..100).sum());
+        assert_eq!(map.values().sum::<i32>(), (0..100).map(|i| i * i).sum());
+
+        for (k, v) in map.iter_mut() {
+            *v -= k * k;
+        }
+        assert!(map.values().all(|&v| v == 0));
+        for (_, v) in &mut map {
+            *v += 1;
+        }
+        assert_eq!((&map).into_iter().count(), 100);
+
+        map.retain(|&k, _| k % 3 == 0);
+        assert_eq!(map.len(), 34);
+        assert_eq!(map.get_key_value(&99), Some((&99, &1)));
+        assert_eq!(map.get(&98), None);
+
+        map.extend((100..110).map(|i| (i, 0)));
+        assert_eq!(map.len(), 44);
+        if let Some(v) = map.get_mut(&105) {
+            *v = 5;
+        }
+
+        let mut drained: Vec<_> = map.drain().take(3).collect();
+        assert_eq!(drained.len(), 3);
+        assert!(map.is_empty());
+        assert_eq!(map.get(&105), None);
+        assert_eq!(map.iter().count(), 0);
+
+        map.insert(1, 1);
+        map.insert(2, 2);
+        drained = map.into_iter().collect();
+        drained.sort();
+        assert_eq!(drained, [(1, 1), (2, 2)]);
+
+        let mut map: HashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        let size = map.table.slots.len();
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.table.slots.len(), size);
+        assert_eq!(map.remove(&1), None);
+    }
+
+    #[test]
+    fn quadratic_stats() {
+        let empty: HashMap<i32, i32> = HashMap::new();
+        let stats = empty.stats();
+        assert_eq!((stats.len, stats.slots, stats.clusters()), (0, 0, 0));
+        assert_eq!(stats.average_probe_distance(), 0.0);
+
+        // keys equal mod 3 share a hash, and so a probe sequence; fixed keys
+        // keep `Colliding(1)` off that sequence
+        let mut map = HashMap::with_capacity_and_hasher(100, RandomState::with_keys(0, 0));
+        for i in 0..5 {
+            map.insert(Colliding(i * 3), i);
+        }
+        map.insert(Colliding(1), 1);
+        let stats = map.stats();
+        assert_eq!(stats.len, 6);
+        assert_eq!(stats.slots, map.table.slots.len());
+        assert_eq!(stats.probe_histogram.iter().sum::<usize>(), 6);
+        assert_eq!(stats.probe_histogram[1..], [1, 1, 1, 1]);
+        assert_eq!(stats.max_probe_distance(), 4);
+
+        map.remove(&Colliding(0));
+        let stats = map.stats();
+        assert_eq!((stats.len, stats.tombstones), (5, 1));
+        assert!(stats.occupancy() > stats.load_factor());
+        // the tombstone keeps the chain whole, entries did not move
+        assert_eq!(stats.max_probe_distance(), 4);
+        let in_use: usize = stats
+            .cluster_histogram
+            .iter()
+            .enumerate()
+            .map(|(size, count)| size * count)
+            .sum();
+        assert_eq!(in_use, 6);
+    }
+
+    #[test]
+    fn table_sizes() {
+        let sizes: Vec<_> = [0, 3, 4, 8, 12, 20, 2048]
+            .iter()
+            .map(|&n| table_size(n))
+            .collect();
+        assert_eq!(sizes, [3, 3, 7, 11, 19, 23, 2063]);
+
+        let map: HashMap<i32, i32> = HashMap::with_capacity(0);
+        assert_eq!(map.capacity(), 0);
+        let map: HashMap<i32, i32> = HashMap::with_capacity(100);
+        assert!(map.capacity() >= 100);
+    }
+
+    #[test]
+    fn long_keys() {
+        let mut map = HashMap::new();
+        map.insert("longer than eight bytes", 1);
+        assert_eq!(map.get("longer than eight bytes"), Some(&1));
     }
 }
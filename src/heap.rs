@@ -6,10 +6,18 @@
 //! converted to a sorted vector in-place, allowing it to be used for an *O*(*n* \* log(*n*))
 //! in-place heapsort.
 //!
-use std::mem::{swap, take};
+//! Both heaps are ordered by a [`Compare`] type parameter, which defaults to
+//! the natural order of `Ord` and so to a max-heap.
+//!
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use crate::dary::dary_heap_sort;
+use crate::InvariantError;
+use std::mem::swap;
+use std::ops::Deref;
 /// A priority queue implemented with a binary heap.
 ///
-/// This will be a max-heap.
+/// This will be a max-heap by default, or order its items by any other
+/// comparator `C`, the greatest by `C` being on top.
 ///
 /// It is a logic error for an item to be modified in such a way that the
 /// item's ordering relative to any other item, as determined by the `Ord`
@@ -50,28 +58,46 @@ use std::mem::{swap, take};
 ///
 /// ## Min-heap
 ///
-/// Either `std::cmp::Reverse` or a custom `Ord` implementation can be used to
-/// make `BinaryHeap` a min-heap. This makes `heap.pop()` return the smallest
-/// value instead of the greatest one.
+/// `BinaryHeap::new_min` makes a min-heap, so that `heap.pop()` returns the
+/// smallest value instead of the greatest one. Wrapping values in
+/// `std::cmp::Reverse` works too.
 ///
 /// ```
 /// use tsinghua_ds::BinaryHeap;
-/// use std::cmp::Reverse;
 ///
-/// let mut heap = BinaryHeap::new();
+/// let mut heap = BinaryHeap::new_min();
 ///
-/// // Wrap values in `Reverse`
-/// heap.push(Reverse(1));
-/// heap.push(Reverse(5));
-/// heap.push(Reverse(2));
+/// heap.push(1);
+/// heap.push(5);
+/// heap.push(2);
 ///
 /// // If we pop these scores now, they should come back in the reverse order.
-/// assert_eq!(heap.pop(), Some(Reverse(1)));
-/// assert_eq!(heap.pop(), Some(Reverse(2)));
-/// assert_eq!(heap.pop(), Some(Reverse(5)));
+/// assert_eq!(heap.pop(), Some(1));
+/// assert_eq!(heap.pop(), Some(2));
+/// assert_eq!(heap.pop(), Some(5));
 /// assert_eq!(heap.pop(), None);
 /// ```
 ///
+/// ## Custom order
+///
+/// Any comparator closure, or [`ByKey`](crate::ByKey) for a key of each
+/// item, orders the heap without wrapping the items.
+///
+/// ```
+/// use tsinghua_ds::BinaryHeap;
+///
+/// // shortest first, then alphabetical
+/// let mut heap = BinaryHeap::with_comparator(|a: &&str, b: &&str| {
+///     b.len().cmp(&a.len()).then(b.cmp(a))
+/// });
+/// heap.push("ccc");
+/// heap.push("bb");
+/// heap.push("aa");
+/// assert_eq!(heap.pop(), Some("aa"));
+/// assert_eq!(heap.pop(), Some("bb"));
+/// assert_eq!(heap.pop(), Some("ccc"));
+/// ```
+///
 /// # Time complexity
 ///
 /// | [push] | [pop]     | [peek]/[peek\_mut] |
@@ -85,11 +111,12 @@ use std::mem::{swap, take};
 /// [pop]: BinaryHeap::pop
 /// [peek]: BinaryHeap::peek
 /// [peek\_mut]: BinaryHeap::peek_mut
-pub struct BinaryHeap<T> {
+pub struct BinaryHeap<T, C = MaxComparator> {
     data: Vec<T>,
+    cmp: C,
 }
 
-impl<T: Ord + Default> BinaryHeap<T> {
+impl<T: Ord> BinaryHeap<T> {
     /// Creates an empty `BinaryHeap` as a max-heap.
     ///
     /// # Examples
@@ -102,7 +129,7 @@ impl<T: Ord + Default> BinaryHeap<T> {
     /// heap.push(4);
     /// ```
     pub fn new() -> BinaryHeap<T> {
-        BinaryHeap { data: vec![] }
+        BinaryHeap::with_comparator(MaxComparator)
     }
 
     /// Creates an empty `BinaryHeap` with a specific capacity.
@@ -120,11 +147,81 @@ impl<T: Ord + Default> BinaryHeap<T> {
     /// heap.push(4);
     /// ```
     pub fn with_capacity(capacity: usize) -> BinaryHeap<T> {
+        BinaryHeap::with_capacity_and_comparator(capacity, MaxComparator)
+    }
+}
+
+impl<T: Ord> BinaryHeap<T, MinComparator> {
+    /// Creates an empty `BinaryHeap` as a min-heap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use tsinghua_ds::BinaryHeap;
+    /// let mut heap = BinaryHeap::new_min();
+    /// heap.push(4);
+    /// heap.push(1);
+    /// assert_eq!(heap.peek(), Some(&1));
+    /// ```
+    pub fn new_min() -> BinaryHeap<T, MinComparator> {
+        BinaryHeap::with_comparator(MinComparator)
+    }
+}
+
+impl<T, C: Compare<T>> BinaryHeap<T, C> {
+    /// Creates an empty `BinaryHeap` ordered by `cmp`, the greatest item by
+    /// `cmp` being on top.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use tsinghua_ds::BinaryHeap;
+    /// let mut heap = BinaryHeap::with_comparator(|a: &i32, b: &i32| a.abs().cmp(&b.abs()));
+    /// heap.push(3);
+    /// heap.push(-7);
+    /// assert_eq!(heap.peek(), Some(&-7));
+    /// ```
+    pub fn with_comparator(cmp: C) -> BinaryHeap<T, C> {
+        BinaryHeap::with_capacity_and_comparator(0, cmp)
+    }
+
+    /// Creates an empty `BinaryHeap` ordered by `cmp`, with a specific
+    /// capacity.
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> BinaryHeap<T, C> {
         BinaryHeap {
             data: Vec::with_capacity(capacity),
+            cmp,
         }
     }
 
+    /// Converts a `Vec<T>` into a `BinaryHeap` ordered by `cmp`.
+    ///
+    /// This conversion happens in-place, and has *O*(*n*) time complexity.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use tsinghua_ds::{BinaryHeap, MinComparator};
+    /// let mut heap = BinaryHeap::from_vec_with_comparator(vec![3, 1, 2], MinComparator);
+    /// assert_eq!(heap.pop(), Some(1));
+    /// ```
+    pub fn from_vec_with_comparator(vec: Vec<T>, cmp: C) -> BinaryHeap<T, C> {
+        let mut heap = BinaryHeap { data: vec, cmp };
+        heap.rebuild();
+        heap
+    }
+
+    /// Returns the comparator ordering the heap.
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
     /// Returns the greatest item in the binary heap, or `None` if it is empty.
     ///
     /// # Examples
@@ -147,7 +244,7 @@ impl<T: Ord + Default> BinaryHeap<T> {
     ///
     /// Cost is *O*(1) in the worst case.
     pub fn peek(&self) -> Option<&T> {
-        self.data.get(0)
+        self.data.first()
     }
 
     /// Pushes an item onto the binary heap.
@@ -188,18 +285,16 @@ impl<T: Ord + Default> BinaryHeap<T> {
         self.sift_up(0, old_len);
     }
 
-    /// sift up
+    /// sift up, swapping the element at `pos` with its parent until in order
     fn sift_up(&mut self, start: usize, mut pos: usize) {
-        let be_replaced = take(&mut self.data[pos]);
         while pos > start {
             let parent = (pos - 1) / 2;
-            if self.data[parent] >= be_replaced {
+            if self.cmp.ge(&self.data[parent], &self.data[pos]) {
                 break;
             }
-            self.data[pos] = take(&mut self.data[parent]);
+            self.data.swap(pos, parent);
             pos = parent;
         }
-        self.data[pos] = be_replaced;
     }
 
     /// Removes the greatest item from the binary heap and returns it, or `None` if it
@@ -233,25 +328,23 @@ impl<T: Ord + Default> BinaryHeap<T> {
 
     /// Take an element at `pos` and move it all the way down the heap,
     fn sift_down(&mut self, mut pos: usize) {
-        let be_replaced = take(&mut self.data[pos]);
         let end = self.len();
         let mut child = 2 * pos + 1;
         while child < end {
             let right = child + 1;
             // compare with the greater of the two children
-            if right < end && self.data[child] <= self.data[right] {
+            if right < end && self.cmp.le(&self.data[child], &self.data[right]) {
                 child = right;
             }
             // if we are already in order, stop.
-            if be_replaced >= self.data[child] {
+            if self.cmp.ge(&self.data[pos], &self.data[child]) {
                 break;
             }
 
-            self.data[pos] = take(&mut self.data[child]);
+            self.data.swap(pos, child);
             pos = child;
             child = 2 * pos + 1;
         }
-        self.data[pos] = be_replaced;
     }
 
     /// Robert Floyd build heap algorithm
@@ -300,58 +393,80 @@ impl<T: Ord + Default> BinaryHeap<T> {
     }
 }
 
-impl<T: Ord + Default> From<Vec<T>> for BinaryHeap<T> {
+impl<T, C: Compare<T> + Default> Default for BinaryHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> From<Vec<T>> for BinaryHeap<T> {
     /// Converts a `Vec<T>` into a `BinaryHeap<T>`.
     ///
     /// This conversion happens in-place, and has *O*(*n*) time complexity.
     fn from(vec: Vec<T>) -> BinaryHeap<T> {
-        let mut heap = BinaryHeap { data: vec };
-        heap.rebuild();
-        heap
-    }
-}
-
-/// Take an element at `pos` and move it all the way down the heap,
-fn sift_down<T: Ord + Default>(seq: &mut [T], mut pos: usize, lo: usize, hi: usize) {
-    let be_replaced = take(&mut seq[pos]);
-    let mut child = 2 * (pos - lo) + lo + 1;
-    while child < hi {
-        let right = child + 1;
-        // compare with the greater of the two children
-        if right < hi && seq[child] <= seq[right] {
-            child = right;
-        }
-        // if we are already in order, stop.
-        if be_replaced >= seq[child] {
-            break;
-        }
-
-        seq[pos] = take(&mut seq[child]);
-        pos = child;
-        child = 2 * (pos - lo) + lo + 1;
+        BinaryHeap::from_vec_with_comparator(vec, MaxComparator)
     }
-    seq[pos] = be_replaced;
 }
 
 // use std::fmt::Debug;
 /// Heap sort algorithm
 /// half-open inclusive
-pub fn heap_sort<T: Ord + Default>(seq: &mut [T], lo: usize, mut hi: usize) {
-    let mut n = (hi - lo) / 2 + lo;
-    // build heap
-    while n > lo {
-        n -= 1;
-        sift_down(seq, n, lo, hi);
-    }
-
-    while lo < hi {
-        hi -= 1;
-        let old_hi = take(&mut seq[hi]);
-        let old_lo = take(&mut seq[lo]);
-        seq[hi] = old_lo;
-        seq[lo] = old_hi;
-        sift_down(seq, lo, lo, hi);
-    }
+pub fn heap_sort<T: Ord>(seq: &mut [T], lo: usize, hi: usize) {
+    dary_heap_sort::<2, _>(seq, lo, hi);
+}
+
+/// A heap that can be melded with another of its kind into one
+///
+/// The pairing and Fibonacci heaps meld in *O*(1), the leftist and binomial
+/// heaps in *O*(log(*n*)) and the skew heap in amortized *O*(log(*n*)).
+/// `peek` hands out a [`Deref`] to the greatest item, a plain reference for
+/// heaps owning their nodes, a `Ref` for the ones whose nodes are shared
+/// with handles.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{BinomialHeap, MergeableHeap, SkewHeap};
+///
+/// fn drain<H: MergeableHeap<i32>>(mut a: H, b: H) -> Vec<i32> {
+///     a.meld(b);
+///     std::iter::from_fn(|| a.pop()).collect()
+/// }
+///
+/// let (mut a, mut b) = (SkewHeap::new(), SkewHeap::new());
+/// a.push(1);
+/// b.push(3);
+/// b.push(2);
+/// assert_eq!(drain(a, b), [3, 2, 1]);
+///
+/// let (mut a, b) = (BinomialHeap::new_min(), BinomialHeap::new_min());
+/// a.push(5);
+/// assert_eq!(a.peek().as_deref(), Some(&5));
+/// assert_eq!(drain(a, b), [5]);
+/// ```
+pub trait MergeableHeap<T> {
+    /// borrow of the greatest item
+    type Peek<'a>: Deref<Target = T>
+    where
+        Self: 'a;
+
+    /// Pushes an item onto the heap.
+    fn push(&mut self, item: T);
+
+    /// Returns the greatest item, or `None` if the heap is empty.
+    fn peek(&self) -> Option<Self::Peek<'_>>;
+
+    /// Removes the greatest item from the heap and returns it, or `None` if
+    /// it is empty.
+    fn pop(&mut self) -> Option<T>;
+
+    /// Moves every item of `other` into the heap.
+    fn meld(&mut self, other: Self)
+    where
+        Self: Sized;
+
+    /// Checks if the heap is empty.
+    fn is_empty(&self) -> bool;
 }
 
 /// leftist heaps node
@@ -363,39 +478,72 @@ pub struct TreeNode<T> {
     right: Option<Box<TreeNode<T>>>,
 }
 
-impl<T: Ord + Default> TreeNode<T> {
-    /// Construct new instance
-    pub fn new() -> Self {
-        Self {
-            elem: T::default(),
-            npl: -1,
-            left: None,
-            right: None,
-        }
-    }
-
+impl<T> TreeNode<T> {
     /// Construct new instance
     pub fn with_elem(elem: T) -> Self {
         Self {
             elem,
-            npl: -1,
+            npl: 1,
             left: None,
             right: None,
         }
     }
 }
 
-/// Leftist Heap
+/// Leftist Heap, a max-heap by default or ordered by any comparator `C`
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::LeftistHeap;
+///
+/// let mut heap = LeftistHeap::new_min();
+/// heap.insert(3);
+/// heap.insert(1);
+/// heap.insert(2);
+/// assert_eq!(heap.remove(), Some(1));
+/// assert_eq!(heap.get(), Some(&2));
+/// ```
 #[derive(Debug)]
-pub struct LeftistHeap<T>(Option<Box<TreeNode<T>>>);
+pub struct LeftistHeap<T, C = MaxComparator>(Option<Box<TreeNode<T>>>, C);
 
-impl<T: Ord + Default> LeftistHeap<T> {
+impl<T, C: Compare<T> + Default> Default for LeftistHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> LeftistHeap<T> {
     /// Construct new instance
     pub fn new() -> Self {
-        Self(Some(Box::new(TreeNode::new())))
+        Self::with_comparator(MaxComparator)
     }
     /// merage algorithm
     pub fn merge(
+        left_tree: Option<Box<TreeNode<T>>>,
+        right_tree: Option<Box<TreeNode<T>>>,
+    ) -> Option<Box<TreeNode<T>>> {
+        LeftistHeap::merge_by(&MaxComparator, left_tree, right_tree)
+    }
+}
+
+impl<T: Ord> LeftistHeap<T, MinComparator> {
+    /// Construct new instance as a min-heap
+    pub fn new_min() -> Self {
+        Self::with_comparator(MinComparator)
+    }
+}
+
+impl<T, C: Compare<T>> LeftistHeap<T, C> {
+    /// Construct new instance ordered by `cmp`, the greatest element by `cmp`
+    /// being on top
+    pub fn with_comparator(cmp: C) -> Self {
+        Self(None, cmp)
+    }
+
+    /// merge algorithm, for trees ordered by `cmp`
+    pub fn merge_by(
+        cmp: &C,
         mut left_tree: Option<Box<TreeNode<T>>>,
         mut right_tree: Option<Box<TreeNode<T>>>,
     ) -> Option<Box<TreeNode<T>>> {
@@ -406,13 +554,16 @@ impl<T: Ord + Default> LeftistHeap<T> {
             return left_tree;
         }
 
-        if left_tree.as_ref().unwrap().elem < right_tree.as_ref().unwrap().elem {
+        if cmp.lt(
+            &left_tree.as_ref().unwrap().elem,
+            &right_tree.as_ref().unwrap().elem,
+        ) {
             swap(&mut left_tree, &mut right_tree);
         }
 
         if let Some(left_node) = left_tree.as_mut() {
             let right_subtree = left_node.right.take();
-            left_node.right = LeftistHeap::merge(right_subtree, right_tree);
+            left_node.right = LeftistHeap::merge_by(cmp, right_subtree, right_tree);
 
             if left_node.left.is_none()
                 || left_node.left.as_ref().unwrap().npl < left_node.right.as_ref().unwrap().npl
@@ -434,7 +585,7 @@ impl<T: Ord + Default> LeftistHeap<T> {
     pub fn insert(&mut self, elem: T) {
         let v = Some(Box::new(TreeNode::with_elem(elem)));
         let old_root = self.0.take();
-        self.0 = LeftistHeap::merge(old_root, v);
+        self.0 = LeftistHeap::merge_by(&self.1, old_root, v);
     }
 
     /// get node
@@ -448,11 +599,13 @@ impl<T: Ord + Default> LeftistHeap<T> {
 
     /// remove node
     pub fn remove(&mut self) -> Option<T> {
-        if let Some(mut root) = self.0.take() {
-            let (left, right) = (root.left.take(), root.right.take());
-            self.0 = LeftistHeap::merge(left, right);
+        if let Some(root) = self.0.take() {
+            let TreeNode {
+                elem, left, right, ..
+            } = *root;
+            self.0 = LeftistHeap::merge_by(&self.1, left, right);
 
-            Some(take(&mut root.elem))
+            Some(elem)
         } else {
             None
         }
@@ -462,6 +615,76 @@ impl<T: Ord + Default> LeftistHeap<T> {
     pub fn clear(&mut self) -> Option<Box<TreeNode<T>>> {
         self.0.take()
     }
+
+    /// Checks if the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Checks heap order, and that every node has npl one more than its right
+    /// child and no less than its left child
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        check_npl(&self.1, &self.0, 0).map(|_| ())
+    }
+}
+
+impl<T, C: Compare<T>> MergeableHeap<T> for LeftistHeap<T, C> {
+    type Peek<'a>
+        = &'a T
+    where
+        Self: 'a;
+
+    fn push(&mut self, item: T) {
+        self.insert(item);
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.get()
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.remove()
+    }
+
+    /// melds along the right paths, in *O*(log(*n*))
+    fn meld(&mut self, mut other: Self) {
+        let root = self.0.take();
+        self.0 = LeftistHeap::merge_by(&self.1, root, other.0.take());
+    }
+
+    fn is_empty(&self) -> bool {
+        LeftistHeap::is_empty(self)
+    }
+}
+
+/// check the subtree of `link`, return its npl, 0 for an empty one
+fn check_npl<T, C: Compare<T>>(
+    cmp: &C,
+    link: &Option<Box<TreeNode<T>>>,
+    depth: usize,
+) -> Result<i32, InvariantError> {
+    let node = match link.as_ref() {
+        Some(node) => node,
+        None => return Ok(0),
+    };
+    let left = check_npl(cmp, &node.left, depth + 1)?;
+    let right = check_npl(cmp, &node.right, depth + 1)?;
+
+    let above = |child: &Option<Box<TreeNode<T>>>| {
+        child.as_ref().is_some_and(|c| cmp.gt(&c.elem, &node.elem))
+    };
+    if above(&node.left) || above(&node.right) {
+        return Err(InvariantError::HeapOrder { depth: depth + 1 });
+    }
+    if left < right || node.npl != right + 1 {
+        return Err(InvariantError::Npl {
+            depth,
+            left,
+            right,
+            stored: node.npl,
+        });
+    }
+    Ok(node.npl)
 }
 
 #[cfg(test)]
@@ -469,6 +692,7 @@ mod tests {
     use super::*;
     #[test]
     fn test_leftistheap() {
+        assert_eq!(LeftistHeap::<i32>::new().get(), None);
         let mut leftheap = LeftistHeap::new();
         let array = [3, 4, 5, 2, 48, 19, 39, 390, 43, 12, 8, 9, 10, 7, 5];
         for &k in array.iter() {
@@ -479,8 +703,101 @@ mod tests {
         for &k in b.iter() {
             heap.insert(k);
         }
+        assert_eq!(leftheap.check_invariants(), Ok(()));
+        assert_eq!(heap.check_invariants(), Ok(()));
         let _ = LeftistHeap::merge(leftheap.clear(), heap.clear());
     }
+    #[test]
+    fn broken_leftist_heap() {
+        let mut heap = LeftistHeap::new();
+        for k in 1..4 {
+            heap.insert(k);
+        }
+        assert_eq!(heap.check_invariants(), Ok(()));
+
+        heap.0.as_mut().unwrap().npl += 1;
+        assert_eq!(
+            heap.check_invariants(),
+            Err(InvariantError::Npl {
+                depth: 0,
+                left: 1,
+                right: 0,
+                stored: 2
+            })
+        );
+        heap.0.as_mut().unwrap().npl -= 1;
+        heap.0.as_mut().unwrap().elem = -1;
+        assert_eq!(
+            heap.check_invariants(),
+            Err(InvariantError::HeapOrder { depth: 1 })
+        );
+    }
+
+    #[test]
+    fn comparator_heaps() {
+        let array = [3, 4, 5, 2, 48, 19, 39, 390, 43, 12, 8, 9, 10, 7, 5];
+        let mut sorted = array.to_vec();
+        sorted.sort_unstable();
+
+        let mut min_heap = BinaryHeap::from_vec_with_comparator(array.to_vec(), MinComparator);
+        let popped: Vec<_> = std::iter::from_fn(|| min_heap.pop()).collect();
+        assert_eq!(popped, sorted);
+
+        // greatest distance from 10 on top
+        let mut heap = BinaryHeap::with_comparator(|a: &i32, b: &i32| {
+            (a - 10).abs().cmp(&(b - 10).abs()).then(a.cmp(b))
+        });
+        for &k in array.iter() {
+            heap.push(k);
+        }
+        assert_eq!(heap.pop(), Some(390));
+        assert_eq!(heap.pop(), Some(48));
+        assert_eq!(heap.pop(), Some(43));
+
+        let (mut left, mut right) = (LeftistHeap::new_min(), LeftistHeap::new_min());
+        for (i, &k) in array.iter().enumerate() {
+            match i % 2 {
+                0 => left.insert(k),
+                _ => right.insert(k),
+            }
+        }
+        let mut merged = LeftistHeap::new_min();
+        merged.0 = LeftistHeap::merge_by(&MinComparator, left.clear(), right.clear());
+        assert_eq!(merged.check_invariants(), Ok(()));
+        let popped: Vec<_> = std::iter::from_fn(|| merged.remove()).collect();
+        assert_eq!(popped, sorted);
+    }
+
+    #[test]
+    fn heaps_without_default() {
+        use std::num::NonZeroU32;
+        let ids: Vec<_> = [7, 3, 9, 1, 3, 8]
+            .iter()
+            .map(|&i| NonZeroU32::new(i).unwrap())
+            .collect();
+        let sorted = [1, 3, 3, 7, 8, 9];
+
+        let mut array = ids.clone();
+        heap_sort(&mut array, 0, ids.len());
+        assert!(array.iter().map(|id| id.get()).eq(sorted.iter().copied()));
+
+        let mut heap = BinaryHeap::from(ids.clone());
+        let popped: Vec<_> = std::iter::from_fn(|| heap.pop())
+            .map(|id| id.get())
+            .collect();
+        assert!(popped.iter().rev().eq(sorted.iter()));
+
+        let mut heap = LeftistHeap::new_min();
+        for &id in ids.iter() {
+            heap.insert(id);
+        }
+        assert_eq!(heap.check_invariants(), Ok(()));
+        let popped: Vec<_> = std::iter::from_fn(|| heap.remove())
+            .map(|id| id.get())
+            .collect();
+        assert_eq!(popped, sorted);
+    }
+
     #[test]
     fn test_heap_sort() {
         let mut array = [3, 4, 5, 2, 48, 19, 39, 390, 43, 12, 8, 9, 10, 7, 5];
@@ -0,0 +1,381 @@
+//! A binary heap whose elements are reached through handles
+//!
+//! Every element pushed gets a [`HeapHandle`], and a position map from
+//! handles to heap indices is kept up to date as elements are swapped during
+//! sifts. This lets an element anywhere in the heap change its priority or
+//! be removed in *O*(log(*n*)), as Dijkstra's algorithm and event schedulers
+//! need. Handle slots are reused once their element leaves the heap, with a
+//! generation count so that a stale handle never reaches the new element.
+
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use crate::InvariantError;
+use std::cmp::Ordering;
+use std::mem::replace;
+
+/// Handle to an element of an [`IndexedBinaryHeap`], valid until the element
+/// is popped or removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeapHandle {
+    slot: usize,
+    generation: u32,
+}
+
+/// where the element of a handle slot is
+#[derive(Debug)]
+struct Slot {
+    generation: u32,
+    /// index in the heap, `None` once the element has left
+    pos: Option<usize>,
+}
+
+/// A priority queue with handles, a max-heap by default or ordered by any
+/// comparator `C`
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::IndexedBinaryHeap;
+///
+/// let mut queue = IndexedBinaryHeap::new_min();
+/// let a = queue.push(10);
+/// let b = queue.push(20);
+/// let c = queue.push(30);
+///
+/// // decrease-key
+/// queue.change_priority(c, 5);
+/// assert_eq!(queue.peek(), Some((c, &5)));
+///
+/// assert_eq!(queue.remove(a), Some(10));
+/// assert!(!queue.contains(a));
+/// assert_eq!(queue.pop(), Some((c, 5)));
+/// assert_eq!(queue.pop(), Some((b, 20)));
+/// assert_eq!(queue.pop(), None);
+/// ```
+///
+/// # Time complexity
+///
+/// | push, pop, change_priority, remove | peek, get, contains |
+/// |------------------------------------|---------------------|
+/// | *O*(log(*n*))                          | *O*(1)                |
+#[derive(Debug)]
+pub struct IndexedBinaryHeap<T, C = MaxComparator> {
+    /// elements in heap order, with the handle slot of each
+    data: Vec<(T, usize)>,
+    slots: Vec<Slot>,
+    /// slots whose element has left the heap
+    free: Vec<usize>,
+    cmp: C,
+}
+
+impl<T: Ord> IndexedBinaryHeap<T> {
+    /// Creates an empty `IndexedBinaryHeap` as a max-heap.
+    pub fn new() -> Self {
+        Self::with_comparator(MaxComparator)
+    }
+}
+
+impl<T: Ord> IndexedBinaryHeap<T, MinComparator> {
+    /// Creates an empty `IndexedBinaryHeap` as a min-heap.
+    pub fn new_min() -> Self {
+        Self::with_comparator(MinComparator)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for IndexedBinaryHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> IndexedBinaryHeap<T, C> {
+    /// Creates an empty `IndexedBinaryHeap` ordered by `cmp`, the greatest
+    /// item by `cmp` being on top.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            data: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            cmp,
+        }
+    }
+
+    /// Returns the number of elements in the heap.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Checks if the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Pushes an item onto the heap, returning its handle.
+    pub fn push(&mut self, item: T) -> HeapHandle {
+        let pos = self.data.len();
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot].pos = Some(pos);
+                slot
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    pos: Some(pos),
+                });
+                self.slots.len() - 1
+            }
+        };
+        self.data.push((item, slot));
+        self.sift_up(pos);
+        self.handle(slot)
+    }
+
+    /// Returns the greatest item and its handle, or `None` if the heap is
+    /// empty.
+    pub fn peek(&self) -> Option<(HeapHandle, &T)> {
+        self.data
+            .first()
+            .map(|(item, slot)| (self.handle(*slot), item))
+    }
+
+    /// Removes the greatest item from the heap and returns it with its
+    /// handle, or `None` if the heap is empty.
+    pub fn pop(&mut self) -> Option<(HeapHandle, T)> {
+        match self.data.is_empty() {
+            true => None,
+            false => {
+                let handle = self.handle(self.data[0].1);
+                Some((handle, self.remove_at(0)))
+            }
+        }
+    }
+
+    /// Returns `true` if the element of `handle` is still in the heap.
+    pub fn contains(&self, handle: HeapHandle) -> bool {
+        self.pos(handle).is_some()
+    }
+
+    /// Returns the item of `handle`, or `None` if it has left the heap.
+    pub fn get(&self, handle: HeapHandle) -> Option<&T> {
+        self.pos(handle).map(|pos| &self.data[pos].0)
+    }
+
+    /// Replaces the item of `handle` by `item` and moves it to its new place,
+    /// returning the old item, or `None`, leaving the heap as it was, if the
+    /// element has left the heap.
+    pub fn change_priority(&mut self, handle: HeapHandle, item: T) -> Option<T> {
+        let pos = self.pos(handle)?;
+        let old = replace(&mut self.data[pos].0, item);
+        match self.cmp.compare(&self.data[pos].0, &old) {
+            Ordering::Greater => self.sift_up(pos),
+            Ordering::Less => self.sift_down(pos),
+            Ordering::Equal => {}
+        }
+        Some(old)
+    }
+
+    /// Removes the element of `handle` from the heap and returns its item,
+    /// or `None` if it has left the heap already.
+    pub fn remove(&mut self, handle: HeapHandle) -> Option<T> {
+        let pos = self.pos(handle)?;
+        Some(self.remove_at(pos))
+    }
+
+    /// Checks heap order, and that the position map agrees with the heap
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        for (pos, (item, slot)) in self.data.iter().enumerate() {
+            if pos > 0 && self.cmp.gt(item, &self.data[(pos - 1) / 2].0) {
+                return Err(InvariantError::HeapOrder { depth: depth(pos) });
+            }
+            if self.slots[*slot].pos != Some(pos) {
+                return Err(InvariantError::Position {
+                    index: pos,
+                    stored: self.slots[*slot].pos,
+                });
+            }
+        }
+        let in_heap = self.slots.iter().filter(|slot| slot.pos.is_some()).count();
+        if in_heap != self.data.len() {
+            return Err(InvariantError::Size {
+                stored: in_heap,
+                actual: self.data.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// current handle of a slot
+    fn handle(&self, slot: usize) -> HeapHandle {
+        HeapHandle {
+            slot,
+            generation: self.slots[slot].generation,
+        }
+    }
+
+    /// heap index of the element of `handle`, if still in the heap
+    fn pos(&self, handle: HeapHandle) -> Option<usize> {
+        self.slots
+            .get(handle.slot)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.pos)
+    }
+
+    /// swap two elements, keeping the position map in step
+    fn swap(&mut self, a: usize, b: usize) {
+        self.data.swap(a, b);
+        self.slots[self.data[a].1].pos = Some(a);
+        self.slots[self.data[b].1].pos = Some(b);
+    }
+
+    /// move the last element into `pos`, free the slot of the one there and
+    /// restore heap order
+    fn remove_at(&mut self, pos: usize) -> T {
+        let last = self.data.len() - 1;
+        self.swap(pos, last);
+        let (item, slot) = self.data.pop().unwrap();
+        self.slots[slot].pos = None;
+        self.slots[slot].generation = self.slots[slot].generation.wrapping_add(1);
+        self.free.push(slot);
+        if pos < self.data.len() {
+            // the element moved in may belong above or below
+            self.sift_up(pos);
+            self.sift_down(pos);
+        }
+        item
+    }
+
+    /// sift up, swapping the element at `pos` with its parent until in order
+    fn sift_up(&mut self, mut pos: usize) {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if self.cmp.ge(&self.data[parent].0, &self.data[pos].0) {
+                break;
+            }
+            self.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    /// sift down, swapping the element at `pos` with its greater child until
+    /// in order
+    fn sift_down(&mut self, mut pos: usize) {
+        let end = self.data.len();
+        let mut child = 2 * pos + 1;
+        while child < end {
+            let right = child + 1;
+            if right < end && self.cmp.le(&self.data[child].0, &self.data[right].0) {
+                child = right;
+            }
+            if self.cmp.ge(&self.data[pos].0, &self.data[child].0) {
+                break;
+            }
+            self.swap(pos, child);
+            pos = child;
+            child = 2 * pos + 1;
+        }
+    }
+}
+
+/// depth of heap index `pos`, the root being at 0
+fn depth(pos: usize) -> usize {
+    (usize::BITS - 1 - (pos + 1).leading_zeros()) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_and_remove() {
+        let mut heap = IndexedBinaryHeap::new();
+        let handles: Vec<_> = (0..100).map(|i| heap.push(i * 7 % 100)).collect();
+        assert_eq!(heap.check_invariants(), Ok(()));
+
+        // increase-key to the top, decrease-key to the bottom
+        assert_eq!(heap.change_priority(handles[3], 1000), Some(21));
+        assert_eq!(heap.peek(), Some((handles[3], &1000)));
+        assert_eq!(heap.change_priority(handles[3], -1), Some(1000));
+        assert_eq!(heap.check_invariants(), Ok(()));
+
+        for handle in handles.iter().step_by(2) {
+            assert!(heap.remove(*handle).is_some());
+            assert!(!heap.contains(*handle));
+            assert_eq!(heap.check_invariants(), Ok(()));
+        }
+        assert_eq!(heap.len(), 50);
+        assert_eq!(heap.get(handles[3]), Some(&-1));
+
+        let mut popped = Vec::new();
+        while let Some((handle, item)) = heap.pop() {
+            assert_eq!(heap.get(handle), None);
+            popped.push(item);
+        }
+        assert!(popped.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(popped.last(), Some(&-1));
+    }
+
+    #[test]
+    fn stale_handles() {
+        let mut heap = IndexedBinaryHeap::new_min();
+        let a = heap.push("a");
+        assert_eq!(heap.pop(), Some((a, "a")));
+        // the slot of `a` is reused, but `a` does not reach the new element
+        let b = heap.push("b");
+        assert!(!heap.contains(a));
+        assert_eq!(heap.remove(a), None);
+        assert_eq!(heap.change_priority(a, "z"), None);
+        assert_eq!(heap.get(b), Some(&"b"));
+    }
+
+    #[test]
+    fn dijkstra() {
+        // weighted edges of a small graph
+        let edges = [
+            (0, 1, 7),
+            (0, 2, 9),
+            (0, 5, 14),
+            (1, 2, 10),
+            (1, 3, 15),
+            (2, 3, 11),
+            (2, 5, 2),
+            (3, 4, 6),
+            (4, 5, 9),
+        ];
+        let mut dist = [u32::MAX; 6];
+        let mut queue = IndexedBinaryHeap::new_min();
+        let handles: Vec<_> = (0..6)
+            .map(|v| queue.push((if v == 0 { 0 } else { u32::MAX }, v)))
+            .collect();
+        while let Some((_, (d, u))) = queue.pop() {
+            dist[u] = d;
+            for &(a, b, w) in edges.iter() {
+                let v = match (a == u, b == u) {
+                    (true, _) => b,
+                    (_, true) => a,
+                    _ => continue,
+                };
+                if queue.get(handles[v]).is_some_and(|&(dv, _)| d + w < dv) {
+                    queue.change_priority(handles[v], (d + w, v));
+                }
+            }
+            assert_eq!(queue.check_invariants(), Ok(()));
+        }
+        assert_eq!(dist, [0, 7, 9, 20, 20, 11]);
+    }
+
+    #[test]
+    fn broken_position_map() {
+        let mut heap = IndexedBinaryHeap::new();
+        for i in 0..4 {
+            heap.push(i);
+        }
+        heap.slots[heap.data[2].1].pos = Some(1);
+        assert_eq!(
+            heap.check_invariants(),
+            Err(InvariantError::Position {
+                index: 2,
+                stored: Some(1)
+            })
+        );
+    }
+}
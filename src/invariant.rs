@@ -0,0 +1,290 @@
+//! Structural invariant checking
+//!
+//! Nodes are located by `depth` (root is 0) and `index`, the in-order
+//! position of the key in the tree, so no `Debug` bound is needed on keys.
+
+use crate::node::Color;
+use crate::TreeNode;
+use std::error::Error;
+use std::fmt;
+
+/// A broken structural invariant, with where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantError {
+    /// key at `index` is not greater than the one before it
+    KeyOrder {
+        /// in-order position of the key
+        index: usize,
+    },
+    /// stored height does not match the subtree
+    Height {
+        /// in-order position of the node
+        index: usize,
+        /// height stored in the node
+        stored: usize,
+        /// height of the subtree
+        actual: usize,
+    },
+    /// balance factor out of [-1, 1]
+    Unbalanced {
+        /// in-order position of the node
+        index: usize,
+        /// left height minus right height
+        factor: isize,
+    },
+    /// stored size does not match the number of entries
+    Size {
+        /// size stored in the node or tree
+        stored: usize,
+        /// number of entries counted
+        actual: usize,
+    },
+    /// root of a red-black tree is red
+    RedRoot,
+    /// red node with a red child
+    DoubleRed {
+        /// in-order position of the red parent
+        index: usize,
+    },
+    /// paths from a node down to its leaves cross different numbers of black nodes
+    BlackHeight {
+        /// in-order position of the node
+        index: usize,
+        /// black height of the left subtree
+        left: usize,
+        /// black height of the right subtree
+        right: usize,
+    },
+    /// B-tree node holding too few or too many keys
+    KeyCount {
+        /// depth of the node
+        depth: usize,
+        /// number of keys in the node
+        count: usize,
+        /// least number of keys allowed
+        min: usize,
+        /// greatest number of keys allowed
+        max: usize,
+    },
+    /// B-tree node whose children do not fit its keys
+    ChildCount {
+        /// depth of the node
+        depth: usize,
+        /// number of keys in the node
+        keys: usize,
+        /// number of children in the node
+        children: usize,
+    },
+    /// B-tree node with a different number of values and keys
+    ValueCount {
+        /// depth of the node
+        depth: usize,
+        /// number of keys in the node
+        keys: usize,
+        /// number of values in the node
+        vals: usize,
+    },
+    /// B-tree leaves on different levels
+    LeafDepth {
+        /// depth of the first leaf
+        expected: usize,
+        /// depth of the offending leaf
+        actual: usize,
+    },
+    /// heap node less than one of its children
+    HeapOrder {
+        /// depth of the child
+        depth: usize,
+    },
+    /// leftist heap node with a wrong null path length
+    Npl {
+        /// depth of the node
+        depth: usize,
+        /// npl of the left child
+        left: i32,
+        /// npl of the right child
+        right: i32,
+        /// npl stored in the node
+        stored: i32,
+    },
+    /// heap node whose back link misses its parent or elder sibling
+    BackLink {
+        /// depth of the node
+        depth: usize,
+    },
+    /// indexed heap element whose handle maps to another position
+    Position {
+        /// index of the element in the heap
+        index: usize,
+        /// position the handle map stores for it
+        stored: Option<usize>,
+    },
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyOrder { index } => write!(f, "key #{} is out of order", index),
+            Self::Height {
+                index,
+                stored,
+                actual,
+            } => write!(
+                f,
+                "node #{} stores height {} but has height {}",
+                index, stored, actual
+            ),
+            Self::Unbalanced { index, factor } => {
+                write!(f, "node #{} has balance factor {}", index, factor)
+            }
+            Self::Size { stored, actual } => {
+                write!(f, "size is {} but {} entries found", stored, actual)
+            }
+            Self::RedRoot => write!(f, "root is red"),
+            Self::DoubleRed { index } => write!(f, "red node #{} has a red child", index),
+            Self::BlackHeight { index, left, right } => write!(
+                f,
+                "node #{} has black heights {} on the left and {} on the right",
+                index, left, right
+            ),
+            Self::KeyCount {
+                depth,
+                count,
+                min,
+                max,
+            } => write!(
+                f,
+                "node at depth {} has {} keys, not in [{}, {}]",
+                depth, count, min, max
+            ),
+            Self::ChildCount {
+                depth,
+                keys,
+                children,
+            } => write!(
+                f,
+                "node at depth {} has {} keys but {} children",
+                depth, keys, children
+            ),
+            Self::ValueCount { depth, keys, vals } => write!(
+                f,
+                "node at depth {} has {} keys but {} values",
+                depth, keys, vals
+            ),
+            Self::LeafDepth { expected, actual } => {
+                write!(f, "leaf at depth {}, expected {}", actual, expected)
+            }
+            Self::HeapOrder { depth } => {
+                write!(f, "node at depth {} is greater than its parent", depth)
+            }
+            Self::Npl {
+                depth,
+                left,
+                right,
+                stored,
+            } => write!(
+                f,
+                "node at depth {} has npl {} with children npl {} and {}",
+                depth, stored, left, right
+            ),
+            Self::BackLink { depth } => {
+                write!(f, "node at depth {} has a wrong back link", depth)
+            }
+            Self::Position { index, stored } => {
+                write!(f, "element #{} has handle position {:?}", index, stored)
+            }
+        }
+    }
+}
+
+impl Error for InvariantError {}
+
+/// keys strictly increase in order, walked by hand as the iterators rely on
+/// the order to stop
+pub(crate) fn check_order<K: Ord, V>(root: Option<&TreeNode<K, V>>) -> Result<(), InvariantError> {
+    let (mut stack, mut ptr) = (Vec::new(), root);
+    let mut prev: Option<&K> = None;
+    let mut index = 0;
+    loop {
+        while let Some(node) = ptr {
+            stack.push(node);
+            ptr = node.left.as_deref();
+        }
+        let node = match stack.pop() {
+            Some(node) => node,
+            None => return Ok(()),
+        };
+        if prev.is_some_and(|prev| *prev >= node.key) {
+            return Err(InvariantError::KeyOrder { index });
+        }
+        prev = Some(&node.key);
+        index += 1;
+        ptr = node.right.as_deref();
+    }
+}
+
+/// stored height and size, and balance factor in [-1, 1],
+/// `offset` is the in-order position of the leftmost node,
+/// return the height and size of the subtree
+pub(crate) fn check_balance<K, V>(
+    link: &Option<Box<TreeNode<K, V>>>,
+    offset: usize,
+) -> Result<(usize, usize), InvariantError> {
+    let node = match link.as_ref() {
+        Some(node) => node,
+        None => return Ok((0, 0)),
+    };
+    let (left_height, left_size) = check_balance(&node.left, offset)?;
+    let index = offset + left_size;
+    let (right_height, right_size) = check_balance(&node.right, index + 1)?;
+
+    let height = 1 + left_height.max(right_height);
+    if node.height != height {
+        return Err(InvariantError::Height {
+            index,
+            stored: node.height,
+            actual: height,
+        });
+    }
+    let size = 1 + left_size + right_size;
+    if node.size != size {
+        return Err(InvariantError::Size {
+            stored: node.size,
+            actual: size,
+        });
+    }
+    let factor = left_height as isize - right_height as isize;
+    if !(-1..=1).contains(&factor) {
+        return Err(InvariantError::Unbalanced { index, factor });
+    }
+    Ok((height, size))
+}
+
+/// no red node has a red child and every path has the same number of black
+/// nodes, return the black height and size of the subtree
+pub(crate) fn check_colors<K, V>(
+    link: &Option<Box<TreeNode<K, V>>>,
+    offset: usize,
+) -> Result<(usize, usize), InvariantError> {
+    let node = match link.as_ref() {
+        Some(node) => node,
+        None => return Ok((1, 0)),
+    };
+    let (left, left_size) = check_colors(&node.left, offset)?;
+    let index = offset + left_size;
+    let (right, right_size) = check_colors(&node.right, index + 1)?;
+
+    let is_red = |link: &Option<Box<TreeNode<K, V>>>| {
+        link.as_ref().is_some_and(|node| node.color == Color::Red)
+    };
+    if is_red(link) && (is_red(&node.left) || is_red(&node.right)) {
+        return Err(InvariantError::DoubleRed { index });
+    }
+    if left != right {
+        return Err(InvariantError::BlackHeight { index, left, right });
+    }
+    Ok((
+        left + (node.color == Color::Black) as usize,
+        1 + left_size + right_size,
+    ))
+}
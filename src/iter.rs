@@ -0,0 +1,647 @@
+//! In-order traversal iterators over `TreeNode` and `BTNode` trees
+
+use crate::{BTNode, TreeNode};
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+use std::{slice, vec};
+
+/// whether `key` is not below the lower bound
+fn above_lower<K, T>(key: &K, bound: Bound<&T>) -> bool
+where
+    K: Borrow<T>,
+    T: Ord + ?Sized,
+{
+    match bound {
+        Bound::Included(start) => key.borrow() >= start,
+        Bound::Excluded(start) => key.borrow() > start,
+        Bound::Unbounded => true,
+    }
+}
+
+/// whether `key` is not beyond the upper bound
+fn below_upper<K, T>(key: &K, bound: Bound<&T>) -> bool
+where
+    K: Borrow<T>,
+    T: Ord + ?Sized,
+{
+    match bound {
+        Bound::Included(end) => key.borrow() <= end,
+        Bound::Excluded(end) => key.borrow() < end,
+        Bound::Unbounded => true,
+    }
+}
+
+/// Double-ended iterator over a sub-range of a binary tree, in key order
+///
+/// `front` and `back` hold the paths to the next element of each end, so
+/// the iterator is done once the two ends meet on the same node.
+#[derive(Debug)]
+pub struct TreeRange<'a, K, V> {
+    front: Vec<&'a TreeNode<K, V>>,
+    back: Vec<&'a TreeNode<K, V>>,
+    finished: bool,
+}
+
+impl<'a, K: Ord, V> TreeRange<'a, K, V> {
+    pub(crate) fn new<T, R>(root: Option<&'a TreeNode<K, V>>, range: R) -> Self
+    where
+        K: Borrow<T>,
+        T: Ord + ?Sized,
+        R: RangeBounds<T>,
+    {
+        let mut front = Vec::new();
+        let mut ptr = root;
+        while let Some(node) = ptr {
+            if above_lower(&node.key, range.start_bound()) {
+                front.push(node);
+                ptr = node.left.as_deref();
+            } else {
+                ptr = node.right.as_deref();
+            }
+        }
+
+        let mut back = Vec::new();
+        let mut ptr = root;
+        while let Some(node) = ptr {
+            if below_upper(&node.key, range.end_bound()) {
+                back.push(node);
+                ptr = node.right.as_deref();
+            } else {
+                ptr = node.left.as_deref();
+            }
+        }
+
+        let finished = match (front.last(), back.last()) {
+            (Some(first), Some(last)) => first.key > last.key,
+            _ => true,
+        };
+        Self {
+            front,
+            back,
+            finished,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for TreeRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let node = self.front.pop()?;
+        let mut ptr = node.right.as_deref();
+        while let Some(next_node) = ptr {
+            self.front.push(next_node);
+            ptr = next_node.left.as_deref();
+        }
+
+        if self
+            .back
+            .last()
+            .is_none_or(|last| std::ptr::eq(*last, node))
+        {
+            self.finished = true;
+        }
+        Some((&node.key, &node.val))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for TreeRange<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let node = self.back.pop()?;
+        let mut ptr = node.left.as_deref();
+        while let Some(next_node) = ptr {
+            self.back.push(next_node);
+            ptr = next_node.right.as_deref();
+        }
+
+        if self
+            .front
+            .last()
+            .is_none_or(|first| std::ptr::eq(*first, node))
+        {
+            self.finished = true;
+        }
+        Some((&node.key, &node.val))
+    }
+}
+
+/// Double-ended iterator over all entries of a binary tree, in key order
+#[derive(Debug)]
+pub struct TreeIter<'a, K, V>(TreeRange<'a, K, V>);
+
+impl<'a, K: Ord, V> TreeIter<'a, K, V> {
+    pub(crate) fn new(root: Option<&'a TreeNode<K, V>>) -> Self {
+        Self(TreeRange::new::<K, _>(root, ..))
+    }
+}
+
+impl<'a, K, V> Iterator for TreeIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for TreeIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+/// pending node split into key, value and the right subtree
+type TreeFrameMut<'a, K, V> = (&'a K, &'a mut V, Option<&'a mut TreeNode<K, V>>);
+
+/// Mutable iterator over the entries of a binary tree, in key order
+#[derive(Debug)]
+pub struct TreeIterMut<'a, K, V> {
+    stack: Vec<TreeFrameMut<'a, K, V>>,
+}
+
+impl<'a, K, V> TreeIterMut<'a, K, V> {
+    pub(crate) fn new(root: Option<&'a mut TreeNode<K, V>>) -> Self {
+        let mut iter = Self { stack: Vec::new() };
+        iter.push_left(root);
+        iter
+    }
+
+    fn push_left(&mut self, mut ptr: Option<&'a mut TreeNode<K, V>>) {
+        while let Some(node) = ptr {
+            let TreeNode {
+                key,
+                val,
+                left,
+                right,
+                ..
+            } = node;
+            self.stack.push((key, val, right.as_deref_mut()));
+            ptr = left.as_deref_mut();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for TreeIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, val, right) = self.stack.pop()?;
+        self.push_left(right);
+        Some((key, val))
+    }
+}
+
+/// Owning iterator over the entries of a binary tree, in key order
+#[derive(Debug)]
+pub struct TreeIntoIter<K, V> {
+    stack: Vec<Box<TreeNode<K, V>>>,
+}
+
+impl<K, V> TreeIntoIter<K, V> {
+    pub(crate) fn new(root: Option<Box<TreeNode<K, V>>>) -> Self {
+        let mut iter = Self { stack: Vec::new() };
+        iter.push_left(root);
+        iter
+    }
+
+    fn push_left(&mut self, mut ptr: Option<Box<TreeNode<K, V>>>) {
+        while let Some(mut node) = ptr {
+            ptr = node.left.take();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<K, V> Iterator for TreeIntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut node = self.stack.pop()?;
+        self.push_left(node.right.take());
+        let node = *node;
+        Some((node.key, node.val))
+    }
+}
+
+/// Double-ended iterator over a sub-range of a B-tree, in key order
+///
+/// Every stack frame is a node with the index of the next key that end
+/// will yield, `keys[idx]` for the front and `keys[idx - 1]` for the back.
+#[derive(Debug)]
+pub struct BTreeRange<'a, K, V> {
+    front: Vec<(&'a BTNode<K, V>, usize)>,
+    back: Vec<(&'a BTNode<K, V>, usize)>,
+    finished: bool,
+}
+
+impl<'a, K: Ord, V> BTreeRange<'a, K, V> {
+    pub(crate) fn new<T, R>(root: Option<&'a BTNode<K, V>>, range: R) -> Self
+    where
+        K: Borrow<T>,
+        T: Ord + ?Sized,
+        R: RangeBounds<T>,
+    {
+        let mut front = Vec::new();
+        let mut ptr = root;
+        while let Some(node) = ptr {
+            let idx = node
+                .keys
+                .partition_point(|key| !above_lower(key, range.start_bound()));
+            front.push((node, idx));
+            ptr = node.children.get(idx).and_then(|child| child.as_deref());
+        }
+
+        let mut back = Vec::new();
+        let mut ptr = root;
+        while let Some(node) = ptr {
+            let idx = node
+                .keys
+                .partition_point(|key| below_upper(key, range.end_bound()));
+            back.push((node, idx));
+            ptr = node.children.get(idx).and_then(|child| child.as_deref());
+        }
+
+        let mut iter = Self {
+            front,
+            back,
+            finished: false,
+        };
+        iter.normalize_front();
+        iter.normalize_back();
+        iter.finished = match (iter.front.last(), iter.back.last()) {
+            (Some(&(first, i)), Some(&(last, j))) => first.keys[i] > last.keys[j - 1],
+            _ => true,
+        };
+        iter
+    }
+}
+
+impl<'a, K, V> BTreeRange<'a, K, V> {
+    /// drop exhausted frames so the top frame points to the next key
+    fn normalize_front(&mut self) {
+        while let Some(&(node, idx)) = self.front.last() {
+            if idx < node.keys.len() {
+                break;
+            }
+            self.front.pop();
+        }
+    }
+
+    /// drop exhausted frames so the top frame points to the next key
+    fn normalize_back(&mut self) {
+        while let Some(&(_, idx)) = self.back.last() {
+            if idx > 0 {
+                break;
+            }
+            self.back.pop();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for BTreeRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let (node, idx) = self.front.pop()?;
+        self.front.push((node, idx + 1));
+        let mut ptr = node
+            .children
+            .get(idx + 1)
+            .and_then(|child| child.as_deref());
+        while let Some(child) = ptr {
+            self.front.push((child, 0));
+            ptr = child.children.first().and_then(|child| child.as_deref());
+        }
+        self.normalize_front();
+
+        if self
+            .back
+            .last()
+            .is_none_or(|&(last, j)| std::ptr::eq(last, node) && j == idx + 1)
+        {
+            self.finished = true;
+        }
+        Some((&node.keys[idx], &node.vals[idx]))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for BTreeRange<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let (node, idx) = self.back.pop()?;
+        self.back.push((node, idx - 1));
+        let mut ptr = node
+            .children
+            .get(idx - 1)
+            .and_then(|child| child.as_deref());
+        while let Some(child) = ptr {
+            self.back.push((child, child.keys.len()));
+            ptr = child.children.last().and_then(|child| child.as_deref());
+        }
+        self.normalize_back();
+
+        if self
+            .front
+            .last()
+            .is_none_or(|&(first, i)| std::ptr::eq(first, node) && i == idx - 1)
+        {
+            self.finished = true;
+        }
+        Some((&node.keys[idx - 1], &node.vals[idx - 1]))
+    }
+}
+
+/// Double-ended iterator over all entries of a B-tree, in key order
+#[derive(Debug)]
+pub struct BTreeIter<'a, K, V>(BTreeRange<'a, K, V>);
+
+impl<'a, K: Ord, V> BTreeIter<'a, K, V> {
+    pub(crate) fn new(root: Option<&'a BTNode<K, V>>) -> Self {
+        Self(BTreeRange::new::<K, _>(root, ..))
+    }
+}
+
+impl<'a, K, V> Iterator for BTreeIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for BTreeIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+type BTFrameMut<'a, K, V> = (
+    slice::Iter<'a, K>,
+    slice::IterMut<'a, V>,
+    slice::IterMut<'a, Option<Box<BTNode<K, V>>>>,
+);
+
+/// Mutable iterator over the entries of a B-tree, in key order
+#[derive(Debug)]
+pub struct BTreeIterMut<'a, K, V> {
+    stack: Vec<BTFrameMut<'a, K, V>>,
+}
+
+impl<'a, K, V> BTreeIterMut<'a, K, V> {
+    pub(crate) fn new(root: Option<&'a mut BTNode<K, V>>) -> Self {
+        let mut iter = Self { stack: Vec::new() };
+        iter.push_left(root);
+        iter
+    }
+
+    fn push_left(&mut self, mut ptr: Option<&'a mut BTNode<K, V>>) {
+        while let Some(node) = ptr {
+            let BTNode {
+                keys,
+                vals,
+                children,
+            } = node;
+            let mut children = children.iter_mut();
+            ptr = children.next().and_then(|child| child.as_deref_mut());
+            self.stack.push((keys.iter(), vals.iter_mut(), children));
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for BTreeIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (keys, vals, children) = self.stack.last_mut()?;
+            if let (Some(key), Some(val)) = (keys.next(), vals.next()) {
+                let child = children.next().and_then(|child| child.as_deref_mut());
+                self.push_left(child);
+                return Some((key, val));
+            }
+            self.stack.pop();
+        }
+    }
+}
+
+type BTFrame<K, V> = (
+    vec::IntoIter<K>,
+    vec::IntoIter<V>,
+    vec::IntoIter<Option<Box<BTNode<K, V>>>>,
+);
+
+/// Owning iterator over the entries of a B-tree, in key order
+#[derive(Debug)]
+pub struct BTreeIntoIter<K, V> {
+    stack: Vec<BTFrame<K, V>>,
+}
+
+impl<K, V> BTreeIntoIter<K, V> {
+    pub(crate) fn new(root: Option<Box<BTNode<K, V>>>) -> Self {
+        let mut iter = Self { stack: Vec::new() };
+        iter.push_left(root);
+        iter
+    }
+
+    fn push_left(&mut self, mut ptr: Option<Box<BTNode<K, V>>>) {
+        while let Some(node) = ptr {
+            let BTNode {
+                keys,
+                vals,
+                children,
+            } = *node;
+            let mut children = children.into_iter();
+            ptr = children.next().flatten();
+            self.stack
+                .push((keys.into_iter(), vals.into_iter(), children));
+        }
+    }
+}
+
+impl<K, V> Iterator for BTreeIntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (keys, vals, children) = self.stack.last_mut()?;
+            if let (Some(key), Some(val)) = (keys.next(), vals.next()) {
+                let child = children.next().flatten();
+                self.push_left(child);
+                return Some((key, val));
+            }
+            self.stack.pop();
+        }
+    }
+}
+
+/// Iterator over the keys of a tree, in key order, or of a map
+#[derive(Debug)]
+pub struct Keys<I>(pub(crate) I);
+
+impl<'a, K: 'a, V: 'a, I> Iterator for Keys<I>
+where
+    I: Iterator<Item = (&'a K, &'a V)>,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.0.next().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> DoubleEndedIterator for Keys<I>
+where
+    I: DoubleEndedIterator<Item = (&'a K, &'a V)>,
+{
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.0.next_back().map(|(key, _)| key)
+    }
+}
+
+/// Iterator over the values of a tree, in key order, or of a map
+#[derive(Debug)]
+pub struct Values<I>(pub(crate) I);
+
+impl<'a, K: 'a, V: 'a, I> Iterator for Values<I>
+where
+    I: Iterator<Item = (&'a K, &'a V)>,
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.0.next().map(|(_, val)| val)
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> DoubleEndedIterator for Values<I>
+where
+    I: DoubleEndedIterator<Item = (&'a K, &'a V)>,
+{
+    fn next_back(&mut self) -> Option<&'a V> {
+        self.0.next_back().map(|(_, val)| val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AVLTree, BTree, BinarySearchTree, SplayTree, BST};
+
+    const KEYS: [i32; 16] = [10, 4, 8, 5, 9, 3, 2, 15, 13, 11, 18, 20, 19, 12, 21, 1];
+
+    fn sorted() -> Vec<i32> {
+        let mut keys = KEYS.to_vec();
+        keys.sort();
+        keys
+    }
+
+    fn fill<T: BST<i32, i32>>(tree: &mut T) {
+        for &k in KEYS.iter() {
+            tree.insert(k, k * 10);
+        }
+    }
+
+    #[test]
+    fn tree_iterators() {
+        let mut avl = AVLTree::new(10, 100);
+        fill(&mut avl);
+        let keys: Vec<i32> = avl.keys().copied().collect();
+        assert_eq!(keys, sorted());
+        let rev: Vec<i32> = avl.values().rev().copied().collect();
+        assert_eq!(
+            rev,
+            sorted().iter().rev().map(|k| k * 10).collect::<Vec<_>>()
+        );
+
+        let range: Vec<i32> = avl.range(4..12).map(|(k, _)| *k).collect();
+        assert_eq!(range, vec![4, 5, 8, 9, 10, 11]);
+        let range: Vec<i32> = avl.range(6..=13).rev().map(|(k, _)| *k).collect();
+        assert_eq!(range, vec![13, 12, 11, 10, 9, 8]);
+        assert_eq!(avl.range(6..8).next(), None);
+        assert_eq!(avl.range(30..).next(), None);
+
+        let mut range = avl.range(..=5);
+        assert_eq!(range.next(), Some((&1, &10)));
+        assert_eq!(range.next_back(), Some((&5, &50)));
+        assert_eq!(range.next(), Some((&2, &20)));
+        assert_eq!(range.next_back(), Some((&4, &40)));
+        assert_eq!(range.next(), Some((&3, &30)));
+        assert_eq!(range.next_back(), None);
+        assert_eq!(range.next(), None);
+
+        for (_, val) in avl.iter_mut() {
+            *val += 1;
+        }
+        let mut bst = BinarySearchTree::new(10, 100);
+        fill(&mut bst);
+        for (_, val) in &mut bst {
+            *val += 1;
+        }
+        let mut splay = SplayTree::new(10, 100);
+        fill(&mut splay);
+        for (_, val) in splay.iter_mut() {
+            *val += 1;
+        }
+
+        let expected: Vec<(i32, i32)> = sorted().into_iter().map(|k| (k, k * 10 + 1)).collect();
+        assert_eq!(avl.into_iter().collect::<Vec<_>>(), expected);
+        assert_eq!(bst.into_iter().collect::<Vec<_>>(), expected);
+        assert_eq!(splay.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn btree_iterators() {
+        for order in 3..10 {
+            let mut bst = BTree::new(0, order);
+            fill(&mut bst);
+            let keys: Vec<i32> = bst.keys().copied().collect();
+            assert_eq!(keys, sorted());
+            let rev: Vec<i32> = bst.keys().rev().copied().collect();
+            assert_eq!(rev, sorted().into_iter().rev().collect::<Vec<_>>());
+
+            for lo in 0..23 {
+                for hi in lo..23 {
+                    let expected: Vec<i32> = sorted()
+                        .into_iter()
+                        .filter(|k| lo <= *k && *k < hi)
+                        .collect();
+                    let range: Vec<i32> = bst.range(lo..hi).map(|(k, _)| *k).collect();
+                    assert_eq!(range, expected);
+                    let mut range: Vec<i32> = bst.range(lo..hi).rev().map(|(k, _)| *k).collect();
+                    range.reverse();
+                    assert_eq!(range, expected);
+
+                    let mut zigzag = Vec::new();
+                    let mut range = bst.range(lo..hi);
+                    while let Some((k, _)) = range.next() {
+                        zigzag.push(*k);
+                        if let Some((k, _)) = range.next_back() {
+                            zigzag.push(*k);
+                        }
+                    }
+                    zigzag.sort();
+                    assert_eq!(zigzag, expected);
+                }
+            }
+
+            for val in bst.iter_mut().map(|(_, val)| val) {
+                *val += 1;
+            }
+            let expected: Vec<(i32, i32)> = sorted().into_iter().map(|k| (k, k * 10 + 1)).collect();
+            assert_eq!(bst.into_iter().collect::<Vec<_>>(), expected);
+        }
+
+        let empty: BTree<i32, i32> = BTree::new(0, 3);
+        assert_eq!(empty.iter().next(), None);
+        assert_eq!(empty.range(..).next_back(), None);
+    }
+}
@@ -2,39 +2,174 @@
 
 #![doc(html_playground_url = "https://play.rust-lang.org/")]
 #![deny(future_incompatible, nonstandard_style, warnings, missing_docs)]
-
+#![forbid(unsafe_code)]
 
 pub use avl::AVLTree;
 pub use binary::BinarySearchTree;
+pub use binomial::BinomialHeap;
 pub use btree::BTree;
-pub use hashmap::HashMap;
-pub use heap::{heap_sort, BinaryHeap, LeftistHeap};
+pub use chained::{Chained, ChainedHashMap};
+pub use compare::{ByKey, Compare, MaxComparator, MinComparator};
+pub use concurrent::ConcurrentHashMap;
+pub use cuckoo::{Cuckoo, CuckooHashMap};
+pub use dary::{dary_heap_sort, DaryHeap};
+pub use fibonacci::{FibonacciHandle, FibonacciHeap};
+pub use hasher::{FxBuildHasher, FxHasher, RandomState, SipHasher};
+pub use hashmap::{
+    Entry, HashMap, HashMapDrain, HashMapIntoIter, HashMapIter, HashMapIterMut, OccupiedEntry,
+    Quadratic, Table, VacantEntry,
+};
+pub use heap::{heap_sort, BinaryHeap, LeftistHeap, MergeableHeap};
+pub use indexed::{HeapHandle, IndexedBinaryHeap};
+pub use invariant::InvariantError;
+pub use iter::{
+    BTreeIntoIter, BTreeIter, BTreeIterMut, BTreeRange, Keys, TreeIntoIter, TreeIter, TreeIterMut,
+    TreeRange, Values,
+};
 pub use node::{BTNode, TreeNode};
+pub use pairing::{PairingHandle, PairingHeap};
+pub use rbtree::RedBlackTree;
+pub use robinhood::{RobinHood, RobinHoodHashMap};
+pub use skew::SkewHeap;
 pub use sort::*;
 pub use splay::SplayTree;
+pub use stats::HashMapStats;
 pub use string::{bmbc_match, karp_rabin, kmp_match};
 mod avl;
 mod binary;
+mod binomial;
 mod btree;
+mod chained;
+mod compare;
+mod concurrent;
+mod cuckoo;
+mod dary;
+mod fibonacci;
+mod hasher;
 mod hashmap;
 mod heap;
+mod indexed;
+mod invariant;
+mod iter;
 mod node;
+mod pairing;
+mod rbtree;
+mod robinhood;
+mod skew;
 mod sort;
 mod splay;
+mod stats;
 mod string;
+#[cfg(test)]
+mod test_util;
 
 /// Binary Search Tree Interface
-pub trait BST {
+pub trait BST<K: Ord, V> {
     /// search val by key
-    fn search(&mut self, key: i32) -> Option<i32>;
-    /// insert key-val  
-    fn insert(&mut self, key: i32, val: i32);
+    fn search(&mut self, key: &K) -> Option<&V>;
+    /// insert key-val
+    fn insert(&mut self, key: K, val: V);
     /// remove val by key
-    fn remove(&mut self, key: i32) -> Option<i32>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    /// key-val with the minimum key
+    fn first(&mut self) -> Option<(&K, &V)>;
+    /// key-val with the maximum key
+    fn last(&mut self) -> Option<(&K, &V)>;
+    /// key-val with the greatest key less than or equal to `key`
+    fn floor(&mut self, key: &K) -> Option<(&K, &V)>;
+    /// key-val with the least key greater than or equal to `key`
+    fn ceiling(&mut self, key: &K) -> Option<(&K, &V)>;
+    /// key-val with the greatest key strictly less than `key`
+    fn predecessor(&mut self, key: &K) -> Option<(&K, &V)>;
+    /// key-val with the least key strictly greater than `key`
+    fn successor(&mut self, key: &K) -> Option<(&K, &V)>;
+    /// remove and return the key-val with the minimum key
+    fn pop_first(&mut self) -> Option<(K, V)>;
+    /// remove and return the key-val with the maximum key
+    fn pop_last(&mut self) -> Option<(K, V)>;
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn string_keys<T: BST<String, Vec<u8>>>(mut tree: T) {
+        let words = ["tsinghua", "data", "structure", "avl", "splay", "btree"];
+        for word in words.iter() {
+            tree.insert(word.to_string(), word.as_bytes().to_vec());
+        }
+        for word in words.iter() {
+            assert_eq!(
+                tree.search(&word.to_string()),
+                Some(&word.as_bytes().to_vec())
+            );
+        }
+        assert_eq!(tree.search(&"heap".to_string()), None);
+        assert_eq!(tree.remove(&"avl".to_string()), Some(b"avl".to_vec()));
+        assert_eq!(tree.remove(&"avl".to_string()), None);
+        assert_eq!(tree.search(&"splay".to_string()), Some(&b"splay".to_vec()));
+    }
+
+    fn nearest_keys<T: BST<i32, i32>>(mut tree: T, mut keys: Vec<i32>) {
+        for &k in keys.iter() {
+            tree.insert(k, -k);
+        }
+        keys.sort();
+        keys.dedup();
+        let entry = |k: &i32| Some((*k, -*k));
+        let own = |e: Option<(&i32, &i32)>| e.map(|(k, v)| (*k, *v));
+
+        assert_eq!(own(tree.first()), keys.first().and_then(entry));
+        assert_eq!(own(tree.last()), keys.last().and_then(entry));
+        for probe in -2..keys.last().unwrap() + 3 {
+            let floor = keys.iter().rev().find(|&&k| k <= probe);
+            let ceiling = keys.iter().find(|&&k| k >= probe);
+            let predecessor = keys.iter().rev().find(|&&k| k < probe);
+            let successor = keys.iter().find(|&&k| k > probe);
+            assert_eq!(own(tree.floor(&probe)), floor.and_then(entry));
+            assert_eq!(own(tree.ceiling(&probe)), ceiling.and_then(entry));
+            assert_eq!(own(tree.predecessor(&probe)), predecessor.and_then(entry));
+            assert_eq!(own(tree.successor(&probe)), successor.and_then(entry));
+        }
+
+        let (mut lo, mut hi) = (0, keys.len());
+        while lo < hi {
+            assert_eq!(tree.pop_first(), entry(&keys[lo]));
+            lo += 1;
+            if lo < hi {
+                hi -= 1;
+                assert_eq!(tree.pop_last(), entry(&keys[hi]));
+            }
+        }
+        assert_eq!(tree.pop_first(), None);
+        assert_eq!(tree.pop_last(), None);
+        assert_eq!(tree.first(), None);
+        assert_eq!(tree.floor(&0), None);
+    }
+
+    #[test]
+    fn nearest_key_queries() {
+        let keys = vec![
+            10, 4, 8, 5, 9, 3, 2, 15, 13, 11, 18, 20, 19, 12, 21, 1, 30, 27, 24,
+        ];
+        nearest_keys(AVLTree::new(10, -10), keys.clone());
+        nearest_keys(BinarySearchTree::new(10, -10), keys.clone());
+        nearest_keys(RedBlackTree::new(10, -10), keys.clone());
+        nearest_keys(SplayTree::new(10, -10), keys.clone());
+        for order in 3..10 {
+            nearest_keys(BTree::new(0, order), keys.clone());
+        }
+    }
+
+    #[test]
+    fn generic_key_value() {
+        string_keys(AVLTree::new("root".to_string(), vec![]));
+        string_keys(BinarySearchTree::new("root".to_string(), vec![]));
+        string_keys(RedBlackTree::new("root".to_string(), vec![]));
+        string_keys(SplayTree::new("root".to_string(), vec![]));
+        string_keys(BTree::new(0, 4));
+    }
+
     #[test]
     fn just_for_fun() {
         let mut array = [3, 4, 5, 2, 48, 19, 39, 390, 43, 12, 8, 9, 10, 7, 5];
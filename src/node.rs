@@ -1,21 +1,34 @@
 //! Common Node Defination
-use std::cmp::max;
+use std::cmp::{max, Ordering};
 use std::mem::{replace, swap};
 
 /// Common Tree Node
 #[derive(Debug, PartialEq, Eq)]
-pub struct TreeNode {
+pub struct TreeNode<K, V> {
     /// key
-    pub key: i32,
+    pub key: K,
     /// value
-    pub val: i32,
+    pub val: V,
     /// height
     pub height: usize,
+    /// number of nodes in the subtree rooted here
+    pub size: usize,
+    /// node color, only meaningful in `RedBlackTree`
+    pub(crate) color: Color,
     /// left child
-    pub left: Option<Box<TreeNode>>,
+    pub left: Option<Box<TreeNode<K, V>>>,
     /// right child
-    pub right: Option<Box<TreeNode>>,
+    pub right: Option<Box<TreeNode<K, V>>>,
 }
+/// Red-Black tree node color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Color {
+    /// red node
+    Red,
+    /// black node
+    Black,
+}
+
 /// TreeNode Child Type
 #[derive(Debug, PartialEq, Eq)]
 pub enum ChildType {
@@ -24,43 +37,45 @@ pub enum ChildType {
 }
 
 /// B-Tree Node
-#[derive(Default, Debug, PartialEq, Eq)]
-pub struct BTNode {
+#[derive(Debug, PartialEq, Eq)]
+pub struct BTNode<K, V> {
     /// key vector
-    pub keys: Vec<i32>,
+    pub keys: Vec<K>,
     /// values vector
-    pub vals: Vec<i32>,
+    pub vals: Vec<V>,
     /// children vector
-    pub children: Vec<Option<Box<BTNode>>>,
+    pub children: Vec<Option<Box<BTNode<K, V>>>>,
 }
 
-impl TreeNode {
+impl<K: Ord, V> TreeNode<K, V> {
     /// New treenode instance
     #[inline]
-    pub const fn new(key: i32, val: i32) -> Self {
+    pub const fn new(key: K, val: V) -> Self {
         Self {
             key,
             val,
             height: 1,
+            size: 1,
+            color: Color::Red,
             left: None,
             right: None,
         }
     }
     /// get key
     #[inline]
-    pub fn get_key(&self) -> i32 {
-        self.key
+    pub fn get_key(&self) -> &K {
+        &self.key
     }
 
     /// get val
     #[inline]
-    pub fn get_val(&self) -> i32 {
-        self.val
+    pub fn get_val(&self) -> &V {
+        &self.val
     }
-    /// set val
+    /// set val, return the old one
     #[inline]
-    pub fn set_val(&mut self, val: i32) {
-        self.val = val;
+    pub fn set_val(&mut self, val: V) -> V {
+        replace(&mut self.val, val)
     }
 
     /// get left child height
@@ -73,10 +88,21 @@ impl TreeNode {
     fn right_height(&self) -> usize {
         self.right.as_ref().map_or(0, |right| right.height)
     }
-    /// update `TreeNode
+    /// get left child size
+    #[inline]
+    pub fn left_size(&self) -> usize {
+        self.left.as_ref().map_or(0, |left| left.size)
+    }
+    /// get right child size
+    #[inline]
+    pub fn right_size(&self) -> usize {
+        self.right.as_ref().map_or(0, |right| right.size)
+    }
+    /// update `TreeNode` height and subtree size from its children
     #[inline]
     pub fn update_height(&mut self) {
         self.height = 1 + max(self.left_height(), self.right_height());
+        self.size = 1 + self.left_size() + self.right_size();
     }
     /// get balance factor,
     /// TreeHeavy::Left mean left child heavy, so call `rotate_right()` method
@@ -93,7 +119,7 @@ impl TreeNode {
     }
 
     /// Rotate left , mean `zag`
-    fn rotate_left(&mut self) {
+    pub(crate) fn rotate_left(&mut self) {
         if self.right.is_none() {
             return;
         }
@@ -120,7 +146,7 @@ impl TreeNode {
         self.update_height();
     }
     /// Rotate right , mean `zig`
-    fn rotate_right(&mut self) {
+    pub(crate) fn rotate_right(&mut self) {
         if self.left.is_none() {
             return;
         }
@@ -146,8 +172,86 @@ impl TreeNode {
         self.update_height();
     }
 
-    /// Rebalance tree
-    pub fn rebalance(&mut self) {
+    /// leftmost node of the subtree
+    pub fn first(&self) -> &Self {
+        let mut node = self;
+        while let Some(left) = node.left.as_ref() {
+            node = left;
+        }
+        node
+    }
+
+    /// rightmost node of the subtree
+    pub fn last(&self) -> &Self {
+        let mut node = self;
+        while let Some(right) = node.right.as_ref() {
+            node = right;
+        }
+        node
+    }
+
+    /// the last node whose key is on the left of the split point
+    fn lower_neighbor(&self, is_left: impl Fn(&K) -> bool) -> Option<&Self> {
+        let (mut ptr, mut best) = (Some(self), None);
+        while let Some(node) = ptr {
+            if is_left(&node.key) {
+                best = Some(node);
+                ptr = node.right.as_deref();
+            } else {
+                ptr = node.left.as_deref();
+            }
+        }
+        best
+    }
+
+    /// the first node whose key is on the right of the split point
+    fn upper_neighbor(&self, is_left: impl Fn(&K) -> bool) -> Option<&Self> {
+        let (mut ptr, mut best) = (Some(self), None);
+        while let Some(node) = ptr {
+            if is_left(&node.key) {
+                ptr = node.right.as_deref();
+            } else {
+                best = Some(node);
+                ptr = node.left.as_deref();
+            }
+        }
+        best
+    }
+
+    /// node with the greatest key less than or equal to `key`
+    pub fn floor(&self, key: &K) -> Option<&Self> {
+        self.lower_neighbor(|k| k <= key)
+    }
+
+    /// node with the least key greater than or equal to `key`
+    pub fn ceiling(&self, key: &K) -> Option<&Self> {
+        self.upper_neighbor(|k| k < key)
+    }
+
+    /// node with the greatest key strictly less than `key`
+    pub fn predecessor(&self, key: &K) -> Option<&Self> {
+        self.lower_neighbor(|k| k < key)
+    }
+
+    /// node with the least key strictly greater than `key`
+    pub fn successor(&self, key: &K) -> Option<&Self> {
+        self.upper_neighbor(|k| k <= key)
+    }
+
+    /// key-val pair reference
+    #[inline]
+    pub fn entry(&self) -> (&K, &V) {
+        (&self.key, &self.val)
+    }
+
+    /// consume node into key-val pair
+    #[inline]
+    pub fn into_entry(self) -> (K, V) {
+        (self.key, self.val)
+    }
+
+    /// Rebalance tree, return the number of rotations done
+    pub fn rebalance(&mut self) -> usize {
         match self.balance_factor() {
             -2 => {
                 // root is right heavy
@@ -155,9 +259,12 @@ impl TreeNode {
                 // inner node is left heavy
                 if right_node.balance_factor() == 1 {
                     right_node.rotate_right();
+                    self.rotate_left();
+                    return 2;
                 }
 
                 self.rotate_left();
+                1
             }
             2 => {
                 // root is left heavy
@@ -165,167 +272,125 @@ impl TreeNode {
                 // inner node is right heavy
                 if left_node.balance_factor() == -1 {
                     left_node.rotate_left();
+                    self.rotate_right();
+                    return 2;
                 }
 
                 self.rotate_right();
+                1
             }
-            _ => (),
+            _ => 0,
         }
     }
 
-    /// caller `self` must be `parent`'s child
-    fn who_child(&self, parent: &TreeNode) -> ChildType {
-        if let Some(node) = parent.left.as_ref() {
-            if node.key == self.key {
-                return ChildType::Left;
+    /// splay algorithm, walk down from `root` by `cmp` and move the last node
+    /// touched up to `root`, two levels a step from the bottom
+    pub fn splay(root: &mut Option<Box<Self>>, cmp: impl Fn(&K) -> Ordering) {
+        let mut node = match root.take() {
+            Some(node) => node,
+            None => return,
+        };
+
+        // zipper: every ancestor owns itself minus the link towards `node`
+        let mut path = Vec::<(Box<Self>, ChildType)>::new();
+        loop {
+            let child_type = match cmp(&node.key) {
+                Ordering::Less => ChildType::Left,
+                Ordering::Greater => ChildType::Right,
+                Ordering::Equal => break,
+            };
+            let child = match child_type {
+                ChildType::Left => node.left.take(),
+                ChildType::Right => node.right.take(),
+            };
+            match child {
+                Some(child) => path.push((replace(&mut node, child), child_type)),
+                None => break,
             }
         }
 
-        return ChildType::Right;
-    }
-
-    /// splay algorithm
-    pub fn splay(mut self: &mut Self, mut prev_ptrs: Vec<*mut TreeNode>) {
-        let last_parent = if prev_ptrs.len() % 2 == 1 {
-            prev_ptrs.remove(0)
-        } else {
-            0 as *mut TreeNode
-        };
-
-        while let (Some(parent_ptr), Some(grandparent_ptr)) = (prev_ptrs.pop(), prev_ptrs.pop()) {
-            let grandparent = unsafe { &mut *grandparent_ptr };
-            let parent = unsafe { &mut *parent_ptr };
+        while let Some((mut parent, p_type)) = path.pop() {
+            let mut grandparent = match path.pop() {
+                Some(grandparent) => grandparent,
+                None => {
+                    // do another single rotatation
+                    match p_type {
+                        // zig
+                        ChildType::Left => {
+                            parent.left = node.right.take();
+                            parent.update_height();
+                            node.right = Some(parent);
+                        }
+                        // zag
+                        ChildType::Right => {
+                            parent.right = node.left.take();
+                            parent.update_height();
+                            node.left = Some(parent);
+                        }
+                    }
+                    node.update_height();
+                    break;
+                }
+            };
 
-            match (self.who_child(parent), parent.who_child(grandparent)) {
+            match (p_type, grandparent.1) {
                 (ChildType::Left, ChildType::Left) => {
                     // zig-zig
-                    let v_left = self.left.take();
-                    let v_right = self.right.take();
-
-                    let p_left = parent.left.take();
-                    let p_right = parent.right.take();
-
-                    let g_left = grandparent.left.take();
-                    let g_right = grandparent.right.take();
-
-                    // grandparent swap with self
-                    swap(&mut self.key, &mut grandparent.key);
-                    swap(&mut self.val, &mut grandparent.val);
-
-                    self.left = p_right;
-                    self.right = g_right;
-
-                    parent.left = v_right;
-                    parent.right = p_left;
-
-                    grandparent.left = v_left;
-                    grandparent.right = g_left;
-
-                    self.update_height();
+                    grandparent.0.left = parent.right.take();
+                    grandparent.0.update_height();
+                    parent.left = node.right.take();
+                    parent.right = Some(grandparent.0);
                     parent.update_height();
-                    grandparent.update_height();
-
-                    self = grandparent;
+                    node.right = Some(parent);
                 }
                 (ChildType::Right, ChildType::Right) => {
                     // zag-zag
-                    let v_left = self.left.take();
-                    let v_right = self.right.take();
-
-                    let p_left = parent.left.take();
-                    let p_right = parent.right.take();
-
-                    let g_left = grandparent.left.take();
-                    let g_right = grandparent.right.take();
-
-                    // grandparent swap with self
-                    swap(&mut self.key, &mut grandparent.key);
-                    swap(&mut self.val, &mut grandparent.val);
-
-                    self.left = g_left;
-                    self.right = p_left;
-
-                    parent.right = v_left;
-                    parent.left = p_right;
-
-                    grandparent.right = v_right;
-                    grandparent.left = g_right;
-
-                    self.update_height();
+                    grandparent.0.right = parent.left.take();
+                    grandparent.0.update_height();
+                    parent.right = node.left.take();
+                    parent.left = Some(grandparent.0);
                     parent.update_height();
-                    grandparent.update_height();
-
-                    self = grandparent;
+                    node.left = Some(parent);
                 }
-                (ChildType::Left, ChildType::Right) => {
+                (ChildType::Right, ChildType::Left) => {
                     // zig-zag
-                    let v_left = self.left.take();
-                    let v_right = self.right.take();
-
-                    let p_left = parent.left.take();
-                    let g_left = grandparent.left.take();
-
-                    // grandparent swap with self
-                    swap(&mut self.key, &mut grandparent.key);
-                    swap(&mut self.val, &mut grandparent.val);
-
-                    self.left = g_left;
-                    self.right = v_left;
-
-                    parent.left = v_right;
-                    grandparent.left = p_left;
-
-                    self.update_height();
+                    parent.right = node.left.take();
                     parent.update_height();
-                    grandparent.update_height();
-
-                    self = grandparent;
+                    grandparent.0.left = node.right.take();
+                    grandparent.0.update_height();
+                    node.left = Some(parent);
+                    node.right = Some(grandparent.0);
                 }
-                (ChildType::Right, ChildType::Left) => {
+                (ChildType::Left, ChildType::Right) => {
                     // zag-zig
-                    let v_left = self.left.take();
-                    let v_right = self.right.take();
-
-                    let p_right = parent.right.take();
-                    let g_right = grandparent.right.take();
-
-                    // grandparent swap with self
-                    swap(&mut self.key, &mut grandparent.key);
-                    swap(&mut self.val, &mut grandparent.val);
-
-                    self.left = v_right;
-                    self.right = g_right;
-
-                    parent.right = v_left;
-                    grandparent.right = p_right;
-
-                    self.update_height();
+                    parent.left = node.right.take();
                     parent.update_height();
-                    grandparent.update_height();
-
-                    self = grandparent;
+                    grandparent.0.right = node.left.take();
+                    grandparent.0.update_height();
+                    node.right = Some(parent);
+                    node.left = Some(grandparent.0);
                 }
             }
+            node.update_height();
         }
-        // do another single rotatation
-        if !last_parent.is_null() {
-            // grand grand parent
-            let parent = unsafe { &mut *last_parent };
-            match self.who_child(parent) {
-                ChildType::Left => {
-                    parent.rotate_right();
-                }
-                ChildType::Right => {
-                    parent.rotate_left();
-                }
-            }
+
+        *root = Some(node);
+    }
+}
+
+impl<K, V> Default for BTNode<K, V> {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            vals: Vec::new(),
+            children: Vec::new(),
         }
     }
 }
 
-impl BTNode {
+impl<K, V> BTNode<K, V> {
     /// new instance
-    pub fn new(keys: Vec<i32>, vals: Vec<i32>, children: Vec<Option<Box<BTNode>>>) -> Self {
+    pub fn new(keys: Vec<K>, vals: Vec<V>, children: Vec<Option<Box<BTNode<K, V>>>>) -> Self {
         Self {
             keys,
             vals,
@@ -339,7 +404,7 @@ mod tests {
     use super::*;
     #[test]
     fn btnode_operations() {
-        let _btnode = BTNode::default();
+        let _btnode = BTNode::<i32, i32>::default();
         // println!("{:#?}", _btnode);
     }
 }
@@ -0,0 +1,374 @@
+//! Pairing heap with decrease-key through handles
+//!
+//! A heap-ordered multiway tree, kept as a first child and next sibling list
+//! in every node. Melding links two roots, the lesser becoming the first
+//! child of the greater, and popping pairs the children of the root left to
+//! right, then links the pairs right to left. Nodes are shared with the
+//! handles given out by `push`, as `Weak` pointers, so a handle follows its
+//! item through melds and stops working once the item is popped.
+
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use crate::heap::MergeableHeap;
+use crate::InvariantError;
+use std::cell::{Ref, RefCell};
+use std::mem::replace;
+use std::rc::{Rc, Weak};
+
+type Link<T> = Rc<RefCell<PairingNode<T>>>;
+
+#[derive(Debug)]
+struct PairingNode<T> {
+    item: T,
+    child: Option<Link<T>>,
+    next: Option<Link<T>>,
+    /// the parent of a first child, the elder sibling of any other
+    prev: Weak<RefCell<PairingNode<T>>>,
+}
+
+/// Handle to an item of a [`PairingHeap`], valid until the item is popped
+#[derive(Debug)]
+pub struct PairingHandle<T>(Weak<RefCell<PairingNode<T>>>);
+
+impl<T> Clone for PairingHandle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Pairing heap, a max-heap by default or ordered by any comparator `C`
+///
+/// It is a logic error to use a handle with a heap that does not hold its
+/// item.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{MergeableHeap, PairingHeap};
+///
+/// let mut heap = PairingHeap::new_min();
+/// let a = heap.push(10);
+/// heap.push(5);
+///
+/// let mut other = PairingHeap::new_min();
+/// let b = other.push(8);
+/// heap.meld(other);
+///
+/// // handles follow their items into the melded heap
+/// assert_eq!(heap.decrease_key(&b, 1), Ok(8));
+/// assert_eq!(heap.decrease_key(&a, 20), Err(20));
+/// assert_eq!(heap.pop(), Some(1));
+/// assert!(!heap.contains(&b));
+/// assert_eq!(heap.peek().as_deref(), Some(&5));
+/// ```
+#[derive(Debug)]
+pub struct PairingHeap<T, C = MaxComparator> {
+    root: Option<Link<T>>,
+    len: usize,
+    cmp: C,
+}
+
+impl<T: Ord> PairingHeap<T> {
+    /// Creates an empty `PairingHeap` as a max-heap.
+    pub fn new() -> Self {
+        Self::with_comparator(MaxComparator)
+    }
+}
+
+impl<T: Ord> PairingHeap<T, MinComparator> {
+    /// Creates an empty `PairingHeap` as a min-heap.
+    pub fn new_min() -> Self {
+        Self::with_comparator(MinComparator)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for PairingHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    /// Creates an empty `PairingHeap` ordered by `cmp`, the greatest item by
+    /// `cmp` being on top.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            root: None,
+            len: 0,
+            cmp,
+        }
+    }
+
+    /// Returns the number of items in the heap.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes an item onto the heap in *O*(1), returning its handle.
+    pub fn push(&mut self, item: T) -> PairingHandle<T> {
+        let node = Rc::new(RefCell::new(PairingNode {
+            item,
+            child: None,
+            next: None,
+            prev: Weak::new(),
+        }));
+        let handle = PairingHandle(Rc::downgrade(&node));
+        self.root = Some(match self.root.take() {
+            Some(root) => self.link(root, node),
+            None => node,
+        });
+        self.len += 1;
+        handle
+    }
+
+    /// Returns `true` if the item of `handle` has not been popped.
+    pub fn contains(&self, handle: &PairingHandle<T>) -> bool {
+        handle.0.strong_count() > 0
+    }
+
+    /// Returns a clone of the item of `handle`, or `None` if it has been
+    /// popped.
+    pub fn get(&self, handle: &PairingHandle<T>) -> Option<T>
+    where
+        T: Clone,
+    {
+        handle.0.upgrade().map(|node| node.borrow().item.clone())
+    }
+
+    /// Replaces the item of `handle` by `item`, which must not be less than
+    /// it, moving it up in amortized *O*(log(*n*)), and returns the old item.
+    /// Gives `item` back if it is less, or if the old item has been popped.
+    pub fn decrease_key(&mut self, handle: &PairingHandle<T>, item: T) -> Result<T, T> {
+        let node = match handle.0.upgrade() {
+            Some(node) => node,
+            None => return Err(item),
+        };
+        if self.cmp.lt(&item, &node.borrow().item) {
+            return Err(item);
+        }
+        let old = replace(&mut node.borrow_mut().item, item);
+        let root = match self.root.take() {
+            Some(root) if Rc::ptr_eq(&root, &node) => root,
+            Some(root) => {
+                self.cut(&node);
+                self.link(root, node)
+            }
+            None => node,
+        };
+        self.root = Some(root);
+        Ok(old)
+    }
+
+    /// link two roots, the lesser becoming the first child of the greater
+    fn link(&self, a: Link<T>, b: Link<T>) -> Link<T> {
+        let (parent, child) = if self.cmp.lt(&a.borrow().item, &b.borrow().item) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        {
+            let mut p = parent.borrow_mut();
+            let mut c = child.borrow_mut();
+            c.prev = Rc::downgrade(&parent);
+            c.next = p.child.take();
+            if let Some(next) = &c.next {
+                next.borrow_mut().prev = Rc::downgrade(&child);
+            }
+        }
+        parent.borrow_mut().child = Some(child);
+        parent
+    }
+
+    /// detach a non-root node, with its subtree, from its parent or elder
+    /// sibling
+    fn cut(&self, node: &Link<T>) {
+        let (prev, next) = {
+            let mut n = node.borrow_mut();
+            (replace(&mut n.prev, Weak::new()).upgrade(), n.next.take())
+        };
+        let prev = prev.expect("a node below the root has a parent or an elder sibling");
+        {
+            let mut p = prev.borrow_mut();
+            match p.child.as_ref().is_some_and(|c| Rc::ptr_eq(c, node)) {
+                true => p.child = next.clone(),
+                false => p.next = next.clone(),
+            }
+        }
+        if let Some(next) = next {
+            next.borrow_mut().prev = Rc::downgrade(&prev);
+        }
+    }
+
+    /// link the trees in pairs left to right, then the pairs right to left
+    fn combine(&self, trees: Vec<Link<T>>) -> Option<Link<T>> {
+        let mut pairs = Vec::with_capacity(trees.len() / 2 + 1);
+        let mut trees = trees.into_iter();
+        while let Some(a) = trees.next() {
+            pairs.push(match trees.next() {
+                Some(b) => self.link(a, b),
+                None => a,
+            });
+        }
+        let mut root = pairs.pop()?;
+        while let Some(tree) = pairs.pop() {
+            root = self.link(tree, root);
+        }
+        Some(root)
+    }
+
+    /// Checks heap order, the back links and the length
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let mut stack: Vec<(Link<T>, usize)> = self.root.iter().map(|r| (r.clone(), 0)).collect();
+        let mut count = 0;
+        while let Some((node, depth)) = stack.pop() {
+            count += 1;
+            let n = node.borrow();
+            let mut prev = Rc::downgrade(&node);
+            let mut child = n.child.clone();
+            while let Some(c) = child {
+                if self.cmp.gt(&c.borrow().item, &n.item) {
+                    return Err(InvariantError::HeapOrder { depth: depth + 1 });
+                }
+                if !c.borrow().prev.ptr_eq(&prev) {
+                    return Err(InvariantError::BackLink { depth: depth + 1 });
+                }
+                prev = Rc::downgrade(&c);
+                child = c.borrow().next.clone();
+                stack.push((c, depth + 1));
+            }
+        }
+        if count != self.len {
+            return Err(InvariantError::Size {
+                stored: self.len,
+                actual: count,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T, C: Compare<T>> MergeableHeap<T> for PairingHeap<T, C> {
+    type Peek<'a>
+        = Ref<'a, T>
+    where
+        Self: 'a;
+
+    fn push(&mut self, item: T) {
+        PairingHeap::push(self, item);
+    }
+
+    fn peek(&self) -> Option<Ref<'_, T>> {
+        self.root
+            .as_ref()
+            .map(|root| Ref::map(root.borrow(), |node| &node.item))
+    }
+
+    /// amortized *O*(log(*n*))
+    fn pop(&mut self) -> Option<T> {
+        let root = self.root.take()?;
+        let mut children = Vec::new();
+        let mut next = root.borrow_mut().child.take();
+        while let Some(child) = next {
+            {
+                let mut c = child.borrow_mut();
+                next = c.next.take();
+                c.prev = Weak::new();
+            }
+            children.push(child);
+        }
+        self.root = self.combine(children);
+        self.len -= 1;
+        match Rc::try_unwrap(root) {
+            Ok(node) => Some(node.into_inner().item),
+            Err(_) => unreachable!("only weak handles point to a detached root"),
+        }
+    }
+
+    /// *O*(1)
+    fn meld(&mut self, mut other: Self) {
+        self.root = match (self.root.take(), other.root.take()) {
+            (Some(a), Some(b)) => Some(self.link(a, b)),
+            (a, b) => a.or(b),
+        };
+        self.len += other.len;
+    }
+
+    fn is_empty(&self) -> bool {
+        PairingHeap::is_empty(self)
+    }
+}
+
+impl<T, C> Drop for PairingHeap<T, C> {
+    /// free the nodes one at a time, as the sibling lists may be long
+    fn drop(&mut self) {
+        let mut stack: Vec<_> = self.root.take().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            let mut node = node.borrow_mut();
+            stack.extend(node.child.take());
+            stack.extend(node.next.take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairing_decrease_key() {
+        let mut heap = PairingHeap::new_min();
+        let handles: Vec<_> = (0..200).map(|i| heap.push(1000 + i * 7 % 200)).collect();
+        assert_eq!(heap.pop(), Some(1000));
+        assert_eq!(heap.check_invariants(), Ok(()));
+
+        // decrease every third item, from deep in the tree too
+        for (i, handle) in handles.iter().enumerate().skip(1).step_by(3) {
+            let old = heap.get(handle).unwrap();
+            assert_eq!(heap.decrease_key(handle, old - 1000), Ok(old));
+            assert_eq!(heap.check_invariants(), Ok(()));
+            assert_eq!(heap.get(handle), Some(i as i32 * 7 % 200));
+        }
+        let bigger = heap.get(&handles[2]).unwrap() + 1;
+        assert_eq!(heap.decrease_key(&handles[2], bigger), Err(bigger));
+
+        let mut popped = Vec::new();
+        while let Some(item) = MergeableHeap::pop(&mut heap) {
+            popped.push(item);
+        }
+        assert_eq!(popped.len(), 199);
+        assert!(popped.windows(2).all(|w| w[0] <= w[1]));
+        assert!(handles.iter().all(|handle| !heap.contains(handle)));
+        assert_eq!(heap.decrease_key(&handles[0], 0), Err(0));
+    }
+
+    #[test]
+    fn pairing_meld() {
+        let mut a = PairingHeap::new();
+        let mut b = PairingHeap::new();
+        let ha: Vec<_> = (0..100).map(|i| a.push(i)).collect();
+        let hb: Vec<_> = (100..200).map(|i| b.push(i)).collect();
+        a.meld(b);
+        assert_eq!(a.len(), 200);
+        assert_eq!(a.decrease_key(&ha[5], 500), Ok(5));
+        assert_eq!(a.decrease_key(&hb[0], 400), Ok(100));
+        assert_eq!(a.check_invariants(), Ok(()));
+        assert_eq!(MergeableHeap::pop(&mut a), Some(500));
+        assert_eq!(MergeableHeap::pop(&mut a), Some(400));
+        assert_eq!(a.peek().as_deref(), Some(&199));
+    }
+
+    #[test]
+    fn long_sibling_lists() {
+        // every push links the new root above the old one, a path as long as
+        // the heap that must not overflow the stack when dropped
+        let mut heap = PairingHeap::new();
+        for i in 0..200_000 {
+            heap.push(i);
+        }
+        assert_eq!(MergeableHeap::pop(&mut heap), Some(199_999));
+    }
+}
@@ -0,0 +1,539 @@
+//! RedBlackTree
+//! (2, 4) B-tree in disguise, black node with its red children is a B-tree node
+
+use crate::invariant::{check_colors, check_order};
+use crate::node::Color;
+use crate::{
+    InvariantError, Keys, TreeIntoIter, TreeIter, TreeIterMut, TreeNode, TreeRange, Values, BST,
+};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::mem::replace;
+use std::ops::RangeBounds;
+
+/// Red-Black Tree, with the number of rotations done so far
+///
+/// Node rotations of `TreeNode` swap key-val instead of moving nodes, so the
+/// color stays with the position and a single rotation around a black node
+/// already leaves a black top with two red children.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RedBlackTree<K, V>(Option<Box<TreeNode<K, V>>>, usize);
+
+impl<K: Ord, V> RedBlackTree<K, V> {
+    /// Red-Black tree
+    pub fn new(root_key: K, root_val: V) -> Self {
+        let mut root = Box::new(TreeNode::new(root_key, root_val));
+        root.color = Color::Black;
+        Self(Some(root), 0)
+    }
+
+    /// Returns the number of rotations done by fix-ups so far
+    pub fn rotations(&self) -> usize {
+        self.1
+    }
+
+    /// Gets an iterator over the entries of the tree, sorted by key
+    pub fn iter(&self) -> TreeIter<'_, K, V> {
+        TreeIter::new(self.0.as_deref())
+    }
+
+    /// Gets a mutable iterator over the entries of the tree, sorted by key
+    pub fn iter_mut(&mut self) -> TreeIterMut<'_, K, V> {
+        TreeIterMut::new(self.0.as_deref_mut())
+    }
+
+    /// Gets an iterator over the keys of the tree, in sorted order
+    pub fn keys(&self) -> Keys<TreeIter<'_, K, V>> {
+        Keys(self.iter())
+    }
+
+    /// Gets an iterator over the values of the tree, in order by key
+    pub fn values(&self) -> Values<TreeIter<'_, K, V>> {
+        Values(self.iter())
+    }
+
+    /// Constructs a double-ended iterator over a sub-range of entries in the tree
+    pub fn range<T, R>(&self, range: R) -> TreeRange<'_, K, V>
+    where
+        K: Borrow<T>,
+        T: Ord + ?Sized,
+        R: RangeBounds<T>,
+    {
+        TreeRange::new(self.0.as_deref(), range)
+    }
+
+    /// Checks key ordering, a black root, no red node with a red child, and
+    /// the same black height on every path
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        check_order(self.0.as_deref())?;
+        if is_red(&self.0) {
+            return Err(InvariantError::RedRoot);
+        }
+        check_colors(&self.0, 0).map(|_| ())
+    }
+
+    /// root is always black
+    fn blacken_root(&mut self) {
+        if let Some(root) = self.0.as_mut() {
+            root.color = Color::Black;
+        }
+    }
+}
+
+#[inline]
+fn is_red<K, V>(link: &Option<Box<TreeNode<K, V>>>) -> bool {
+    link.as_ref().is_some_and(|node| node.color == Color::Red)
+}
+
+#[inline]
+fn has_red_child<K, V>(link: &Option<Box<TreeNode<K, V>>>) -> bool {
+    link.as_ref()
+        .is_some_and(|node| is_red(&node.left) || is_red(&node.right))
+}
+
+/// insert under `link`, return the replaced value if `key` exists
+fn insert_node<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    key: K,
+    val: V,
+    rotations: &mut usize,
+) -> Option<V> {
+    let node = match link.as_mut() {
+        Some(node) => node,
+        None => {
+            *link = Some(Box::new(TreeNode::new(key, val)));
+            return None;
+        }
+    };
+    let old_val = match key.cmp(&node.key) {
+        Ordering::Less => insert_node(&mut node.left, key, val, rotations),
+        Ordering::Greater => insert_node(&mut node.right, key, val, rotations),
+        Ordering::Equal => return Some(node.set_val(val)),
+    };
+    node.update_height();
+    solve_double_red(node, rotations);
+    old_val
+}
+
+/// `node` is the grandparent of a possible red-red pair
+fn solve_double_red<K: Ord, V>(node: &mut TreeNode<K, V>, rotations: &mut usize) {
+    let left_double = is_red(&node.left) && has_red_child(&node.left);
+    let right_double = is_red(&node.right) && has_red_child(&node.right);
+    if !left_double && !right_double {
+        return;
+    }
+
+    if is_red(&node.left) && is_red(&node.right) {
+        // RR-2: uncle is red, recolor and the double red may move up
+        node.left.as_mut().unwrap().color = Color::Black;
+        node.right.as_mut().unwrap().color = Color::Black;
+        node.color = Color::Red;
+    } else if left_double {
+        // RR-1: uncle is black, 3 + 4 reconstruction by rotation
+        let left_node = node.left.as_mut().unwrap();
+        if is_red(&left_node.right) {
+            left_node.rotate_left();
+            *rotations += 1;
+        }
+        node.rotate_right();
+        *rotations += 1;
+    } else {
+        let right_node = node.right.as_mut().unwrap();
+        if is_red(&right_node.left) {
+            right_node.rotate_right();
+            *rotations += 1;
+        }
+        node.rotate_left();
+        *rotations += 1;
+    }
+}
+
+/// cut a node with at most one child, return whether black height dropped
+fn unlink_node<K, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+) -> Option<(Box<TreeNode<K, V>>, bool)> {
+    let mut node = link.take()?;
+    let child = node.left.take().or_else(|| node.right.take());
+    let short = match child {
+        _ if node.color == Color::Red => false,
+        Some(mut child) if child.color == Color::Red => {
+            child.color = Color::Black;
+            *link = Some(child);
+            return Some((node, false));
+        }
+        _ => true,
+    };
+    *link = child;
+    Some((node, short))
+}
+
+/// remove `key` under `link`, return the node and whether black height dropped
+fn remove_node<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    key: &K,
+    rotations: &mut usize,
+) -> Option<(Box<TreeNode<K, V>>, bool)> {
+    let node = link.as_mut()?;
+    let (removed, short) = match key.cmp(&node.key) {
+        Ordering::Less => {
+            let (removed, short) = remove_node(&mut node.left, key, rotations)?;
+            (removed, short && solve_left_short(node, rotations))
+        }
+        Ordering::Greater => {
+            let (removed, short) = remove_node(&mut node.right, key, rotations)?;
+            (removed, short && solve_right_short(node, rotations))
+        }
+        Ordering::Equal if node.left.is_some() && node.right.is_some() => {
+            // swap with the successor, then the successor is the one cut
+            let (mut succ, short) = pop_min(&mut node.right, rotations)?;
+            succ.key = replace(&mut node.key, succ.key);
+            succ.val = replace(&mut node.val, succ.val);
+            (succ, short && solve_right_short(node, rotations))
+        }
+        Ordering::Equal => return unlink_node(link),
+    };
+    node.update_height();
+    Some((removed, short))
+}
+
+/// detach the leftmost node, return it and whether black height dropped
+fn pop_min<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    rotations: &mut usize,
+) -> Option<(Box<TreeNode<K, V>>, bool)> {
+    let node = link.as_mut()?;
+    if node.left.is_none() {
+        return unlink_node(link);
+    }
+    let (min, short) = pop_min(&mut node.left, rotations)?;
+    let short = short && solve_left_short(node, rotations);
+    node.update_height();
+    Some((min, short))
+}
+
+/// detach the rightmost node, return it and whether black height dropped
+fn pop_max<K: Ord, V>(
+    link: &mut Option<Box<TreeNode<K, V>>>,
+    rotations: &mut usize,
+) -> Option<(Box<TreeNode<K, V>>, bool)> {
+    let node = link.as_mut()?;
+    if node.right.is_none() {
+        return unlink_node(link);
+    }
+    let (max, short) = pop_max(&mut node.right, rotations)?;
+    let short = short && solve_right_short(node, rotations);
+    node.update_height();
+    Some((max, short))
+}
+
+/// double black: left subtree of `node` lost one black height,
+/// return whether the whole subtree is still short
+fn solve_left_short<K: Ord, V>(node: &mut TreeNode<K, V>, rotations: &mut usize) -> bool {
+    if is_red(&node.left) {
+        node.left.as_mut().unwrap().color = Color::Black;
+        return false;
+    }
+
+    let sibling = node.right.as_mut().unwrap();
+    if sibling.color == Color::Red {
+        // BB-3: sibling is red, rotate it up and solve under a red parent
+        node.rotate_left();
+        *rotations += 1;
+        let left_node = node.left.as_mut().unwrap();
+        solve_left_short(left_node, rotations);
+        left_node.update_height();
+        node.update_height();
+        return false;
+    }
+
+    if is_red(&sibling.right) || is_red(&sibling.left) {
+        // BB-1: sibling has a red child, borrow it by rotation
+        if !is_red(&sibling.right) {
+            sibling.rotate_right();
+            *rotations += 1;
+        }
+        node.rotate_left();
+        *rotations += 1;
+        node.right.as_mut().unwrap().color = Color::Black;
+        return false;
+    }
+
+    // BB-2: sibling and its children are black, recolor
+    sibling.color = Color::Red;
+    if node.color == Color::Red {
+        node.color = Color::Black;
+        return false;
+    }
+    true
+}
+
+/// mirror of `solve_left_short`
+fn solve_right_short<K: Ord, V>(node: &mut TreeNode<K, V>, rotations: &mut usize) -> bool {
+    if is_red(&node.right) {
+        node.right.as_mut().unwrap().color = Color::Black;
+        return false;
+    }
+
+    let sibling = node.left.as_mut().unwrap();
+    if sibling.color == Color::Red {
+        node.rotate_right();
+        *rotations += 1;
+        let right_node = node.right.as_mut().unwrap();
+        solve_right_short(right_node, rotations);
+        right_node.update_height();
+        node.update_height();
+        return false;
+    }
+
+    if is_red(&sibling.left) || is_red(&sibling.right) {
+        if !is_red(&sibling.left) {
+            sibling.rotate_left();
+            *rotations += 1;
+        }
+        node.rotate_right();
+        *rotations += 1;
+        node.left.as_mut().unwrap().color = Color::Black;
+        return false;
+    }
+
+    sibling.color = Color::Red;
+    if node.color == Color::Red {
+        node.color = Color::Black;
+        return false;
+    }
+    true
+}
+
+impl<K: Ord, V> BST<K, V> for RedBlackTree<K, V> {
+    /// search val by key
+    fn search(&mut self, key: &K) -> Option<&V> {
+        let mut ptr = self.0.as_ref();
+        while let Some(node) = ptr {
+            match key.cmp(node.get_key()) {
+                Ordering::Greater => ptr = node.right.as_ref(),
+                Ordering::Less => ptr = node.left.as_ref(),
+                Ordering::Equal => return Some(node.get_val()),
+            }
+        }
+        None
+    }
+    /// insert red node, then solve double red
+    fn insert(&mut self, key: K, val: V) {
+        insert_node(&mut self.0, key, val, &mut self.1);
+        self.blacken_root();
+    }
+    /// remove node, then solve double black
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let (node, _) = remove_node(&mut self.0, key, &mut self.1)?;
+        self.blacken_root();
+        Some(node.val)
+    }
+    /// key-val with the minimum key
+    fn first(&mut self) -> Option<(&K, &V)> {
+        self.0.as_ref().map(|root| root.first().entry())
+    }
+    /// key-val with the maximum key
+    fn last(&mut self) -> Option<(&K, &V)> {
+        self.0.as_ref().map(|root| root.last().entry())
+    }
+    /// key-val with the greatest key less than or equal to `key`
+    fn floor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.floor(key).map(TreeNode::entry)
+    }
+    /// key-val with the least key greater than or equal to `key`
+    fn ceiling(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.ceiling(key).map(TreeNode::entry)
+    }
+    /// key-val with the greatest key strictly less than `key`
+    fn predecessor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.predecessor(key).map(TreeNode::entry)
+    }
+    /// key-val with the least key strictly greater than `key`
+    fn successor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.0.as_ref()?.successor(key).map(TreeNode::entry)
+    }
+    /// remove the leftmost node
+    fn pop_first(&mut self) -> Option<(K, V)> {
+        let (node, _) = pop_min(&mut self.0, &mut self.1)?;
+        self.blacken_root();
+        Some(node.into_entry())
+    }
+    /// remove the rightmost node
+    fn pop_last(&mut self) -> Option<(K, V)> {
+        let (node, _) = pop_max(&mut self.0, &mut self.1)?;
+        self.blacken_root();
+        Some(node.into_entry())
+    }
+}
+
+impl<K, V> IntoIterator for RedBlackTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = TreeIntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TreeIntoIter::new(self.0)
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a RedBlackTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = TreeIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut RedBlackTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = TreeIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_operations() {
+        let mut bst = RedBlackTree::new(10, 1024);
+
+        bst.insert(4, 16);
+        bst.insert(8, 256);
+        bst.insert(5, 32);
+        bst.insert(9, 512);
+        bst.insert(3, 8);
+        bst.insert(2, 4);
+        bst.insert(15, 12345);
+        bst.insert(13, 2344);
+        bst.insert(11, 234);
+        bst.insert(18, 1994);
+        bst.insert(20, 12993);
+        bst.insert(19, 2849);
+        bst.insert(12, 284910);
+        bst.insert(21, 28439);
+        bst.insert(22, 28494);
+        bst.insert(23, 28499);
+        assert_eq!(bst.check_invariants(), Ok(()));
+
+        assert_eq!(bst.search(&9), Some(&512));
+        assert_eq!(bst.search(&19), Some(&2849));
+        bst.insert(19, -12849);
+        assert_eq!(bst.search(&19), Some(&-12849));
+        assert_eq!(bst.search(&13), Some(&2344));
+        assert_eq!(bst.search(&4), Some(&16));
+        assert_eq!(bst.search(&-1), None);
+        assert_eq!(bst.search(&0), None);
+        assert_eq!(bst.search(&23), Some(&28499));
+        assert_eq!(bst.remove(&2), Some(4));
+        assert_eq!(bst.search(&2), None);
+        assert_eq!(bst.remove(&2), None);
+        assert_eq!(bst.remove(&11), Some(234));
+        assert_eq!(bst.remove(&11), None);
+        assert_eq!(bst.search(&11), None);
+        assert_eq!(bst.remove(&20), Some(12993));
+        assert_eq!(bst.remove(&20), None);
+        assert_eq!(bst.search(&20), None);
+        assert_eq!(bst.remove(&8), Some(256));
+        assert_eq!(bst.remove(&8), None);
+        assert_eq!(bst.search(&8), None);
+        assert_eq!(bst.check_invariants(), Ok(()));
+        bst.insert(17, 49);
+        bst.insert(14, 149);
+        bst.insert(16, 169);
+        assert_eq!(bst.remove(&15), Some(12345));
+        assert_eq!(bst.remove(&15), None);
+        assert_eq!(bst.search(&15), None);
+        assert_eq!(bst.remove(&3), Some(8));
+        bst.insert(15, 12345);
+        assert_eq!(bst.check_invariants(), Ok(()));
+        bst = RedBlackTree::new(10, 1024);
+        bst.insert(2, 8);
+        bst.insert(3, 8);
+        bst.insert(4, 16);
+        bst.insert(8, 256);
+        bst.insert(9, 512);
+        bst.insert(11, 234);
+        bst.insert(12, 284910);
+        bst.insert(13, 2344);
+        bst.insert(15, 12345);
+        bst.insert(18, 1994);
+        bst.insert(20, 12993);
+        bst.insert(19, 2849);
+        bst.insert(21, 28439);
+        bst.insert(22, 28494);
+        bst.insert(23, 28499);
+        bst.insert(24, 28499);
+        bst.insert(25, 28499);
+        assert_eq!(bst.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn fix_up_invariants() {
+        let mut bst = RedBlackTree::new(0, 0);
+        let mut keys: Vec<i32> = (1..200).map(|i| (i * 37) % 211).collect();
+        for &k in keys.iter() {
+            bst.insert(k, k);
+            assert_eq!(bst.check_invariants(), Ok(()));
+        }
+        keys.push(0);
+        keys.sort();
+        assert_eq!(bst.keys().copied().collect::<Vec<_>>(), keys);
+        assert!(bst.rotations() > 0);
+
+        let mut rest = Vec::new();
+        for (i, &k) in keys.iter().enumerate() {
+            if i % 3 == 0 {
+                rest.push(k);
+                continue;
+            }
+            assert_eq!(bst.remove(&k), Some(k));
+            assert_eq!(bst.check_invariants(), Ok(()));
+        }
+        let mut rest = rest.into_iter();
+        while let Some((k, _)) = bst.pop_first() {
+            assert_eq!(bst.check_invariants(), Ok(()));
+            assert_eq!(Some(k), rest.next());
+            if let Some((k, _)) = bst.pop_last() {
+                assert_eq!(bst.check_invariants(), Ok(()));
+                assert_eq!(Some(k), rest.next_back());
+            }
+        }
+        assert_eq!(rest.next(), None);
+        assert!(bst.0.is_none());
+    }
+
+    #[test]
+    fn broken_invariants() {
+        let mut bst = RedBlackTree::new(2, 2);
+        bst.insert(1, 1);
+        bst.insert(3, 3);
+        assert_eq!(bst.check_invariants(), Ok(()));
+
+        bst.0.as_mut().unwrap().left.as_mut().unwrap().color = Color::Black;
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::BlackHeight {
+                index: 1,
+                left: 2,
+                right: 1
+            })
+        );
+        bst.0.as_mut().unwrap().left.as_mut().unwrap().color = Color::Red;
+        bst.0.as_mut().unwrap().color = Color::Red;
+        assert_eq!(bst.check_invariants(), Err(InvariantError::RedRoot));
+        bst.0.as_mut().unwrap().color = Color::Black;
+
+        // 1 and 3 turn black, 4 is red
+        bst.insert(4, 4);
+        assert_eq!(bst.check_invariants(), Ok(()));
+        bst.0.as_mut().unwrap().right.as_mut().unwrap().color = Color::Red;
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::DoubleRed { index: 2 })
+        );
+    }
+}
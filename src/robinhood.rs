@@ -0,0 +1,277 @@
+//! Robin Hood linear probing table for `HashMap`
+//!
+//! An entry is placed by linear probing from its home slot, but takes the
+//! slot of any entry nearer to its own home, which then probes on. Probe
+//! distances stay short and even, and a lookup stops as soon as it meets an
+//! entry nearer to home than the key would be. Removal shifts the following
+//! entries back instead of leaving tombstones. The table grows once the load
+//! factor crosses 7/8.
+
+use crate::hashmap::{sealed::Sealed, table_size, HashMapDrain, Slot, Table};
+use crate::stats::{clusters, HashMapStats};
+use crate::{HashMap, RandomState};
+use core::mem::replace;
+
+/// load factor threshold as `LOAD_NUM / LOAD_DEN`, over which the table grows
+const LOAD_NUM: usize = 7;
+const LOAD_DEN: usize = 8;
+
+/// `HashMap` resolving collisions by Robin Hood linear probing
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{RobinHood, RobinHoodHashMap, HashMap};
+///
+/// let mut map: RobinHoodHashMap<&str, i32> = HashMap::with_table(RobinHood::default());
+/// map.insert("a", 1);
+/// assert_eq!(map["a"], 1);
+/// ```
+pub type RobinHoodHashMap<K, V, S = RandomState> = HashMap<K, V, S, RobinHood<K, V>>;
+
+/// Robin Hood linear probing with backward shift removal
+#[derive(Debug)]
+pub struct RobinHood<K, V> {
+    slots: Vec<Slot<K, V>>,
+    len: usize,
+}
+
+impl<K, V> Default for RobinHood<K, V> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<K, V> RobinHood<K, V> {
+    /// home slot of `hash`
+    fn home(&self, hash: u64) -> usize {
+        (hash % self.slots.len() as u64) as usize
+    }
+
+    /// how far the full slot `idx` is from the home of its entry
+    fn distance(&self, idx: usize) -> usize {
+        let size = self.slots.len();
+        (idx + size - self.home(self.slots[idx].hash())) % size
+    }
+
+    /// place an entry known to be absent, displacing entries nearer to their
+    /// home, return the slot index it lands in
+    fn insert_new(&mut self, hash: u64, k: K, v: V) -> usize {
+        let size = self.slots.len();
+        let mut entry = Slot::Full(hash, k, v);
+        let (mut idx, mut dist) = (self.home(hash), 0);
+        let mut landed = None;
+        loop {
+            if !self.slots[idx].is_full() {
+                self.slots[idx] = entry;
+                return landed.unwrap_or(idx);
+            }
+            let resident = self.distance(idx);
+            if resident < dist {
+                entry = replace(&mut self.slots[idx], entry);
+                landed.get_or_insert(idx);
+                dist = resident;
+            }
+            idx = (idx + 1) % size;
+            dist += 1;
+        }
+    }
+
+    /// move every entry into a new table of `size` slots
+    fn resize(&mut self, size: usize) {
+        let mut slots = Vec::with_capacity(size);
+        slots.resize_with(size, || Slot::Empty);
+        let old_slots = replace(&mut self.slots, slots);
+        for slot in old_slots {
+            if let Slot::Full(hash, k, v) = slot {
+                self.insert_new(hash, k, v);
+            }
+        }
+    }
+}
+
+impl<K, V> Sealed for RobinHood<K, V> {}
+
+impl<K, V> Table<K, V> for RobinHood<K, V> {
+    fn with_capacity(capacity: usize) -> Self {
+        let mut table = Self::default();
+        if capacity > 0 {
+            table.resize(table_size(capacity * LOAD_DEN / LOAD_NUM + 1));
+        }
+        table
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.len() * LOAD_NUM / LOAD_DEN
+    }
+
+    fn slots(&self) -> &[Slot<K, V>] {
+        &self.slots
+    }
+
+    fn slots_mut(&mut self) -> &mut [Slot<K, V>] {
+        &mut self.slots
+    }
+
+    fn into_slots(self) -> Vec<Slot<K, V>> {
+        self.slots
+    }
+
+    /// probing stops at an empty slot or at an entry nearer to its home
+    fn find(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        let size = self.slots.len();
+        if size == 0 {
+            return None;
+        }
+        let mut idx = self.home(hash);
+        for dist in 0..size {
+            match &self.slots[idx] {
+                Slot::Full(h, key, _) if *h == hash && eq(key) => return Some(idx),
+                Slot::Full(..) if self.distance(idx) >= dist => idx = (idx + 1) % size,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn insert(&mut self, hash: u64, k: K, v: V) -> usize {
+        if (self.len + 1) * LOAD_DEN > self.slots.len() * LOAD_NUM {
+            self.resize(table_size(self.slots.len() * 2 + 1));
+        }
+        self.len += 1;
+        self.insert_new(hash, k, v)
+    }
+
+    /// shift the entries after `idx` back by one, up to an empty slot or an
+    /// entry in its home
+    fn remove(&mut self, idx: usize) -> (K, V) {
+        let size = self.slots.len();
+        let entry = self.slots[idx].take(Slot::Empty);
+        let mut hole = idx;
+        loop {
+            let next = (hole + 1) % size;
+            if !self.slots[next].is_full() || self.distance(next) == 0 {
+                break;
+            }
+            self.slots.swap(hole, next);
+            hole = next;
+        }
+        self.len -= 1;
+        entry
+    }
+
+    /// walk backwards from an empty slot, so that entries shifted back by a
+    /// removal have been visited already
+    fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        let size = self.slots.len();
+        let start = match self.slots.iter().position(|slot| !slot.is_full()) {
+            Some(start) => start,
+            None => return,
+        };
+        for step in 1..size {
+            let idx = (start + size - step) % size;
+            if let Slot::Full(_, k, v) = &mut self.slots[idx] {
+                if !f(k, v) {
+                    self.remove(idx);
+                }
+            }
+        }
+    }
+
+    fn drain(&mut self) -> HashMapDrain<'_, K, V> {
+        let len = replace(&mut self.len, 0);
+        HashMapDrain {
+            slots: self.slots.iter_mut(),
+            len,
+        }
+    }
+
+    fn stats(&self) -> HashMapStats {
+        let size = self.slots.len();
+        let distances = (0..size)
+            .filter(|&idx| self.slots[idx].is_full())
+            .map(|idx| self.distance(idx));
+        HashMapStats::new(
+            self.len,
+            size,
+            0,
+            distances,
+            clusters(size, |idx| self.slots[idx].is_full()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Colliding;
+
+    /// every entry sits no further from home than the ones it passed by
+    fn check_distances<K, V>(table: &RobinHood<K, V>) {
+        let size = table.slots.len();
+        for idx in 0..size {
+            let prev = (idx + size - 1) % size;
+            if table.slots[idx].is_full() && table.distance(idx) > 0 {
+                assert!(table.slots[prev].is_full());
+                assert!(table.distance(prev) + 1 >= table.distance(idx));
+            }
+        }
+    }
+
+    #[test]
+    fn robin_hood_grow_and_remove() {
+        let mut map = HashMap::with_table(RobinHood::default());
+        for i in 0..5000 {
+            assert_eq!(map.insert(i, -i), None);
+            assert!(map.len() * LOAD_DEN <= map.table.slots.len() * LOAD_NUM);
+        }
+        check_distances(&map.table);
+        for i in (0..5000).step_by(2) {
+            assert_eq!(map.remove(&i), Some(-i));
+        }
+        check_distances(&map.table);
+        for i in 0..5000 {
+            let expected = if i % 2 == 0 { None } else { Some(&-i) };
+            assert_eq!(map.get(&i), expected);
+        }
+        map.retain(|&k, _| k % 3 == 0);
+        check_distances(&map.table);
+        assert_eq!(map.len(), (0..5000).filter(|i| i % 6 == 3).count());
+
+        let stats = map.stats();
+        assert_eq!(stats.probe_histogram.iter().sum::<usize>(), map.len());
+        // a probe never leaves its cluster
+        assert!(stats.max_probe_distance() < stats.max_cluster_size());
+        for i in 0..5000 {
+            assert_eq!(map.contains_key(&i), i % 6 == 3);
+        }
+    }
+
+    #[test]
+    fn robin_hood_colliding() {
+        let mut map = HashMap::with_table(RobinHood::default());
+        for i in 0..300 {
+            map.insert(Colliding(i), i);
+        }
+        for i in (0..300).filter(|i| i % 7 == 0) {
+            assert_eq!(map.remove(&Colliding(i)), Some(i));
+        }
+        check_distances(&map.table);
+        for i in 0..300 {
+            let expected = if i % 7 == 0 { None } else { Some(&i) };
+            assert_eq!(map.get(&Colliding(i)), expected);
+        }
+        map.retain(|k, _| k.0 % 2 == 0);
+        check_distances(&map.table);
+        for i in 0..300 {
+            assert_eq!(map.contains_key(&Colliding(i)), i % 7 != 0 && i % 2 == 0);
+        }
+    }
+}
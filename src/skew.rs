@@ -0,0 +1,231 @@
+//! Skew heap, the self-adjusting cousin of the leftist heap
+//!
+//! Two heaps merge along their right paths like leftist heaps, but every
+//! node on the merged path swaps its children unconditionally instead of
+//! keeping a null path length, which keeps the right paths short in the
+//! amortized sense. A single right path may still be long, so merging and
+//! dropping walk the trees without recursion.
+
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use crate::heap::MergeableHeap;
+use crate::InvariantError;
+use std::mem::swap;
+
+type Link<T> = Option<Box<SkewNode<T>>>;
+
+#[derive(Debug)]
+struct SkewNode<T> {
+    item: T,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+/// Skew heap, a max-heap by default or ordered by any comparator `C`
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::{MergeableHeap, SkewHeap};
+///
+/// let mut heap = SkewHeap::new_min();
+/// heap.push(3);
+/// heap.push(1);
+///
+/// let mut other = SkewHeap::new_min();
+/// other.push(2);
+/// heap.meld(other);
+///
+/// assert_eq!(heap.len(), 3);
+/// assert_eq!(heap.pop(), Some(1));
+/// assert_eq!(heap.peek(), Some(&2));
+/// ```
+#[derive(Debug)]
+pub struct SkewHeap<T, C = MaxComparator> {
+    root: Link<T>,
+    len: usize,
+    cmp: C,
+}
+
+impl<T: Ord> SkewHeap<T> {
+    /// Creates an empty `SkewHeap` as a max-heap.
+    pub fn new() -> Self {
+        Self::with_comparator(MaxComparator)
+    }
+}
+
+impl<T: Ord> SkewHeap<T, MinComparator> {
+    /// Creates an empty `SkewHeap` as a min-heap.
+    pub fn new_min() -> Self {
+        Self::with_comparator(MinComparator)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for SkewHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> SkewHeap<T, C> {
+    /// Creates an empty `SkewHeap` ordered by `cmp`, the greatest item by
+    /// `cmp` being on top.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            root: None,
+            len: 0,
+            cmp,
+        }
+    }
+
+    /// Returns the number of items in the heap.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// merge the right paths of `a` and `b` top down, then relink them bottom
+    /// up, each node taking the merged rest as its left child
+    fn merge(&self, mut a: Link<T>, mut b: Link<T>) -> Link<T> {
+        let mut path = Vec::new();
+        let mut merged = loop {
+            match (a, b) {
+                (None, rest) | (rest, None) => break rest,
+                (Some(mut x), Some(mut y)) => {
+                    if self.cmp.lt(&x.item, &y.item) {
+                        swap(&mut x, &mut y);
+                    }
+                    a = x.right.take();
+                    b = Some(y);
+                    path.push(x);
+                }
+            }
+        };
+        while let Some(mut node) = path.pop() {
+            node.right = node.left.take();
+            node.left = merged;
+            merged = Some(node);
+        }
+        merged
+    }
+
+    /// Checks heap order
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let mut stack: Vec<(&SkewNode<T>, usize)> = self.root.iter().map(|r| (&**r, 0)).collect();
+        let mut count = 0;
+        while let Some((node, depth)) = stack.pop() {
+            count += 1;
+            for child in [&node.left, &node.right]
+                .iter()
+                .filter_map(|c| c.as_deref())
+            {
+                if self.cmp.gt(&child.item, &node.item) {
+                    return Err(InvariantError::HeapOrder { depth: depth + 1 });
+                }
+                stack.push((child, depth + 1));
+            }
+        }
+        if count != self.len {
+            return Err(InvariantError::Size {
+                stored: self.len,
+                actual: count,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T, C: Compare<T>> MergeableHeap<T> for SkewHeap<T, C> {
+    type Peek<'a>
+        = &'a T
+    where
+        Self: 'a;
+
+    fn push(&mut self, item: T) {
+        let node = Some(Box::new(SkewNode {
+            item,
+            left: None,
+            right: None,
+        }));
+        let root = self.root.take();
+        self.root = self.merge(root, node);
+        self.len += 1;
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.root.as_ref().map(|root| &root.item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let root = *self.root.take()?;
+        self.root = self.merge(root.left, root.right);
+        self.len -= 1;
+        Some(root.item)
+    }
+
+    /// amortized *O*(log(*n*))
+    fn meld(&mut self, mut other: Self) {
+        let (a, b) = (self.root.take(), other.root.take());
+        self.root = self.merge(a, b);
+        self.len += other.len;
+    }
+
+    fn is_empty(&self) -> bool {
+        SkewHeap::is_empty(self)
+    }
+}
+
+impl<T, C> Drop for SkewHeap<T, C> {
+    /// free the nodes one at a time, as the tree may be deep
+    fn drop(&mut self) {
+        let mut stack: Vec<_> = self.root.take().into_iter().collect();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skew_push_pop_meld() {
+        let mut a = SkewHeap::new();
+        let mut b = SkewHeap::new();
+        for i in 0..500 {
+            match i % 3 {
+                0 => a.push(i * 7 % 500),
+                _ => b.push(i * 7 % 500),
+            }
+        }
+        assert_eq!(a.check_invariants(), Ok(()));
+        a.meld(b);
+        assert_eq!(a.len(), 500);
+        assert_eq!(a.check_invariants(), Ok(()));
+        let popped: Vec<_> = std::iter::from_fn(|| a.pop()).collect();
+        assert!(popped.into_iter().eq((0..500).rev()));
+    }
+
+    #[test]
+    fn long_right_paths() {
+        // ascending pushes into a min-heap build a long path, which must
+        // neither overflow the stack when merged nor when dropped
+        let mut heap = SkewHeap::new_min();
+        for i in 0..200_000 {
+            heap.push(i);
+        }
+        let mut other = SkewHeap::new_min();
+        for i in (0..200_000).rev() {
+            other.push(i);
+        }
+        heap.meld(other);
+        assert_eq!(heap.pop(), Some(0));
+        assert_eq!(heap.pop(), Some(0));
+        assert_eq!(heap.peek(), Some(&1));
+    }
+}
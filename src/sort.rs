@@ -1,43 +1,301 @@
 //! All kinds of Sorting Algorithm
-/// quicksort algorithm
-/// assert!(hi > lo);
-use std::mem::take;
+//!
+//! [`sort`] is an introsort: quicksort pivoting on the median of three,
+//! switching to heap sort once recursion gets deeper than twice the log of
+//! the length, so that it runs in *O*(*n* log(*n*)) even on adversarial
+//! input, and to insertion sort on short ranges. It is not stable.
+//!
+//! [`merge_sort`] is stable. It merge sorts the positions of the items,
+//! which need no `Clone` to be buffered, then moves each item once.
+//!
+//! Both have parallel versions on scoped std threads, configured by
+//! [`ParSort`].
+//!
+//! [`radix`] holds sorts that do not compare, for integers, floats and byte
+//! strings.
+//!
+//! [`select_nth`] finds the item that would be at some index once sorted,
+//! in *O*(*n*), without sorting.
+//!
+//! [`external`] sorts records from a reader to a writer through temporary
+//! files, for inputs that do not fit in memory.
+
+use crate::compare::{ByKey, Compare};
+use crate::dary::dary_heap_sort_by;
+use std::cmp::Ordering;
+
+pub mod external;
+mod parallel;
+pub mod radix;
+mod select;
+
+pub use parallel::{par_merge_sort, par_merge_sort_by, par_sort, par_sort_by, ParSort};
+pub use select::{bottom_k, majority, median, select_nth, select_nth_by, top_k};
+
+/// ranges this short are insertion sorted
+const INSERTION_THRESHOLD: usize = 16;
+
+/// Sorts the slice, not stably, in *O*(*n* log(*n*)).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::sort;
+///
+/// let mut v = [5, 4, 1, 3, 2];
+/// sort(&mut v);
+/// assert_eq!(v, [1, 2, 3, 4, 5]);
+/// ```
+pub fn sort<T: Ord>(v: &mut [T]) {
+    introsort(v, &T::cmp);
+}
+
+/// Sorts the slice with a comparator function, not stably, in
+/// *O*(*n* log(*n*)).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::sort_by;
+///
+/// let mut v = [5, 4, 1, 3, 2];
+/// sort_by(&mut v, |a, b| b.cmp(a));
+/// assert_eq!(v, [5, 4, 3, 2, 1]);
+/// ```
+pub fn sort_by<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], compare: F) {
+    introsort(v, &compare);
+}
+
+/// Sorts the slice with a key extraction function, not stably, in
+/// *O*(*n* log(*n*)).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::sort_by_key;
+///
+/// let mut v = [-5i32, 4, 1, -3, 2];
+/// sort_by_key(&mut v, |k| k.abs());
+/// assert_eq!(v, [1, 2, -3, 4, -5]);
+/// ```
+pub fn sort_by_key<T, K: Ord, F: Fn(&T) -> K>(v: &mut [T], f: F) {
+    introsort(v, &ByKey(f));
+}
+
+/// Sorts the slice, stably, in *O*(*n* log(*n*)) with *O*(*n*) extra space.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::merge_sort;
+///
+/// let mut v = [5, 4, 1, 3, 2];
+/// merge_sort(&mut v);
+/// assert_eq!(v, [1, 2, 3, 4, 5]);
+/// ```
+pub fn merge_sort<T: Ord>(v: &mut [T]) {
+    stable_sort(v, &T::cmp);
+}
+
+/// Sorts the slice with a comparator function, stably, in *O*(*n* log(*n*)).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::merge_sort_by;
+///
+/// let mut v = [(1, 'a'), (0, 'b'), (1, 'c'), (0, 'd')];
+/// merge_sort_by(&mut v, |a, b| a.0.cmp(&b.0));
+/// assert_eq!(v, [(0, 'b'), (0, 'd'), (1, 'a'), (1, 'c')]);
+/// ```
+pub fn merge_sort_by<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], compare: F) {
+    stable_sort(v, &compare);
+}
+
+/// Sorts the slice with a key extraction function, stably, in
+/// *O*(*n* log(*n*)).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::merge_sort_by_key;
+///
+/// let mut v = ["bb", "a", "ccc", "b", "aa"];
+/// merge_sort_by_key(&mut v, |s| s.len());
+/// assert_eq!(v, ["a", "b", "bb", "aa", "ccc"]);
+/// ```
+pub fn merge_sort_by_key<T, K: Ord, F: Fn(&T) -> K>(v: &mut [T], f: F) {
+    stable_sort(v, &ByKey(f));
+}
+
 /// quick sort algorithm
-pub fn quicksort<T: Ord + Default>(slice: &mut [T], lo: usize, hi: usize) {
-    if lo + 2 > hi {
-        return;
+/// half-open inclusive, an introsort of `slice[lo..hi]`
+pub fn quicksort<T: Ord>(slice: &mut [T], lo: usize, hi: usize) {
+    sort(&mut slice[lo..hi]);
+}
+
+/// introsort of `v`, recursing at most twice the log of its length
+pub(crate) fn introsort<T, C: Compare<T>>(v: &mut [T], cmp: &C) {
+    let limit = 2 * (usize::BITS - v.len().leading_zeros());
+    introsort_limited(v, cmp, limit);
+}
+
+fn introsort_limited<T, C: Compare<T>>(mut v: &mut [T], cmp: &C, mut limit: u32) {
+    while v.len() > INSERTION_THRESHOLD {
+        if limit == 0 {
+            // same as `heap_sort`, ordered by `cmp`
+            dary_heap_sort_by::<2, _, _>(v, cmp);
+            return;
+        }
+        limit -= 1;
+        let mid = partition(v, cmp);
+        // recurse into the shorter side, loop on the longer
+        let (left, right) = v.split_at_mut(mid);
+        let right = &mut right[1..];
+        if left.len() < right.len() {
+            introsort_limited(left, cmp, limit);
+            v = right;
+        } else {
+            introsort_limited(right, cmp, limit);
+            v = left;
+        }
+    }
+    insertion_sort(v, cmp);
+}
+
+/// move the median of the first, middle and last items to the front
+fn median_of_three<T, C: Compare<T>>(v: &mut [T], cmp: &C) {
+    let (a, b, c) = (0, v.len() / 2, v.len() - 1);
+    if cmp.lt(&v[b], &v[a]) {
+        v.swap(a, b);
     }
+    if cmp.lt(&v[c], &v[b]) {
+        v.swap(b, c);
+        if cmp.lt(&v[b], &v[a]) {
+            v.swap(a, b);
+        }
+    }
+    v.swap(a, b);
+}
+
+/// partition `v`, at least 2 long, around the median of three, return the
+/// pivot's final index
+pub(crate) fn partition<T, C: Compare<T>>(v: &mut [T], cmp: &C) -> usize {
+    median_of_three(v, cmp);
+    partition_around_first(v, cmp)
+}
 
-    let mut mi = lo;
+/// partition `v`, at least 2 long, around its first item, return the
+/// pivot's final index
+///
+/// Both scans stop on items equal to the pivot, so that runs of equal items
+/// are split evenly.
+fn partition_around_first<T, C: Compare<T>>(v: &mut [T], cmp: &C) -> usize {
+    let (mut i, mut j) = (1, v.len() - 1);
+    loop {
+        while i <= j && cmp.lt(&v[i], &v[0]) {
+            i += 1;
+        }
+        while i <= j && cmp.gt(&v[j], &v[0]) {
+            j -= 1;
+        }
+        if i >= j {
+            break;
+        }
+        v.swap(i, j);
+        i += 1;
+        j -= 1;
+    }
+    v.swap(0, j);
+    j
+}
 
-    for k in lo + 1..hi {
-        if slice[k] < slice[lo] {
-            mi += 1;
-            let temp_mi = take(&mut slice[mi]);
-            let temp_k = take(&mut slice[k]);
-            slice[mi] = temp_k;
-            slice[k] = temp_mi;
+/// stable insertion sort
+pub(crate) fn insertion_sort<T, C: Compare<T>>(v: &mut [T], cmp: &C) {
+    for i in 1..v.len() {
+        let mut j = i;
+        while j > 0 && cmp.lt(&v[j], &v[j - 1]) {
+            v.swap(j, j - 1);
+            j -= 1;
         }
     }
+}
 
-    let pivot = take(&mut slice[lo]);
-    let temp_mi = take(&mut slice[mi]);
-    slice[lo] = temp_mi;
-    slice[mi] = pivot;
+/// stable merge sort of `v`, through the positions of its items
+pub(crate) fn stable_sort<T, C: Compare<T>>(v: &mut [T], cmp: &C) {
+    let mut order: Vec<usize> = (0..v.len()).collect();
+    let mut buf = vec![0; v.len()];
+    merge_sort_positions(v, &mut order, &mut buf, cmp);
+    permute(v, order);
+}
 
-    quicksort(slice, lo, mi);
-    quicksort(slice, mi + 1, hi);
+/// merge sort `order`, positions in `v`, by their items
+fn merge_sort_positions<T, C: Compare<T>>(
+    v: &[T],
+    order: &mut [usize],
+    buf: &mut [usize],
+    cmp: &C,
+) {
+    let by_item = |a: &usize, b: &usize| cmp.compare(&v[*a], &v[*b]);
+    if order.len() <= INSERTION_THRESHOLD {
+        insertion_sort(order, &by_item);
+        return;
+    }
+    let mid = order.len() / 2;
+    let (left, right) = order.split_at_mut(mid);
+    merge_sort_positions(v, left, &mut buf[..mid], cmp);
+    merge_sort_positions(v, right, &mut buf[mid..], cmp);
+    if by_item.le(&order[mid - 1], &order[mid]) {
+        return;
+    }
+    buf.copy_from_slice(order);
+    merge(&buf[..mid], &buf[mid..], order, &by_item);
 }
 
-/// find maj number or median number
+/// merge the sorted `left` and `right` into `out`, taking from `left` on
+/// ties
+pub(crate) fn merge<T: Copy, C: Compare<T>>(left: &[T], right: &[T], out: &mut [T], cmp: &C) {
+    let (mut i, mut j) = (0, 0);
+    for slot in out.iter_mut() {
+        if j == right.len() || (i < left.len() && cmp.le(&left[i], &right[j])) {
+            *slot = left[i];
+            i += 1;
+        } else {
+            *slot = right[j];
+            j += 1;
+        }
+    }
+}
+
+/// move the item at `order[k]` to `k`, following each cycle of the
+/// permutation
+fn permute<T>(v: &mut [T], mut order: Vec<usize>) {
+    for start in 0..v.len() {
+        let mut pos = start;
+        while order[pos] != start {
+            let src = order[pos];
+            order[pos] = pos;
+            v.swap(pos, src);
+            pos = src;
+        }
+        order[pos] = pos;
+    }
+}
+
+/// Boyer-Moore majority vote, the item that survives pairing off unequal
+/// items
+///
+/// It is the majority item, if there is one, but is not checked to be: use
+/// [`majority`] for that.
+#[deprecated(note = "use majority")]
 pub fn find_majory<T: Ord + Default + Copy>(slice: &mut [T]) -> T {
     let mut maj = T::default();
     let mut c = 0;
-    for i in 0..slice.len() {
+    for &item in slice.iter() {
         if c == 0 {
-            maj = slice[i];
+            maj = item;
             c = 1;
-        } else if maj == slice[i] {
+        } else if maj == item {
             c += 1;
         } else {
             c -= 1;
@@ -50,6 +308,7 @@ pub fn find_majory<T: Ord + Default + Copy>(slice: &mut [T]) -> T {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::pseudo_random;
     #[test]
     fn test_quicksort() {
         let mut array = [3, 4, 2, 5, 48, 19, 39, 390, 43, 12, 8, 9, 10, 7, 5];
@@ -115,4 +374,85 @@ mod tests {
             move_array
         );
     }
+
+    /// inputs that break naive quicksorts
+    fn patterns(n: usize) -> Vec<Vec<u32>> {
+        let n32 = n as u32;
+        vec![
+            pseudo_random(n, 1 << 20),
+            pseudo_random(n, 4),
+            (0..n32).collect(),
+            (0..n32).rev().collect(),
+            vec![7; n],
+            (0..n32).map(|i| i.min(n32 - i)).collect(),
+            (0..n32)
+                .map(|i| if i % 2 == 0 { i } else { n32 - i })
+                .collect(),
+        ]
+    }
+
+    #[test]
+    fn introsort_patterns() {
+        for &n in [0, 1, 2, 3, 15, 16, 17, 100, 1000, 10_000].iter() {
+            for items in patterns(n) {
+                let mut expected = items.clone();
+                expected.sort_unstable();
+
+                let mut v = items.clone();
+                sort(&mut v);
+                assert_eq!(v, expected);
+
+                let mut v = items.clone();
+                sort_by(&mut v, |a, b| b.cmp(a));
+                assert!(v.iter().rev().eq(expected.iter()));
+
+                let mut v = items.clone();
+                merge_sort(&mut v);
+                assert_eq!(v, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn heap_sort_fallback() {
+        for items in patterns(500) {
+            let mut expected = items.clone();
+            expected.sort_unstable();
+            let mut v = items.clone();
+            introsort_limited(&mut v, &u32::cmp, 0);
+            assert_eq!(v, expected);
+            let mut v = items.clone();
+            introsort_limited(&mut v, &u32::cmp, 2);
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn merge_sort_is_stable() {
+        let keys = pseudo_random(2000, 10);
+        let mut v: Vec<_> = keys.iter().copied().zip(0..).collect();
+        merge_sort_by_key(&mut v, |&(key, _)| key);
+        assert!(v.windows(2).all(|w| w[0].0 < w[1].0 || w[0] < w[1]));
+
+        let mut v: Vec<_> = keys.iter().copied().zip(0..).collect();
+        merge_sort_by(&mut v, |a, b| b.0.cmp(&a.0));
+        assert!(v.windows(2).all(|w| w[0].0 > w[1].0 || w[0].1 < w[1].1));
+
+        let mut names: Vec<String> = keys.iter().map(|k| format!("{}", k * 37)).collect();
+        let mut expected = names.clone();
+        expected.sort_by_key(|s| s.len());
+        merge_sort_by_key(&mut names, |s| s.len());
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn sort_without_default() {
+        use std::num::NonZeroU32;
+        let ids = [7, 3, 9, 1, 3, 8];
+        let mut array: Vec<_> = ids.iter().map(|&i| NonZeroU32::new(i).unwrap()).collect();
+        let hi = array.len();
+        quicksort(&mut array, 0, hi);
+        let sorted: Vec<_> = array.iter().map(|id| id.get()).collect();
+        assert_eq!(sorted, [1, 3, 3, 7, 8, 9]);
+    }
 }
@@ -0,0 +1,523 @@
+//! External merge sort, for inputs larger than memory
+//!
+//! [`ExternalSorter`] reads records until they fill its memory budget, sorts
+//! them with [`merge_sort_by`](super::merge_sort_by) and writes them out to a
+//! temporary file as a sorted run, until the input ends. It then merges the
+//! runs through a [`BinaryHeap`] holding the head record of each, at most
+//! `fan_in` runs at a time, merging groups of runs into longer ones first if
+//! there are more. Input that fits in memory is sorted without any file.
+//!
+//! How records are cut out of the input and written to the output is up to a
+//! [`Framing`]: [`Newline`] for lines, [`FixedWidth`] for records of some
+//! number of bytes, [`LengthPrefixed`] for records behind their length. Runs
+//! are always length prefixed, so that records may hold any byte.
+//!
+//! # Examples
+//!
+//! ```
+//! use tsinghua_ds::external::{ExternalSorter, Newline};
+//!
+//! let input = "pear\nfig\napple\nkiwi\n".as_bytes();
+//! let mut output = Vec::new();
+//! let stats = ExternalSorter::new(Newline)
+//!     .memory(64)
+//!     .sort(input, &mut output)
+//!     .unwrap();
+//! assert_eq!(output, b"apple\nfig\nkiwi\npear\n");
+//! assert_eq!(stats.records, 4);
+//! assert!(stats.runs > 1);
+//! ```
+
+use super::merge_sort_by;
+use crate::heap::BinaryHeap;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::mem::{size_of, take};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+/// default memory budget, 64 MiB
+const MEMORY: usize = 64 << 20;
+/// default number of runs merged at once
+const FAN_IN: usize = 64;
+
+/// How records are read from the input and written to the output
+pub trait Framing {
+    /// Reads the next record into `record`, empty, returning `false` at the
+    /// end of the input.
+    fn read_record<R: BufRead>(&self, reader: &mut R, record: &mut Vec<u8>) -> io::Result<bool>;
+    /// Writes `record` out.
+    fn write_record<W: Write>(&self, writer: &mut W, record: &[u8]) -> io::Result<()>;
+}
+
+/// Records are lines, ended by `\n` but for maybe the last
+///
+/// The `\n` is not part of the record, and is written after each record.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Newline;
+
+impl Framing for Newline {
+    fn read_record<R: BufRead>(&self, reader: &mut R, record: &mut Vec<u8>) -> io::Result<bool> {
+        if reader.read_until(b'\n', record)? == 0 {
+            return Ok(false);
+        }
+        if record.last() == Some(&b'\n') {
+            record.pop();
+        }
+        Ok(true)
+    }
+
+    fn write_record<W: Write>(&self, writer: &mut W, record: &[u8]) -> io::Result<()> {
+        writer.write_all(record)?;
+        writer.write_all(b"\n")
+    }
+}
+
+/// Records all have the same number of bytes
+#[derive(Debug, Clone, Copy)]
+pub struct FixedWidth {
+    width: usize,
+}
+
+impl FixedWidth {
+    /// Records of `width` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0.
+    pub fn new(width: usize) -> Self {
+        assert!(width > 0, "records must not be empty");
+        Self { width }
+    }
+}
+
+impl Framing for FixedWidth {
+    /// Fails with `UnexpectedEof` if the input ends within a record.
+    fn read_record<R: BufRead>(&self, reader: &mut R, record: &mut Vec<u8>) -> io::Result<bool> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(false);
+        }
+        record.resize(self.width, 0);
+        reader.read_exact(record)?;
+        Ok(true)
+    }
+
+    /// Fails with `InvalidInput` if `record` is not `width` bytes long.
+    fn write_record<W: Write>(&self, writer: &mut W, record: &[u8]) -> io::Result<()> {
+        if record.len() != self.width {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "record of the wrong width",
+            ));
+        }
+        writer.write_all(record)
+    }
+}
+
+/// Records follow their length, as a little-endian `u32`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthPrefixed;
+
+impl Framing for LengthPrefixed {
+    /// Fails with `UnexpectedEof` if the input ends within a record.
+    fn read_record<R: BufRead>(&self, reader: &mut R, record: &mut Vec<u8>) -> io::Result<bool> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(false);
+        }
+        let mut len = [0; 4];
+        reader.read_exact(&mut len)?;
+        // grow the record only as far as the input goes, the prefix may be
+        // corrupt
+        let len = u64::from(u32::from_le_bytes(len));
+        if reader.by_ref().take(len).read_to_end(record)? as u64 != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "input ends within a record",
+            ));
+        }
+        Ok(true)
+    }
+
+    /// Fails with `InvalidInput` if `record` is 4 GiB or longer.
+    fn write_record<W: Write>(&self, writer: &mut W, record: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(record.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record too long"))?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(record)
+    }
+}
+
+/// What an external sort went through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExternalSortStats {
+    /// number of records sorted
+    pub records: u64,
+    /// number of sorted runs written to temporary files, 0 if the input fit
+    /// in memory
+    pub runs: usize,
+    /// number of merge passes over the runs before the last one, writing
+    /// longer runs
+    pub merge_passes: usize,
+}
+
+/// Sorts records from any `Read` to any `Write`, within a memory budget
+#[derive(Debug, Clone)]
+pub struct ExternalSorter<F> {
+    framing: F,
+    memory: usize,
+    fan_in: usize,
+    temp_dir: PathBuf,
+}
+
+impl<F: Framing> ExternalSorter<F> {
+    /// Sorts records cut by `framing`, within 64 MiB, merging up to 64 runs
+    /// at once, in the system's temporary directory.
+    pub fn new(framing: F) -> Self {
+        Self {
+            framing,
+            memory: MEMORY,
+            fan_in: FAN_IN,
+            temp_dir: env::temp_dir(),
+        }
+    }
+
+    /// Hold at most `bytes` of records in memory at a time, counting the
+    /// bytes of each record and the `Vec` holding it. A run always has at
+    /// least one record.
+    pub fn memory(self, bytes: usize) -> Self {
+        Self {
+            memory: bytes,
+            ..self
+        }
+    }
+
+    /// Merge at most `fan_in` runs at once, at least 2, each keeping an open
+    /// file and its buffer.
+    pub fn fan_in(self, fan_in: usize) -> Self {
+        Self {
+            fan_in: fan_in.max(2),
+            ..self
+        }
+    }
+
+    /// Write runs to temporary files in `dir`.
+    pub fn temp_dir(self, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            temp_dir: dir.into(),
+            ..self
+        }
+    }
+
+    /// Sorts the records of `input` by their bytes into `output`.
+    pub fn sort<R: Read, W: Write>(&self, input: R, output: W) -> io::Result<ExternalSortStats> {
+        self.sort_by(input, output, <[u8]>::cmp)
+    }
+
+    /// Sorts the records of `input` with a comparator function into
+    /// `output`, stably.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsinghua_ds::external::{ExternalSorter, FixedWidth};
+    ///
+    /// // 2-byte records ordered by their second byte
+    /// let mut output = Vec::new();
+    /// ExternalSorter::new(FixedWidth::new(2))
+    ///     .sort_by(&b"a3b1c2d1"[..], &mut output, |a, b| a[1].cmp(&b[1]))
+    ///     .unwrap();
+    /// assert_eq!(output, b"b1d1c2a3");
+    /// ```
+    pub fn sort_by<R: Read, W: Write, C: Fn(&[u8], &[u8]) -> Ordering>(
+        &self,
+        input: R,
+        output: W,
+        compare: C,
+    ) -> io::Result<ExternalSortStats> {
+        let mut reader = BufReader::new(input);
+        let mut output = BufWriter::new(output);
+        let mut stats = ExternalSortStats {
+            records: 0,
+            runs: 0,
+            merge_passes: 0,
+        };
+        let mut runs = Vec::new();
+        let mut records = Vec::new();
+        let mut used = 0;
+        loop {
+            let mut record = Vec::new();
+            if !self.framing.read_record(&mut reader, &mut record)? {
+                break;
+            }
+            stats.records += 1;
+            used += record.len() + size_of::<Vec<u8>>();
+            records.push(record);
+            if used >= self.memory {
+                runs.push(self.write_run(take(&mut records), &compare)?);
+                used = 0;
+            }
+        }
+
+        if runs.is_empty() {
+            merge_sort_by(&mut records, |a, b| compare(a, b));
+            for record in records.iter() {
+                self.framing.write_record(&mut output, record)?;
+            }
+        } else {
+            if !records.is_empty() {
+                runs.push(self.write_run(records, &compare)?);
+            }
+            stats.runs = runs.len();
+            while runs.len() > self.fan_in {
+                runs = self.merge_pass(runs, &compare)?;
+                stats.merge_passes += 1;
+            }
+            let framing = &self.framing;
+            merge_runs(runs, &compare, |record| {
+                framing.write_record(&mut output, record)
+            })?;
+        }
+        output.flush()?;
+        Ok(stats)
+    }
+
+    /// sort `records` and write them to a new run
+    fn write_run<C: Fn(&[u8], &[u8]) -> Ordering>(
+        &self,
+        mut records: Vec<Vec<u8>>,
+        compare: &C,
+    ) -> io::Result<Run> {
+        merge_sort_by(&mut records, |a, b| compare(a, b));
+        let (run, mut writer) = Run::create(&self.temp_dir)?;
+        for record in records.iter() {
+            LengthPrefixed.write_record(&mut writer, record)?;
+        }
+        writer.flush()?;
+        Ok(run)
+    }
+
+    /// merge each `fan_in` adjacent runs into one, keeping the runs in input
+    /// order so that the sort stays stable
+    fn merge_pass<C: Fn(&[u8], &[u8]) -> Ordering>(
+        &self,
+        runs: Vec<Run>,
+        compare: &C,
+    ) -> io::Result<Vec<Run>> {
+        let mut merged = Vec::new();
+        let mut runs = runs.into_iter().peekable();
+        while runs.peek().is_some() {
+            let group: Vec<Run> = runs.by_ref().take(self.fan_in).collect();
+            let (run, mut writer) = Run::create(&self.temp_dir)?;
+            merge_runs(group, compare, |record| {
+                LengthPrefixed.write_record(&mut writer, record)
+            })?;
+            writer.flush()?;
+            merged.push(run);
+        }
+        Ok(merged)
+    }
+}
+
+/// k-way merge of `runs`, handing each record to `emit` in order, records
+/// of earlier runs first on ties
+fn merge_runs<C: Fn(&[u8], &[u8]) -> Ordering>(
+    runs: Vec<Run>,
+    compare: &C,
+    mut emit: impl FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut readers = runs.iter().map(Run::open).collect::<io::Result<Vec<_>>>()?;
+    // the least record on top, then the earliest run
+    let mut heads = BinaryHeap::with_capacity_and_comparator(
+        readers.len(),
+        |a: &(Vec<u8>, usize), b: &(Vec<u8>, usize)| {
+            compare(&b.0, &a.0).then_with(|| b.1.cmp(&a.1))
+        },
+    );
+    for (index, reader) in readers.iter_mut().enumerate() {
+        let mut record = Vec::new();
+        if LengthPrefixed.read_record(reader, &mut record)? {
+            heads.push((record, index));
+        }
+    }
+    while let Some((mut record, index)) = heads.pop() {
+        emit(&record)?;
+        record.clear();
+        if LengthPrefixed.read_record(&mut readers[index], &mut record)? {
+            heads.push((record, index));
+        }
+    }
+    Ok(())
+}
+
+/// numbers the temporary files of this process
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// sorted run in a temporary file, removed on drop
+#[derive(Debug)]
+struct Run {
+    path: PathBuf,
+}
+
+impl Run {
+    /// new empty run in `dir`, with a writer to fill it
+    fn create(dir: &Path) -> io::Result<(Run, BufWriter<File>)> {
+        loop {
+            let n = RUN_COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+            let path = dir.join(format!("tsinghua_ds-{}-{}.run", process::id(), n));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((Run { path }, BufWriter::new(file))),
+                // left over from an earlier process with the same id
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn open(&self) -> io::Result<BufReader<File>> {
+        File::open(&self.path).map(BufReader::new)
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pseudo_random;
+
+    /// empty directory of its own for a test's runs
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("tsinghua_ds-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// no run is left behind
+    fn assert_cleaned(dir: &Path) {
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 0);
+        fs::remove_dir(dir).unwrap();
+    }
+
+    #[test]
+    fn lines() {
+        let dir = temp_dir("lines");
+        let lines: Vec<String> = pseudo_random(3000, u32::MAX)
+            .into_iter()
+            .map(|x| format!("{:x}", x >> (x % 32)))
+            .collect();
+        let input = lines.join("\n");
+        let mut expected = lines.clone();
+        expected.sort();
+
+        for &(memory, fan_in) in [(usize::MAX, 64), (4096, 64), (4096, 3), (1, 2)].iter() {
+            let sorter = ExternalSorter::new(Newline)
+                .memory(memory)
+                .fan_in(fan_in)
+                .temp_dir(&dir);
+            let mut output = Vec::new();
+            let stats = sorter.sort(input.as_bytes(), &mut output).unwrap();
+            let output = String::from_utf8(output).unwrap();
+            assert!(output.lines().eq(expected.iter().map(String::as_str)));
+            assert_eq!(stats.records, 3000);
+            match memory {
+                usize::MAX => assert_eq!(stats.runs, 0),
+                1 => assert_eq!(stats.runs, 3000),
+                _ => assert!(stats.runs > 1),
+            }
+            assert_eq!(stats.merge_passes > 0, stats.runs > fan_in);
+        }
+        assert_cleaned(&dir);
+    }
+
+    #[test]
+    fn fixed_width_is_stable() {
+        let dir = temp_dir("fixed");
+        // 8-byte records, a 1-byte key then the record's index
+        let mut input = Vec::new();
+        for (index, x) in pseudo_random(2000, u32::MAX).into_iter().enumerate() {
+            input.push(b'a' + (x % 5) as u8);
+            input.extend_from_slice(&(index as u32).to_be_bytes()[1..]);
+            input.extend_from_slice(b"...\n");
+        }
+        let mut output = Vec::new();
+        let stats = ExternalSorter::new(FixedWidth::new(8))
+            .memory(1000)
+            .fan_in(4)
+            .temp_dir(&dir)
+            .sort_by(&input[..], &mut output, |a, b| a[0].cmp(&b[0]))
+            .unwrap();
+        assert_eq!(output.len(), input.len());
+        assert!(stats.merge_passes > 0);
+        let records: Vec<&[u8]> = output.chunks(8).collect();
+        // by key, then by index since the index is big-endian
+        assert!(records.windows(2).all(|w| w[0][..4] < w[1][..4]));
+        assert_cleaned(&dir);
+    }
+
+    #[test]
+    fn length_prefixed() {
+        let dir = temp_dir("prefixed");
+        let records: Vec<Vec<u8>> = pseudo_random(1000, u32::MAX)
+            .into_iter()
+            .map(|x| x.to_le_bytes()[..(x % 5) as usize].to_vec())
+            .collect();
+        let mut input = Vec::new();
+        for record in records.iter() {
+            LengthPrefixed.write_record(&mut input, record).unwrap();
+        }
+        let mut output = Vec::new();
+        ExternalSorter::new(LengthPrefixed)
+            .memory(500)
+            .temp_dir(&dir)
+            .sort(&input[..], &mut output)
+            .unwrap();
+
+        let mut expected = records;
+        expected.sort();
+        let mut reader = &output[..];
+        let mut record = Vec::new();
+        for want in expected.iter() {
+            record.clear();
+            assert!(LengthPrefixed
+                .read_record(&mut reader, &mut record)
+                .unwrap());
+            assert_eq!(&record, want);
+        }
+        assert!(!LengthPrefixed
+            .read_record(&mut reader, &mut record)
+            .unwrap());
+        assert_cleaned(&dir);
+    }
+
+    #[test]
+    fn truncated_input() {
+        let dir = temp_dir("truncated");
+        let sorter = ExternalSorter::new(FixedWidth::new(4)).temp_dir(&dir);
+        let err = sorter.sort(&b"abcdefg"[..], Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let sorter = ExternalSorter::new(LengthPrefixed).memory(1).temp_dir(&dir);
+        let err = sorter
+            .sort(&[1, 0, 0, 0, 9, 5, 0][..], Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // a 4 GiB prefix over 2 bytes fails without allocating for it
+        let mut record = Vec::new();
+        let err = LengthPrefixed
+            .read_record(&mut &[0xff, 0xff, 0xff, 0xff, 1, 2][..], &mut record)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(record.capacity() < 1 << 20);
+        assert_cleaned(&dir);
+    }
+}
@@ -0,0 +1,282 @@
+//! Parallel sorts on scoped std threads
+//!
+//! [`par_sort`] partitions like [`sort`](super::sort), then sorts both sides
+//! on two threads. [`par_merge_sort`] sorts both halves on two threads, then
+//! splits the longer run at its middle item and the other where that item
+//! would go, and merges both pairs on two threads. Ranges shorter than
+//! [`ParSort::min_len`], or left once every thread is busy, are sorted
+//! serially.
+
+use super::{introsort_limited, merge, merge_sort_positions, partition, permute};
+use crate::compare::Compare;
+use std::cmp::Ordering;
+use std::thread;
+
+/// ranges shorter than this are sorted serially by default
+const MIN_LEN: usize = 1 << 13;
+
+/// Settings of the parallel sorts, how many threads they may use and how
+/// short a range must be to be sorted serially
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::ParSort;
+///
+/// let mut v: Vec<u32> = (0..10_000).rev().collect();
+/// ParSort::new().threads(4).min_len(1000).sort(&mut v);
+/// assert!(v.windows(2).all(|w| w[0] <= w[1]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParSort {
+    threads: usize,
+    min_len: usize,
+}
+
+impl Default for ParSort {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParSort {
+    /// One thread per CPU, ranges shorter than 8192 items sorted serially.
+    pub fn new() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            threads,
+            min_len: MIN_LEN,
+        }
+    }
+
+    /// Use at most `threads` threads, at least 1.
+    pub fn threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    /// Sort ranges shorter than `min_len` items serially, at least 2.
+    pub fn min_len(self, min_len: usize) -> Self {
+        Self {
+            min_len: min_len.max(2),
+            ..self
+        }
+    }
+
+    /// Sorts the slice, not stably.
+    pub fn sort<T: Ord + Send>(&self, v: &mut [T]) {
+        self.sort_by(v, T::cmp);
+    }
+
+    /// Sorts the slice with a comparator function, not stably.
+    pub fn sort_by<T: Send, F: Fn(&T, &T) -> Ordering + Sync>(&self, v: &mut [T], compare: F) {
+        let limit = 2 * (usize::BITS - v.len().leading_zeros());
+        self.quicksort(v, &compare, limit, self.threads);
+    }
+
+    /// Sorts the slice, stably.
+    pub fn merge_sort<T: Ord + Sync>(&self, v: &mut [T]) {
+        self.merge_sort_by(v, T::cmp);
+    }
+
+    /// Sorts the slice with a comparator function, stably.
+    pub fn merge_sort_by<T: Sync, F: Fn(&T, &T) -> Ordering + Sync>(
+        &self,
+        v: &mut [T],
+        compare: F,
+    ) {
+        let mut order: Vec<usize> = (0..v.len()).collect();
+        let mut buf = vec![0; v.len()];
+        self.merge_sort_positions(v, &mut order, &mut buf, &compare, self.threads);
+        permute(v, order);
+    }
+
+    /// introsort of `v` on up to `threads` threads
+    fn quicksort<T: Send, C: Compare<T> + Sync>(
+        &self,
+        v: &mut [T],
+        cmp: &C,
+        limit: u32,
+        threads: usize,
+    ) {
+        if threads < 2 || v.len() < self.min_len || limit == 0 {
+            introsort_limited(v, cmp, limit);
+            return;
+        }
+        let mid = partition(v, cmp);
+        let (left, right) = v.split_at_mut(mid);
+        let right = &mut right[1..];
+        let half = threads / 2;
+        thread::scope(|s| {
+            s.spawn(|| self.quicksort(left, cmp, limit - 1, half));
+            self.quicksort(right, cmp, limit - 1, threads - half);
+        });
+    }
+
+    /// merge sort `order`, positions in `v`, on up to `threads` threads
+    fn merge_sort_positions<T: Sync, C: Compare<T> + Sync>(
+        &self,
+        v: &[T],
+        order: &mut [usize],
+        buf: &mut [usize],
+        cmp: &C,
+        threads: usize,
+    ) {
+        if threads < 2 || order.len() < self.min_len {
+            merge_sort_positions(v, order, buf, cmp);
+            return;
+        }
+        let mid = order.len() / 2;
+        let half = threads / 2;
+        {
+            let (left, right) = order.split_at_mut(mid);
+            let (left_buf, right_buf) = buf.split_at_mut(mid);
+            thread::scope(|s| {
+                s.spawn(|| self.merge_sort_positions(v, left, left_buf, cmp, half));
+                self.merge_sort_positions(v, right, right_buf, cmp, threads - half);
+            });
+        }
+        buf.copy_from_slice(order);
+        let (left, right) = buf.split_at(mid);
+        self.merge(v, left, right, order, cmp, threads);
+    }
+
+    /// stable merge of the sorted positions `left` and `right` into `out`, on
+    /// up to `threads` threads
+    fn merge<T: Sync, C: Compare<T> + Sync>(
+        &self,
+        v: &[T],
+        left: &[usize],
+        right: &[usize],
+        out: &mut [usize],
+        cmp: &C,
+        threads: usize,
+    ) {
+        let by_item = |a: &usize, b: &usize| cmp.compare(&v[*a], &v[*b]);
+        if threads < 2 || out.len() < self.min_len {
+            merge(left, right, out, &by_item);
+            return;
+        }
+        // items of `left` go before equal items of `right`
+        let (i, j) = if left.len() >= right.len() {
+            let i = left.len() / 2;
+            (i, right.partition_point(|r| by_item.lt(r, &left[i])))
+        } else {
+            let j = right.len() / 2;
+            (left.partition_point(|l| by_item.le(l, &right[j])), j)
+        };
+        let (out_lo, out_hi) = out.split_at_mut(i + j);
+        let half = threads / 2;
+        thread::scope(|s| {
+            s.spawn(|| self.merge(v, &left[..i], &right[..j], out_lo, cmp, half));
+            self.merge(v, &left[i..], &right[j..], out_hi, cmp, threads - half);
+        });
+    }
+}
+
+/// Sorts the slice on one thread per CPU, not stably.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::par_sort;
+///
+/// let mut v: Vec<u64> = (0..100_000).map(|i| i * 7919 % 100_003).collect();
+/// par_sort(&mut v);
+/// assert!(v.windows(2).all(|w| w[0] <= w[1]));
+/// ```
+pub fn par_sort<T: Ord + Send>(v: &mut [T]) {
+    ParSort::new().sort(v);
+}
+
+/// Sorts the slice with a comparator function on one thread per CPU, not
+/// stably.
+pub fn par_sort_by<T: Send, F: Fn(&T, &T) -> Ordering + Sync>(v: &mut [T], compare: F) {
+    ParSort::new().sort_by(v, compare);
+}
+
+/// Sorts the slice on one thread per CPU, stably.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::par_merge_sort_by;
+///
+/// let mut v: Vec<(u32, usize)> = (0..50_000).map(|i| (i as u32 % 10, i)).collect();
+/// par_merge_sort_by(&mut v, |a, b| a.0.cmp(&b.0));
+/// assert!(v.windows(2).all(|w| w[0] < w[1]));
+/// ```
+pub fn par_merge_sort<T: Ord + Sync>(v: &mut [T]) {
+    ParSort::new().merge_sort(v);
+}
+
+/// Sorts the slice with a comparator function on one thread per CPU,
+/// stably.
+pub fn par_merge_sort_by<T: Sync, F: Fn(&T, &T) -> Ordering + Sync>(v: &mut [T], compare: F) {
+    ParSort::new().merge_sort_by(v, compare);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pseudo_random;
+
+    #[test]
+    fn thread_counts() {
+        let n = 20_000;
+        let inputs = vec![
+            pseudo_random(n, 1 << 20),
+            pseudo_random(n, 3),
+            (0..n as u32).collect(),
+            (0..n as u32).rev().collect(),
+        ];
+        for items in inputs {
+            let mut expected = items.clone();
+            expected.sort_unstable();
+            for &threads in [1, 2, 3, 8].iter() {
+                for &min_len in [2, 100, n].iter() {
+                    let config = ParSort::new().threads(threads).min_len(min_len);
+                    let mut v = items.clone();
+                    config.sort(&mut v);
+                    assert_eq!(v, expected);
+                    let mut v = items.clone();
+                    config.merge_sort(&mut v);
+                    assert_eq!(v, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn par_merge_sort_is_stable() {
+        let keys = pseudo_random(30_000, 16);
+        let mut v: Vec<_> = keys.into_iter().zip(0..).collect();
+        let config = ParSort::new().threads(6).min_len(64);
+        config.merge_sort_by(&mut v, |a, b| b.0.cmp(&a.0));
+        assert!(v.windows(2).all(|w| w[0].0 > w[1].0 || w[0].1 < w[1].1));
+
+        let mut names: Vec<String> = v.iter().map(|&(_, i)| format!("{}", i)).collect();
+        let mut expected = names.clone();
+        expected.sort_by_key(|s| s.len());
+        par_merge_sort_by(&mut names, |a, b| a.len().cmp(&b.len()));
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn free_functions() {
+        let items = pseudo_random(50_000, 1000);
+        let mut expected = items.clone();
+        expected.sort_unstable();
+        let mut v = items.clone();
+        par_sort(&mut v);
+        assert_eq!(v, expected);
+        let mut v = items.clone();
+        par_merge_sort(&mut v);
+        assert_eq!(v, expected);
+        let mut v = items;
+        par_sort_by(&mut v, |a, b| b.cmp(a));
+        assert!(v.iter().rev().eq(expected.iter()));
+    }
+}
@@ -0,0 +1,453 @@
+//! Non-comparison sorts, for keys that are fixed-width numbers or byte
+//! strings
+//!
+//! [`sort`] is an LSD radix sort: one stable counting pass per byte of the
+//! key, least significant first, skipping bytes that every key shares. It
+//! sorts anything implementing [`RadixKey`]: unsigned and signed integers,
+//! whose sign bit is flipped, and floats, whose negative values have every
+//! bit flipped, so that their bits order like the numbers.
+//!
+//! [`sort_bytes`] is an MSD radix sort of byte strings, in-place: it spreads
+//! the strings into buckets by their byte at some depth, strings ending
+//! there first, then sorts each bucket one byte deeper.
+//!
+//! [`counting_sort`] counts every key between the least and the greatest,
+//! for keys spanning a small range, and radix sorts wider ones.
+//!
+//! # Examples
+//!
+//! ```
+//! use tsinghua_ds::radix;
+//!
+//! let mut v = [3.5, -0.5, 10.0, -7.25, 0.0];
+//! radix::sort(&mut v);
+//! assert_eq!(v, [-7.25, -0.5, 0.0, 3.5, 10.0]);
+//!
+//! let mut words = ["pear", "fig", "apple", "figs"];
+//! radix::sort_bytes(&mut words);
+//! assert_eq!(words, ["apple", "fig", "figs", "pear"]);
+//! ```
+
+use super::{insertion_sort, permute, INSERTION_THRESHOLD};
+
+/// `counting_sort` counts ranges below `COUNTING_RANGE_PER_KEY` per key plus
+/// `COUNTING_MIN_RANGE`
+const COUNTING_RANGE_PER_KEY: u64 = 4;
+const COUNTING_MIN_RANGE: u64 = 256;
+
+/// A key with a fixed width, whose order is that of an unsigned integer made
+/// of its bits
+pub trait RadixKey: Copy {
+    /// width of the key in bytes, at most 8
+    const BYTES: usize;
+    /// the key as an unsigned integer, ordered like `self`
+    fn radix_key(self) -> u64;
+}
+
+macro_rules! unsigned_key {
+    ($($t:ty),*) => {$(
+        impl RadixKey for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+            fn radix_key(self) -> u64 {
+                self as u64
+            }
+        }
+    )*};
+}
+
+macro_rules! signed_key {
+    ($($t:ty => $u:ty),*) => {$(
+        impl RadixKey for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+            /// flip the sign bit, so that negative numbers come first
+            fn radix_key(self) -> u64 {
+                (self as $u ^ (1 << (<$u>::BITS - 1))) as u64
+            }
+        }
+    )*};
+}
+
+macro_rules! float_key {
+    ($($t:ty => $u:ty),*) => {$(
+        impl RadixKey for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+            /// flip every bit of negative numbers, the sign bit of others,
+            /// the order of `total_cmp`
+            fn radix_key(self) -> u64 {
+                let bits = self.to_bits();
+                let sign = 1 << (<$u>::BITS - 1);
+                (if bits & sign == 0 { bits ^ sign } else { !bits }) as u64
+            }
+        }
+    )*};
+}
+
+unsigned_key!(u8, u16, u32, u64, usize);
+signed_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+float_key!(f32 => u32, f64 => u64);
+
+/// Sorts the slice with an LSD radix sort, in *O*(*n* `K::BYTES`) with
+/// *O*(*n*) extra space.
+///
+/// Floats are ordered like `total_cmp`, negative `NaN` first and positive
+/// `NaN` last, `-0.0` before `0.0`.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::radix;
+///
+/// let mut v = [1_000_000_007u64, 42, 7, 1 << 40];
+/// radix::sort(&mut v);
+/// assert_eq!(v, [7, 42, 1_000_000_007, 1 << 40]);
+///
+/// let mut v = [3i32, -1, i32::MIN, 0];
+/// radix::sort(&mut v);
+/// assert_eq!(v, [i32::MIN, -1, 0, 3]);
+/// ```
+pub fn sort<K: RadixKey>(v: &mut [K]) {
+    lsd(v, K::BYTES, |k| k.radix_key());
+}
+
+/// Sorts the slice with an LSD radix sort on a key extraction function,
+/// stably, in *O*(*n* `K::BYTES`).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::radix;
+///
+/// let mut events = [("b", 1700000002u32), ("a", 1700000001), ("c", 1700000001)];
+/// radix::sort_by_key(&mut events, |e| e.1);
+/// assert_eq!(events, [("a", 1700000001), ("c", 1700000001), ("b", 1700000002)]);
+/// ```
+pub fn sort_by_key<T, K: RadixKey, F: Fn(&T) -> K>(v: &mut [T], f: F) {
+    let mut keyed: Vec<(u64, usize)> = v
+        .iter()
+        .enumerate()
+        .map(|(pos, item)| (f(item).radix_key(), pos))
+        .collect();
+    lsd(&mut keyed, K::BYTES, |&(key, _)| key);
+    permute(v, keyed.into_iter().map(|(_, pos)| pos).collect());
+}
+
+/// stable LSD radix sort of `v` by the low `bytes` bytes of `key`
+fn lsd<E: Copy>(v: &mut [E], bytes: usize, key: impl Fn(&E) -> u64) {
+    if v.len() < 2 {
+        return;
+    }
+    let mut counts = vec![[0; 256]; bytes];
+    for item in v.iter() {
+        let key = key(item);
+        for (byte, count) in counts.iter_mut().enumerate() {
+            count[(key >> (8 * byte)) as usize & 0xff] += 1;
+        }
+    }
+    let mut buf = v.to_vec();
+    let mut in_buf = false;
+    {
+        let (mut src, mut dst) = (&mut *v, &mut buf[..]);
+        for (byte, count) in counts.iter().enumerate() {
+            // every key has the same byte here, the pass would change nothing
+            if count.contains(&src.len()) {
+                continue;
+            }
+            let mut offsets = [0; 256];
+            let mut sum = 0;
+            for (offset, &count) in offsets.iter_mut().zip(count.iter()) {
+                *offset = sum;
+                sum += count;
+            }
+            for item in src.iter() {
+                let digit = (key(item) >> (8 * byte)) as usize & 0xff;
+                dst[offsets[digit]] = *item;
+                offsets[digit] += 1;
+            }
+            std::mem::swap(&mut src, &mut dst);
+            in_buf = !in_buf;
+        }
+    }
+    if in_buf {
+        v.copy_from_slice(&buf);
+    }
+}
+
+/// Sorts a slice of byte strings, such as `&str` or `Vec<u8>`, with an
+/// in-place MSD radix sort, not stably, in *O*(*n* + total bytes examined).
+///
+/// Strings are ordered by their bytes, the order of `str` and `[u8]`.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::radix;
+///
+/// let mut keys = vec![b"GET /b".to_vec(), b"GET /a".to_vec(), b"DELETE".to_vec()];
+/// radix::sort_bytes(&mut keys);
+/// assert_eq!(keys, [&b"DELETE"[..], b"GET /a", b"GET /b"]);
+/// ```
+pub fn sort_bytes<S: AsRef<[u8]>>(v: &mut [S]) {
+    msd(v, 0);
+}
+
+/// MSD radix sort of byte strings sharing their first `depth` bytes
+///
+/// Recurses into every bucket but the largest, and loops on that one, so
+/// that recursion is at most log(*n*) deep.
+fn msd<S: AsRef<[u8]>>(mut v: &mut [S], mut depth: usize) {
+    loop {
+        if v.len() <= INSERTION_THRESHOLD {
+            let suffix = |a: &S, b: &S| a.as_ref()[depth..].cmp(&b.as_ref()[depth..]);
+            insertion_sort(v, &suffix);
+            return;
+        }
+        // bucket 0 holds the strings ending at `depth`, `b + 1` those whose
+        // byte there is `b`
+        let digit = |s: &S| s.as_ref().get(depth).map_or(0, |&b| b as usize + 1);
+        let mut ends = [0; 257];
+        for s in v.iter() {
+            ends[digit(s)] += 1;
+        }
+        let mut heads = [0; 257];
+        let mut sum = 0;
+        for (head, end) in heads.iter_mut().zip(ends.iter_mut()) {
+            *head = sum;
+            sum += *end;
+            *end = sum;
+        }
+        let starts = heads;
+        // American flag sort, swapping each string into its bucket
+        for bucket in 0..257 {
+            while heads[bucket] < ends[bucket] {
+                let d = digit(&v[heads[bucket]]);
+                if d != bucket {
+                    v.swap(heads[bucket], heads[d]);
+                }
+                heads[d] += 1;
+            }
+        }
+        let largest = (1..257)
+            .max_by_key(|&bucket| ends[bucket] - starts[bucket])
+            .unwrap_or(1);
+        for bucket in 1..257 {
+            if bucket != largest && ends[bucket] - starts[bucket] > 1 {
+                msd(&mut v[starts[bucket]..ends[bucket]], depth + 1);
+            }
+        }
+        v = &mut v[starts[largest]..ends[largest]];
+        depth += 1;
+    }
+}
+
+/// Sorts the slice with a counting sort over the range of its keys, in
+/// *O*(*n* + *r*) time and space where *r* is the range between the least
+/// and the greatest key.
+///
+/// Keys spanning a range of `4 * n + 256` or more, which counting would
+/// take more memory than the slice for, are sorted by [`sort`] instead.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::radix;
+///
+/// let mut ages = [34u8, 21, 34, 90, 5];
+/// radix::counting_sort(&mut ages);
+/// assert_eq!(ages, [5, 21, 34, 34, 90]);
+/// ```
+pub fn counting_sort<K: RadixKey>(v: &mut [K]) {
+    let (min, max) = match key_range(v.iter().map(|k| k.radix_key())) {
+        Some(range) => range,
+        None => return,
+    };
+    if max - min >= COUNTING_RANGE_PER_KEY * v.len() as u64 + COUNTING_MIN_RANGE {
+        sort(v);
+        return;
+    }
+    let range = (max - min) as usize + 1;
+    let mut offsets = vec![0; range + 1];
+    for k in v.iter() {
+        offsets[(k.radix_key() - min) as usize + 1] += 1;
+    }
+    for digit in 1..=range {
+        offsets[digit] += offsets[digit - 1];
+    }
+    let buf = v.to_vec();
+    for k in buf {
+        let digit = (k.radix_key() - min) as usize;
+        v[offsets[digit]] = k;
+        offsets[digit] += 1;
+    }
+}
+
+/// Sorts the slice with a counting sort on a key extraction function
+/// returning small indices, stably, in *O*(*n* + *m*) where *m* is the
+/// greatest key.
+///
+/// Keys of `4 * n + 256` or more are sorted by [`sort_by_key`] instead, which
+/// calls `f` again for every element.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::radix;
+///
+/// let mut grades = [("ann", 3), ("bob", 1), ("cid", 3), ("dee", 0)];
+/// radix::counting_sort_by_key(&mut grades, |g| g.1);
+/// assert_eq!(grades, [("dee", 0), ("bob", 1), ("ann", 3), ("cid", 3)]);
+/// ```
+pub fn counting_sort_by_key<T, F: Fn(&T) -> usize>(v: &mut [T], f: F) {
+    let keys: Vec<usize> = v.iter().map(&f).collect();
+    let max = match keys.iter().max() {
+        Some(&max) => max,
+        None => return,
+    };
+    if max as u64 >= COUNTING_RANGE_PER_KEY * v.len() as u64 + COUNTING_MIN_RANGE {
+        sort_by_key(v, f);
+        return;
+    }
+    let mut offsets = vec![0; max + 2];
+    for &key in keys.iter() {
+        offsets[key + 1] += 1;
+    }
+    for key in 1..offsets.len() {
+        offsets[key] += offsets[key - 1];
+    }
+    let mut order = vec![0; v.len()];
+    for (pos, &key) in keys.iter().enumerate() {
+        order[offsets[key]] = pos;
+        offsets[key] += 1;
+    }
+    permute(v, order);
+}
+
+/// least and greatest of `keys`
+fn key_range(keys: impl Iterator<Item = u64>) -> Option<(u64, u64)> {
+    keys.fold(None, |range, key| match range {
+        None => Some((key, key)),
+        Some((min, max)) => Some((min.min(key), max.max(key))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pseudo_random_u64;
+
+    fn check<K: RadixKey + Ord + std::fmt::Debug>(items: Vec<K>) {
+        let mut expected = items.clone();
+        expected.sort_unstable();
+        let mut v = items;
+        sort(&mut v);
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn integers() {
+        let bits = pseudo_random_u64(3000);
+        check(bits.clone());
+        check(bits.iter().map(|&b| b as u32).collect());
+        check(bits.iter().map(|&b| b as u16).collect());
+        check(bits.iter().map(|&b| b as u8).collect());
+        check(bits.iter().map(|&b| b as usize).collect());
+        check(bits.iter().map(|&b| b as i64).collect());
+        check(bits.iter().map(|&b| b as i32).collect());
+        check(bits.iter().map(|&b| b as i16).collect());
+        check(bits.iter().map(|&b| b as i8).collect());
+        check(bits.iter().map(|&b| (b >> 40) as i64 - (1 << 23)).collect());
+        check(vec![i64::MAX, i64::MIN, 0, -1, 1, i64::MIN + 1]);
+        check(vec![5u32; 100]);
+        check(Vec::<u32>::new());
+    }
+
+    #[test]
+    fn floats() {
+        let bits = pseudo_random_u64(3000);
+        let mut v: Vec<f64> = bits.iter().map(|&b| f64::from_bits(b)).collect();
+        v.extend_from_slice(&[0.0, -0.0, f64::INFINITY, f64::NEG_INFINITY, -f64::NAN]);
+        let mut expected = v.clone();
+        expected.sort_by(f64::total_cmp);
+        sort(&mut v);
+        assert!(v
+            .iter()
+            .zip(expected.iter())
+            .all(|(a, b)| a.to_bits() == b.to_bits()));
+
+        let mut v: Vec<f32> = bits.iter().map(|&b| (b as i32) as f32 / 7.0).collect();
+        let mut expected = v.clone();
+        expected.sort_by(f32::total_cmp);
+        sort(&mut v);
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn by_key_is_stable() {
+        let keys = pseudo_random_u64(5000);
+        let mut v: Vec<(i16, usize)> = keys.iter().map(|&k| k as i16 % 50).zip(0..).collect();
+        sort_by_key(&mut v, |&(key, _)| key);
+        assert!(v.windows(2).all(|w| w[0] < w[1]));
+
+        let mut v: Vec<(usize, usize)> = keys.iter().map(|&k| (k % 13) as usize).zip(0..).collect();
+        counting_sort_by_key(&mut v, |&(key, _)| key);
+        assert!(v.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn counting() {
+        let keys = pseudo_random_u64(5000);
+        let mut v: Vec<i32> = keys.iter().map(|&k| (k % 1000) as i32 - 500).collect();
+        let mut expected = v.clone();
+        expected.sort_unstable();
+        counting_sort(&mut v);
+        assert_eq!(v, expected);
+
+        let mut v = [u64::MAX - 3, u64::MAX, u64::MAX - 3];
+        counting_sort(&mut v);
+        assert_eq!(v, [u64::MAX - 3, u64::MAX - 3, u64::MAX]);
+        counting_sort::<u8>(&mut []);
+    }
+
+    #[test]
+    fn counting_wide_range() {
+        // 1 << 40 counters would not fit in memory
+        let mut v = [1u64 << 40, 0, 5, 1 << 40, u64::MAX];
+        counting_sort(&mut v);
+        assert_eq!(v, [0, 5, 1 << 40, 1 << 40, u64::MAX]);
+
+        let mut v: Vec<i64> = (0..100).map(|i| (i % 7) * 1_000_003 - 3).collect();
+        let mut expected = v.clone();
+        expected.sort_unstable();
+        counting_sort(&mut v);
+        assert_eq!(v, expected);
+
+        let mut v = [("a", 1 << 40), ("b", 0), ("c", usize::MAX), ("d", 1 << 40)];
+        counting_sort_by_key(&mut v, |e| e.1);
+        assert_eq!(
+            v,
+            [("b", 0), ("a", 1 << 40), ("d", 1 << 40), ("c", usize::MAX)]
+        );
+    }
+
+    #[test]
+    fn byte_strings() {
+        let keys = pseudo_random_u64(5000);
+        let mut v: Vec<String> = keys
+            .iter()
+            .map(|&k| format!("{:x}", k >> (k % 64)).replace('f', "\u{e9}"))
+            .collect();
+        v.extend(["", "a", "", "ab", "a"].iter().map(|s| s.to_string()));
+        let mut expected = v.clone();
+        expected.sort_unstable();
+        sort_bytes(&mut v);
+        assert_eq!(v, expected);
+
+        // a long chain of shared prefixes must not recurse deeply
+        let mut v: Vec<Vec<u8>> = (0..3000).rev().map(|n| vec![b'a'; n]).collect();
+        sort_bytes(&mut v);
+        assert!(v.iter().enumerate().all(|(n, s)| s.len() == n));
+
+        let mut v: Vec<&[u8]> = vec![b"\xff\x00", b"\xff", b"\x00\xff", b"\x00"];
+        sort_bytes(&mut v);
+        assert_eq!(v, [&b"\x00"[..], b"\x00\xff", b"\xff", b"\xff\x00"]);
+    }
+}
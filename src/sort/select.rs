@@ -0,0 +1,285 @@
+//! Selection, finding the item at some rank without sorting
+//!
+//! [`select_nth`] is an introselect: quickselect pivoting on the median of
+//! three, which narrows down to the side holding the wanted rank. After
+//! more bad pivots than twice the log of the length, a bad one keeping more
+//! than 3/4 of the items, it pivots on the median of the medians of groups
+//! of five instead, which leaves at most 7/10 of the items on either side,
+//! so that it runs in *O*(*n*) even on adversarial input.
+
+use super::{insertion_sort, median_of_three, partition_around_first, INSERTION_THRESHOLD};
+use crate::compare::Compare;
+use crate::heap::BinaryHeap;
+use std::cmp::Ordering;
+
+/// Reorders the slice so that the item at `k` is the one that would be there
+/// once sorted, lesser or equal items before it and greater or equal ones
+/// after, and returns it, in *O*(*n*).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::select_nth;
+///
+/// let mut v = [9, 1, 8, 2, 7, 3];
+/// assert_eq!(*select_nth(&mut v, 1), 2);
+/// assert!(v[..1].iter().all(|&x| x <= 2) && v[2..].iter().all(|&x| x >= 2));
+/// ```
+///
+/// # Panics
+///
+/// Panics if `k` is not less than the length of the slice.
+pub fn select_nth<T: Ord>(v: &mut [T], k: usize) -> &mut T {
+    select_nth_by(v, k, T::cmp)
+}
+
+/// Reorders the slice so that the item at `k` is the one that would be there
+/// once sorted with a comparator function, and returns it, in *O*(*n*).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::select_nth_by;
+///
+/// let mut v = ["ccc", "a", "dddd", "bb"];
+/// assert_eq!(*select_nth_by(&mut v, 0, |a, b| b.len().cmp(&a.len())), "dddd");
+/// ```
+///
+/// # Panics
+///
+/// Panics if `k` is not less than the length of the slice.
+pub fn select_nth_by<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], k: usize, compare: F) -> &mut T {
+    assert!(k < v.len(), "rank {} out of a slice of {}", k, v.len());
+    let limit = 2 * (usize::BITS - v.len().leading_zeros());
+    select(v, k, &compare, limit);
+    &mut v[k]
+}
+
+/// move the item of rank `k` in `v` to `k`, pivoting on the median of
+/// three until `limit` pivots were bad, then on the median of medians
+fn select<T, C: Compare<T>>(mut v: &mut [T], mut k: usize, cmp: &C, mut limit: u32) {
+    loop {
+        if v.len() <= INSERTION_THRESHOLD {
+            insertion_sort(v, cmp);
+            return;
+        }
+        if limit == 0 {
+            median_of_medians(v, cmp);
+        } else {
+            median_of_three(v, cmp);
+        }
+        let len = v.len();
+        let mid = partition_around_first(v, cmp);
+        match k.cmp(&mid) {
+            Ordering::Equal => return,
+            Ordering::Less => v = &mut v[..mid],
+            Ordering::Greater => {
+                v = &mut v[mid + 1..];
+                k -= mid + 1;
+            }
+        }
+        if v.len() * 4 > len * 3 {
+            limit = limit.saturating_sub(1);
+        }
+    }
+}
+
+/// move the median of the medians of groups of five to the front of `v`,
+/// longer than `INSERTION_THRESHOLD`
+fn median_of_medians<T, C: Compare<T>>(v: &mut [T], cmp: &C) {
+    let groups = v.len() / 5;
+    for group in 0..groups {
+        insertion_sort(&mut v[5 * group..5 * group + 5], cmp);
+        // `group` lies in a group already done with
+        v.swap(group, 5 * group + 2);
+    }
+    select(&mut v[..groups], groups / 2, cmp, 0);
+    v.swap(0, groups / 2);
+}
+
+/// Returns the median of the slice, the lower of the two middle items for
+/// an even length, or `None` if it is empty, reordering it like
+/// [`select_nth`].
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::median;
+///
+/// assert_eq!(median(&mut [5, 3, 1, 4, 2]), Some(&3));
+/// assert_eq!(median(&mut [4, 1, 3, 2]), Some(&2));
+/// assert_eq!(median::<u8>(&mut []), None);
+/// ```
+pub fn median<T: Ord>(v: &mut [T]) -> Option<&T> {
+    if v.is_empty() {
+        return None;
+    }
+    let k = (v.len() - 1) / 2;
+    Some(select_nth(v, k))
+}
+
+/// Returns the item making up more than half of the slice, if any, in
+/// *O*(*n*) with a Boyer-Moore majority vote checked by a second pass.
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::majority;
+///
+/// assert_eq!(majority(&[2, 1, 2, 3, 2]), Some(2));
+/// assert_eq!(majority(&[2, 1, 2, 3]), None);
+/// ```
+pub fn majority<T: Eq + Clone>(v: &[T]) -> Option<T> {
+    let mut candidate = None;
+    let mut votes = 0;
+    for item in v.iter() {
+        if votes == 0 {
+            candidate = Some(item);
+            votes = 1;
+        } else if candidate == Some(item) {
+            votes += 1;
+        } else {
+            votes -= 1;
+        }
+    }
+    let candidate = candidate?;
+    let count = v.iter().filter(|&item| item == candidate).count();
+    if count > v.len() / 2 {
+        Some(candidate.clone())
+    } else {
+        None
+    }
+}
+
+/// Returns the `k` greatest items, greatest first, keeping at most `k + 1`
+/// of them at a time in a min-heap, in *O*(*n* log(*k*)).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::top_k;
+///
+/// assert_eq!(top_k(vec![4, 9, 1, 7, 3], 2), [9, 7]);
+/// assert_eq!(top_k(0..3, 5), [2, 1, 0]);
+/// ```
+pub fn top_k<T: Ord, I: IntoIterator<Item = T>>(items: I, k: usize) -> Vec<T> {
+    keep_k(items, k, BinaryHeap::new_min())
+}
+
+/// Returns the `k` least items, least first, keeping at most `k + 1` of
+/// them at a time in a max-heap, in *O*(*n* log(*k*)).
+///
+/// # Examples
+///
+/// ```
+/// use tsinghua_ds::bottom_k;
+///
+/// assert_eq!(bottom_k(vec![4, 9, 1, 7, 3], 2), [1, 3]);
+/// ```
+pub fn bottom_k<T: Ord, I: IntoIterator<Item = T>>(items: I, k: usize) -> Vec<T> {
+    keep_k(items, k, BinaryHeap::new())
+}
+
+/// the `k` items least by the heap's comparator, which are popped last,
+/// in the order they pop in reverse
+fn keep_k<T, I: IntoIterator<Item = T>, C: Compare<T>>(
+    items: I,
+    k: usize,
+    mut heap: BinaryHeap<T, C>,
+) -> Vec<T> {
+    if k == 0 {
+        return Vec::new();
+    }
+    for item in items {
+        heap.push(item);
+        if heap.len() > k {
+            heap.pop();
+        }
+    }
+    let mut kept: Vec<T> = std::iter::from_fn(|| heap.pop()).collect();
+    kept.reverse();
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pseudo_random;
+
+    fn check_select(items: &[u32], k: usize, limit: u32) {
+        let mut sorted = items.to_vec();
+        sorted.sort_unstable();
+        let mut v = items.to_vec();
+        select(&mut v, k, &u32::cmp, limit);
+        assert_eq!(v[k], sorted[k]);
+        assert!(v[..k].iter().all(|x| *x <= v[k]));
+        assert!(v[k + 1..].iter().all(|x| *x >= v[k]));
+    }
+
+    #[test]
+    fn selection() {
+        let n = 1000;
+        let inputs = [
+            pseudo_random(n, 1 << 20),
+            pseudo_random(n, 3),
+            (0..n as u32).collect(),
+            (0..n as u32).rev().collect(),
+            vec![4; n],
+        ];
+        for items in inputs.iter() {
+            for &k in [0, 1, 16, 17, 499, 500, 998, 999].iter() {
+                check_select(items, k, 20);
+                // median of medians only
+                check_select(items, k, 0);
+            }
+        }
+        for len in 1..40 {
+            let items = pseudo_random(len, 10);
+            for k in 0..len {
+                check_select(&items, k, 0);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn select_out_of_range() {
+        select_nth(&mut [1, 2, 3], 3);
+    }
+
+    #[test]
+    fn medians_and_majorities() {
+        let mut v = pseudo_random(1001, 1 << 20);
+        let mut sorted = v.clone();
+        sorted.sort_unstable();
+        assert_eq!(median(&mut v), Some(&sorted[500]));
+        v.pop();
+        sorted = v.clone();
+        sorted.sort_unstable();
+        assert_eq!(median(&mut v), Some(&sorted[499]));
+
+        assert_eq!(majority::<u8>(&[]), None);
+        assert_eq!(majority(&["a"]), Some("a"));
+        assert_eq!(majority(&[1, 1, 2, 2]), None);
+        // the vote ends on 3, which is no majority
+        assert_eq!(majority(&[1, 2, 1, 2, 3]), None);
+        let mut v = vec![7; 501];
+        v.extend(pseudo_random(500, 5));
+        assert_eq!(majority(&v), Some(7));
+        v.push(7 + 1);
+        assert_eq!(majority(&v), None);
+    }
+
+    #[test]
+    fn top_and_bottom() {
+        let items = pseudo_random(2000, 500);
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        for &k in [0, 1, 10, 1999, 2000, 5000].iter() {
+            let top = top_k(items.iter().copied(), k);
+            assert!(top.iter().eq(sorted.iter().rev().take(k)));
+            let bottom = bottom_k(items.iter().copied(), k);
+            assert!(bottom.iter().eq(sorted.iter().take(k)));
+        }
+    }
+}
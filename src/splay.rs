@@ -1,128 +1,230 @@
 //! SplayTree
 
-use crate::{TreeNode, BST};
+use crate::invariant::check_order;
+use crate::{
+    InvariantError, Keys, TreeIntoIter, TreeIter, TreeIterMut, TreeNode, TreeRange, Values, BST,
+};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::mem::replace;
+use std::ops::RangeBounds;
 
 /// Splay Tree
 #[derive(Debug, PartialEq, Eq)]
-pub struct SplayTree(Option<Box<TreeNode>>);
+pub struct SplayTree<K, V>(Option<Box<TreeNode<K, V>>>);
 
-impl SplayTree {
+impl<K: Ord, V> SplayTree<K, V> {
     /// Splay tree
-    pub fn new(root_key: i32, root_val: i32) -> Self {
+    pub fn new(root_key: K, root_val: V) -> Self {
         Self(Some(Box::new(TreeNode::new(root_key, root_val))))
     }
 
-    fn searchin(&mut self, key: i32) {
-        let mut ptr = self.0.as_mut();
-        let mut prev_ptrs = Vec::<*mut TreeNode>::new();
+    /// Gets an iterator over the entries of the tree, sorted by key
+    pub fn iter(&self) -> TreeIter<'_, K, V> {
+        TreeIter::new(self.0.as_deref())
+    }
+
+    /// Gets a mutable iterator over the entries of the tree, sorted by key
+    pub fn iter_mut(&mut self) -> TreeIterMut<'_, K, V> {
+        TreeIterMut::new(self.0.as_deref_mut())
+    }
+
+    /// Gets an iterator over the keys of the tree, in sorted order
+    pub fn keys(&self) -> Keys<TreeIter<'_, K, V>> {
+        Keys(self.iter())
+    }
+
+    /// Gets an iterator over the values of the tree, in order by key
+    pub fn values(&self) -> Values<TreeIter<'_, K, V>> {
+        Values(self.iter())
+    }
+
+    /// Constructs a double-ended iterator over a sub-range of entries in the tree
+    pub fn range<T, R>(&self, range: R) -> TreeRange<'_, K, V>
+    where
+        K: Borrow<T>,
+        T: Ord + ?Sized,
+        R: RangeBounds<T>,
+    {
+        TreeRange::new(self.0.as_deref(), range)
+    }
 
-        while let Some(node) = ptr {
-            let node_key = node.key;
-            prev_ptrs.push(&mut **node);
-            if key > node_key {
-                ptr = node.right.as_mut();
-            } else if key < node_key {
-                ptr = node.left.as_mut();
+    /// Checks that keys strictly increase in order
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        check_order(self.0.as_deref())
+    }
+
+    fn searchin(&mut self, key: &K) {
+        splay_by(&mut self.0, |node_key| key.cmp(node_key));
+    }
+
+    /// splay the nearest node around a split point, `is_left` tells whether a
+    /// key lies on the left of it, `lower` picks the side to return
+    fn splay_neighbor(&mut self, is_left: impl Fn(&K) -> bool, lower: bool) -> Option<(&K, &V)> {
+        splay_by(&mut self.0, |node_key| {
+            if is_left(node_key) {
+                Ordering::Greater
             } else {
-                break;
+                Ordering::Less
             }
-        }
+        });
 
-        if let Some(node_ptr) = prev_ptrs.pop() {
-            let node = unsafe { &mut *node_ptr };
-            node.splay(prev_ptrs);
+        // root is now either the last key on the left or the first on the right
+        let root = self.0.as_mut()?;
+        if lower && !is_left(&root.key) {
+            splay_by(&mut root.left, |_| Ordering::Greater);
+            root.left.as_ref()?;
+            root.rotate_right();
+        } else if !lower && is_left(&root.key) {
+            splay_by(&mut root.right, |_| Ordering::Less);
+            root.right.as_ref()?;
+            root.rotate_left();
         }
+        Some(root.entry())
     }
 }
 
-impl BST for SplayTree {
+/// walk down by `cmp` and splay the last node touched to the top of `link`
+fn splay_by<K: Ord, V>(link: &mut Option<Box<TreeNode<K, V>>>, cmp: impl Fn(&K) -> Ordering) {
+    TreeNode::splay(link, cmp);
+}
+
+impl<K: Ord, V> BST<K, V> for SplayTree<K, V> {
     /// search val by key
-    fn search(&mut self, key: i32) -> Option<i32> {
+    fn search(&mut self, key: &K) -> Option<&V> {
         self.searchin(key);
 
         if let Some(node) = self.0.as_ref() {
             // found proper key
-            if node.key == key {
-                return Some(node.val);
+            if node.key == *key {
+                return Some(&node.val);
             }
         }
         // Tree is empty or found nearest key
         None
     }
     /// 3-4 insert method rotated
-    fn insert(&mut self, key: i32, val: i32) {
-        self.searchin(key);
+    fn insert(&mut self, key: K, val: V) {
+        self.searchin(&key);
         if let Some(node) = self.0.as_mut() {
-            if node.key == key {
-                node.val = val;
-            } else if node.key < key {
-                let old_key = replace(&mut node.key, key);
-                let old_val = replace(&mut node.val, val);
-
-                let mut old_node = Box::new(TreeNode::new(old_key, old_val));
-                old_node.left = node.left.take();
-                old_node.update_height();
-                node.left = Some(old_node);
-                node.update_height();
-            } else {
-                let old_key = replace(&mut node.key, key);
-                let old_val = replace(&mut node.val, val);
-
-                let mut old_node = Box::new(TreeNode::new(old_key, old_val));
-                old_node.right = node.right.take();
-                old_node.update_height();
-                node.right = Some(old_node);
-                node.update_height();
+            match node.key.cmp(&key) {
+                Ordering::Equal => {
+                    node.val = val;
+                }
+                Ordering::Less => {
+                    let old_key = replace(&mut node.key, key);
+                    let old_val = replace(&mut node.val, val);
+
+                    let mut old_node = Box::new(TreeNode::new(old_key, old_val));
+                    old_node.left = node.left.take();
+                    old_node.update_height();
+                    node.left = Some(old_node);
+                    node.update_height();
+                }
+                Ordering::Greater => {
+                    let old_key = replace(&mut node.key, key);
+                    let old_val = replace(&mut node.val, val);
+
+                    let mut old_node = Box::new(TreeNode::new(old_key, old_val));
+                    old_node.right = node.right.take();
+                    old_node.update_height();
+                    node.right = Some(old_node);
+                    node.update_height();
+                }
             }
+        } else {
+            self.0 = Some(Box::new(TreeNode::new(key, val)));
         }
     }
     /// remove val
-    fn remove(&mut self, key: i32) -> Option<i32> {
+    fn remove(&mut self, key: &K) -> Option<V> {
         self.searchin(key);
 
-        if let Some(node) = self.0.as_mut() {
-            if node.key == key {
-                if node.right.is_none() {
-                    let left_tree = node.left.take();
-                    let inner_val = node.val;
-                    self.0 = left_tree;
-                    return Some(inner_val);
-                }
+        match self.0.as_ref() {
+            Some(node) if node.key == *key => {}
+            _ => return None,
+        }
 
-                // if right.left.is_none(), min right child is right node, so
-                // take right
-                if node.right.as_ref().unwrap().left.is_none() {
-                    let mut right_node = node.right.take().unwrap();
-                    let inner_val = replace(&mut node.val, right_node.val);
-                    let _ = replace(&mut node.key, right_node.key);
-                    let _ = replace(&mut node.right, right_node.right.take());
-                    node.update_height();
-                    return Some(inner_val);
-                }
+        let mut root = self.0.take().unwrap();
+        let mut right_tree = root.right.take();
+        // splay the minimum of right subtree, it has no left child then
+        splay_by(&mut right_tree, |_| Ordering::Less);
+        self.0 = match right_tree {
+            Some(mut right_node) => {
+                right_node.left = root.left.take();
+                right_node.update_height();
+                Some(right_node)
+            }
+            None => root.left.take(),
+        };
+        Some(root.val)
+    }
+    /// splay the minimum key to root
+    fn first(&mut self) -> Option<(&K, &V)> {
+        splay_by(&mut self.0, |_| Ordering::Less);
+        self.0.as_ref().map(|root| root.entry())
+    }
+    /// splay the maximum key to root
+    fn last(&mut self) -> Option<(&K, &V)> {
+        splay_by(&mut self.0, |_| Ordering::Greater);
+        self.0.as_ref().map(|root| root.entry())
+    }
+    /// splay the floor of `key` to root
+    fn floor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.splay_neighbor(|k| k <= key, true)
+    }
+    /// splay the ceiling of `key` to root
+    fn ceiling(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.splay_neighbor(|k| k < key, false)
+    }
+    /// splay the predecessor of `key` to root
+    fn predecessor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.splay_neighbor(|k| k < key, true)
+    }
+    /// splay the successor of `key` to root
+    fn successor(&mut self, key: &K) -> Option<(&K, &V)> {
+        self.splay_neighbor(|k| k <= key, false)
+    }
+    /// splay the minimum key to root and remove it
+    fn pop_first(&mut self) -> Option<(K, V)> {
+        splay_by(&mut self.0, |_| Ordering::Less);
+        let mut root = self.0.take()?;
+        self.0 = root.right.take();
+        Some(root.into_entry())
+    }
+    /// splay the maximum key to root and remove it
+    fn pop_last(&mut self) -> Option<(K, V)> {
+        splay_by(&mut self.0, |_| Ordering::Greater);
+        let mut root = self.0.take()?;
+        self.0 = root.left.take();
+        Some(root.into_entry())
+    }
+}
 
-                let mut right_tree = node.right.as_mut();
-                let mut parent_left_ptr: *mut TreeNode = 0 as *mut TreeNode;
-                while let Some(next_node) = right_tree {
-                    if next_node.left.is_some() {
-                        parent_left_ptr = &mut **next_node;
-                    }
+impl<K, V> IntoIterator for SplayTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = TreeIntoIter<K, V>;
 
-                    right_tree = next_node.left.as_mut();
-                }
+    fn into_iter(self) -> Self::IntoIter {
+        TreeIntoIter::new(self.0)
+    }
+}
 
-                let parent_left_node = unsafe { &mut *parent_left_ptr };
-                let mut leftmost_node = parent_left_node.left.take().unwrap();
+impl<'a, K: Ord, V> IntoIterator for &'a SplayTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = TreeIter<'a, K, V>;
 
-                // replace key, value
-                let inner_val = replace(&mut node.val, leftmost_node.val);
-                let _ = replace(&mut node.key, leftmost_node.key);
-                let _ = replace(&mut parent_left_node.left, leftmost_node.right.take());
-                node.update_height();
-                return Some(inner_val);
-            }
-        }
-        None
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut SplayTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = TreeIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
@@ -148,33 +250,81 @@ mod tests {
         bst.insert(19, 2849);
         bst.insert(12, 28499);
 
-        assert_eq!(bst.search(4), Some(16));
-        assert_eq!(bst.search(19), Some(2849));
+        assert_eq!(bst.search(&4), Some(&16));
+        assert_eq!(bst.search(&19), Some(&2849));
         bst.insert(19, -12849);
-        assert_eq!(bst.search(19), Some(-12849));
-        assert_eq!(bst.search(13), Some(2344));
-        assert_eq!(bst.search(4), Some(16));
-        assert_eq!(bst.search(-1), None);
-        assert_eq!(bst.search(0), None);
-        assert_eq!(bst.remove(2), Some(4));
-        assert_eq!(bst.search(2), None);
-        assert_eq!(bst.remove(2), None);
-        assert_eq!(bst.remove(11), Some(234));
-        assert_eq!(bst.remove(11), None);
-        assert_eq!(bst.search(11), None);
-        assert_eq!(bst.remove(20), Some(12993));
-        assert_eq!(bst.remove(20), None);
-        assert_eq!(bst.search(20), None);
-        assert_eq!(bst.remove(8), Some(256));
-        assert_eq!(bst.remove(8), None);
-        assert_eq!(bst.search(8), None);
-        assert_eq!(bst.search(12), Some(28499));
+        assert_eq!(bst.search(&19), Some(&-12849));
+        assert_eq!(bst.search(&13), Some(&2344));
+        assert_eq!(bst.search(&4), Some(&16));
+        assert_eq!(bst.search(&-1), None);
+        assert_eq!(bst.search(&0), None);
+        assert_eq!(bst.remove(&2), Some(4));
+        assert_eq!(bst.search(&2), None);
+        assert_eq!(bst.remove(&2), None);
+        assert_eq!(bst.remove(&11), Some(234));
+        assert_eq!(bst.remove(&11), None);
+        assert_eq!(bst.search(&11), None);
+        assert_eq!(bst.remove(&20), Some(12993));
+        assert_eq!(bst.remove(&20), None);
+        assert_eq!(bst.search(&20), None);
+        assert_eq!(bst.remove(&8), Some(256));
+        assert_eq!(bst.remove(&8), None);
+        assert_eq!(bst.search(&8), None);
+        assert_eq!(bst.search(&12), Some(&28499));
         bst.insert(17, 49);
         bst.insert(14, 149);
         bst.insert(16, 169);
-        assert_eq!(bst.remove(15), Some(12345));
-        assert_eq!(bst.remove(15), None);
-        assert_eq!(bst.search(15), None);
+        assert_eq!(bst.remove(&15), Some(12345));
+        assert_eq!(bst.remove(&15), None);
+        assert_eq!(bst.search(&15), None);
         bst.insert(15, 12345);
+        assert_eq!(bst.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn insert_after_emptying() {
+        let mut bst = SplayTree::new(10, 1024);
+        assert_eq!(bst.remove(&10), Some(1024));
+        bst.insert(4, 16);
+        assert_eq!(bst.search(&4), Some(&16));
+    }
+
+    #[test]
+    fn nearest_splays_to_root() {
+        let mut bst = SplayTree::new(10, 10);
+        for &k in [4, 8, 5, 9, 3, 2, 15, 13, 11, 18, 20, 19, 12].iter() {
+            bst.insert(k, k);
+        }
+        let root_key = |tree: &SplayTree<i32, i32>| tree.0.as_ref().map(|root| root.key);
+
+        assert_eq!(bst.floor(&7), Some((&5, &5)));
+        assert_eq!(root_key(&bst), Some(5));
+        assert_eq!(bst.ceiling(&14), Some((&15, &15)));
+        assert_eq!(root_key(&bst), Some(15));
+        assert_eq!(bst.predecessor(&11), Some((&10, &10)));
+        assert_eq!(root_key(&bst), Some(10));
+        assert_eq!(bst.successor(&13), Some((&15, &15)));
+        assert_eq!(root_key(&bst), Some(15));
+        assert_eq!(bst.first(), Some((&2, &2)));
+        assert_eq!(root_key(&bst), Some(2));
+        assert_eq!(bst.last(), Some((&20, &20)));
+        assert_eq!(root_key(&bst), Some(20));
+        assert_eq!(bst.successor(&20), None);
+        assert_eq!(bst.predecessor(&2), None);
+    }
+
+    #[test]
+    fn broken_invariants() {
+        let mut bst = SplayTree::new(1, 1);
+        bst.insert(0, 0);
+        bst.insert(2, 2);
+        assert_eq!(bst.check_invariants(), Ok(()));
+
+        // root is the last inserted
+        bst.0.as_mut().unwrap().key = -1;
+        assert_eq!(
+            bst.check_invariants(),
+            Err(InvariantError::KeyOrder { index: 2 })
+        );
     }
 }
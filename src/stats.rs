@@ -0,0 +1,188 @@
+//! Occupancy and probing statistics of a `HashMap` table
+//!
+//! The probe distance of an entry is the number of slots a lookup for its key
+//! reads before the one holding it, so 0 means the entry sits in its home
+//! slot. A cluster is a run of adjacent slots in use, which for open
+//! addressing are the slots that probing has to cross.
+
+use std::fmt;
+
+/// Snapshot of how the entries of a `HashMap` sit in its table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMapStats {
+    /// number of entries
+    pub len: usize,
+    /// number of slots, or of buckets for separate chaining
+    pub slots: usize,
+    /// number of slots holding a tombstone
+    pub tombstones: usize,
+    /// `probe_histogram[d]` is the number of entries at probe distance `d`
+    pub probe_histogram: Vec<usize>,
+    /// `cluster_histogram[s]` is the number of clusters of `s` slots, or of
+    /// chains of `s` entries for separate chaining
+    pub cluster_histogram: Vec<usize>,
+}
+
+impl HashMapStats {
+    /// statistics from the probe distance of every entry and the size of
+    /// every cluster
+    pub(crate) fn new(
+        len: usize,
+        slots: usize,
+        tombstones: usize,
+        distances: impl IntoIterator<Item = usize>,
+        clusters: impl IntoIterator<Item = usize>,
+    ) -> Self {
+        Self {
+            len,
+            slots,
+            tombstones,
+            probe_histogram: histogram(distances),
+            cluster_histogram: histogram(clusters),
+        }
+    }
+
+    /// entries per slot
+    pub fn load_factor(&self) -> f64 {
+        ratio(self.len, self.slots)
+    }
+
+    /// share of the slots in use, by entries or tombstones
+    pub fn occupancy(&self) -> f64 {
+        ratio(self.len + self.tombstones, self.slots)
+    }
+
+    /// mean probe distance over all entries
+    pub fn average_probe_distance(&self) -> f64 {
+        let total = self
+            .probe_histogram
+            .iter()
+            .enumerate()
+            .map(|(distance, count)| distance * count)
+            .sum();
+        ratio(total, self.len)
+    }
+
+    /// longest probe distance of any entry
+    pub fn max_probe_distance(&self) -> usize {
+        self.probe_histogram.len().saturating_sub(1)
+    }
+
+    /// number of clusters
+    pub fn clusters(&self) -> usize {
+        self.cluster_histogram.iter().sum()
+    }
+
+    /// mean size of a cluster
+    pub fn average_cluster_size(&self) -> f64 {
+        let total = self
+            .cluster_histogram
+            .iter()
+            .enumerate()
+            .map(|(size, count)| size * count)
+            .sum();
+        ratio(total, self.clusters())
+    }
+
+    /// size of the largest cluster
+    pub fn max_cluster_size(&self) -> usize {
+        self.cluster_histogram.len().saturating_sub(1)
+    }
+}
+
+impl fmt::Display for HashMapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} entries, {} tombstones in {} slots: load factor {:.3}, occupancy {:.3}",
+            self.len,
+            self.tombstones,
+            self.slots,
+            self.load_factor(),
+            self.occupancy()
+        )?;
+        writeln!(
+            f,
+            "probe distance: average {:.3}, max {}",
+            self.average_probe_distance(),
+            self.max_probe_distance()
+        )?;
+        for (distance, &count) in self.probe_histogram.iter().enumerate() {
+            if count > 0 {
+                writeln!(f, "  {:>4}: {}", distance, count)?;
+            }
+        }
+        write!(
+            f,
+            "clusters: {}, average size {:.3}, max {}",
+            self.clusters(),
+            self.average_cluster_size(),
+            self.max_cluster_size()
+        )
+    }
+}
+
+/// `histogram[v]` is the number of times `v` occurs in `values`
+fn histogram(values: impl IntoIterator<Item = usize>) -> Vec<usize> {
+    let mut histogram = Vec::new();
+    for value in values {
+        if histogram.len() <= value {
+            histogram.resize(value + 1, 0);
+        }
+        histogram[value] += 1;
+    }
+    histogram
+}
+
+fn ratio(num: usize, den: usize) -> f64 {
+    match den {
+        0 => 0.0,
+        _ => num as f64 / den as f64,
+    }
+}
+
+/// sizes of the runs of adjacent slots among `0..size` for which `in_use`
+/// holds, wrapping around the end as probing does
+pub(crate) fn clusters(size: usize, in_use: impl Fn(usize) -> bool) -> Vec<usize> {
+    let start = match (0..size).find(|&idx| !in_use(idx)) {
+        Some(start) => start,
+        None if size == 0 => return Vec::new(),
+        None => return vec![size],
+    };
+    let mut sizes = Vec::new();
+    let mut run = 0;
+    for step in 1..=size {
+        if in_use((start + step) % size) {
+            run += 1;
+        } else if run > 0 {
+            sizes.push(run);
+            run = 0;
+        }
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cluster_runs() {
+        let used = [true, true, false, true, false, false, true, true, true];
+        let mut sizes = clusters(used.len(), |idx| used[idx]);
+        sizes.sort_unstable();
+        // the run at the end wraps around to the front
+        assert_eq!(sizes, [1, 5]);
+        assert_eq!(clusters(4, |_| true), [4]);
+        assert!(clusters(4, |_| false).is_empty());
+        assert!(clusters(0, |_| true).is_empty());
+
+        let stats = HashMapStats::new(6, 9, 0, vec![0, 0, 1, 0, 3, 1], sizes);
+        assert_eq!(stats.probe_histogram, [3, 2, 0, 1]);
+        assert_eq!(stats.max_probe_distance(), 3);
+        assert!((stats.average_probe_distance() - 5.0 / 6.0).abs() < 1e-9);
+        assert_eq!(stats.clusters(), 2);
+        assert_eq!(stats.max_cluster_size(), 5);
+        assert!((stats.load_factor() - 6.0 / 9.0).abs() < 1e-9);
+    }
+}
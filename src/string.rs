@@ -82,7 +82,7 @@ fn build_bc(pattern: &str) -> [i32; 256] {
 /// Karp-Rabin string match hashing algorithm
 pub fn karp_rabin(text: &str, pattern: &str) -> Option<usize> {
     const BASE: usize = 65539;
-    if pattern.len() == 0 {
+    if pattern.is_empty() {
         return Some(0);
     }
 
@@ -96,8 +96,8 @@ pub fn karp_rabin(text: &str, pattern: &str) -> Option<usize> {
     let (text, pattern) = (text.as_bytes(), pattern.as_bytes());
 
     let mut hash_code = 0;
-    for i in 0..m {
-        hash_code = (hash_code * 31 + pattern[i] as usize) % BASE;
+    for &byte in pattern.iter() {
+        hash_code = (hash_code * 31 + byte as usize) % BASE;
     }
 
     let mut source_code = 0;
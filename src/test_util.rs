@@ -0,0 +1,31 @@
+//! Helpers shared by the unit tests
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use common::Rng;
+use core::hash::{Hash, Hasher};
+
+/// `n` numbers below `range`, the same on every run
+pub(crate) fn pseudo_random(n: usize, range: u32) -> Vec<u32> {
+    let mut rng = Rng::new();
+    (0..n)
+        .map(|_| ((rng.next() >> 32) % u64::from(range)) as u32)
+        .collect()
+}
+
+/// `n` numbers over all of `u64`, the same on every run
+pub(crate) fn pseudo_random_u64(n: usize) -> Vec<u64> {
+    let mut rng = Rng::new();
+    (0..n).map(|_| rng.next()).collect()
+}
+
+/// key hashing into only a few buckets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Colliding(pub(crate) u32);
+
+impl Hash for Colliding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 % 3).hash(state);
+    }
+}
@@ -0,0 +1,620 @@
+//! Model-based tests, random operation sequences are run against the std
+//! collections as oracles and shrunk to a minimal failing sequence on mismatch
+//!
+//! Set `MODEL_SEED` to replay a reported failure.
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use common::Rng;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use tsinghua_ds::{
+    AVLTree, BTree, BinaryHeap, BinarySearchTree, BinomialHeap, ByKey, Chained, Compare, Cuckoo,
+    FibonacciHeap, HashMap, InvariantError, LeftistHeap, MergeableHeap, PairingHeap, Quadratic,
+    RedBlackTree, RobinHood, SkewHeap, SplayTree, Table, BST,
+};
+
+/// number of sequences generated for each property
+const CASES: usize = 200;
+/// longest sequence generated
+const MAX_OPS: usize = 200;
+/// keys are drawn from `0..KEYS`, small enough to hit existing ones often
+const KEYS: i32 = 64;
+/// every tree starts with this key, trees can't be created empty
+const ROOT: i32 = KEYS / 2;
+
+/// key drawn from `0..KEYS`
+fn key(rng: &mut Rng) -> i32 {
+    rng.below(KEYS as u64) as i32
+}
+
+fn base_seed() -> u64 {
+    std::env::var("MODEL_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(0x5eed_2021)
+}
+
+/// Operations simple enough to be replaced by smaller ones while shrinking
+trait Shrink: Sized {
+    /// candidates simpler than `self`
+    fn simpler(&self) -> Vec<Self>;
+}
+
+fn smaller_keys(key: i32) -> Vec<i32> {
+    match key {
+        0 => vec![],
+        _ => vec![0, key / 2, key - 1],
+    }
+}
+
+/// run `check` on `CASES` random sequences, panic with the shrunk sequence
+/// of the first one failing
+fn forall<O, G, C>(name: &str, gen: G, check: C)
+where
+    O: Clone + Debug + Shrink,
+    G: Fn(&mut Rng) -> O,
+    C: Fn(&[O]) -> Result<(), String>,
+{
+    let seed = base_seed();
+    let mut rng = Rng::with_seed(seed);
+    for case in 0..CASES {
+        let len = rng.below(MAX_OPS as u64) as usize + 1;
+        let ops: Vec<O> = (0..len).map(|_| gen(&mut rng)).collect();
+        if check(&ops).is_err() {
+            let ops = shrink(ops, &check);
+            let err = check(&ops).unwrap_err();
+            panic!(
+                "{}: case {} of seed {} fails, minimal sequence of {} ops:\n{:?}\n{}",
+                name,
+                case,
+                seed,
+                ops.len(),
+                ops,
+                err
+            );
+        }
+    }
+}
+
+/// drop chunks of the sequence, then simplify single operations, as long as
+/// it keeps failing
+fn shrink<O, C>(mut ops: Vec<O>, check: &C) -> Vec<O>
+where
+    O: Clone + Shrink,
+    C: Fn(&[O]) -> Result<(), String>,
+{
+    let fails = |ops: &[O]| check(ops).is_err();
+    loop {
+        let mut progress = false;
+
+        let mut chunk = ops.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start + chunk <= ops.len() {
+                let mut candidate = ops[..start].to_vec();
+                candidate.extend_from_slice(&ops[start + chunk..]);
+                if fails(&candidate) {
+                    ops = candidate;
+                    progress = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+
+        for idx in 0..ops.len() {
+            for op in ops[idx].simpler() {
+                let mut candidate = ops.clone();
+                candidate[idx] = op;
+                if fails(&candidate) {
+                    ops = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+
+        if !progress {
+            return ops;
+        }
+    }
+}
+
+/// fail with the step and what differs
+fn expect<T: PartialEq + Debug>(step: usize, what: &str, got: T, want: T) -> Result<(), String> {
+    if got == want {
+        return Ok(());
+    }
+    Err(format!(
+        "step {}: {} gave {:?}, expected {:?}",
+        step, what, got, want
+    ))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TreeOp {
+    Insert(i32, i32),
+    Remove(i32),
+    Search(i32),
+    First,
+    Last,
+    Floor(i32),
+    Ceiling(i32),
+    Predecessor(i32),
+    Successor(i32),
+    PopFirst,
+    PopLast,
+}
+
+impl Shrink for TreeOp {
+    fn simpler(&self) -> Vec<Self> {
+        use TreeOp::*;
+        let mut ops = Vec::new();
+        match *self {
+            Insert(key, val) => {
+                ops.extend(smaller_keys(key).into_iter().map(|k| Insert(k, val)));
+                if val != 0 {
+                    ops.push(Insert(key, 0));
+                }
+            }
+            Remove(key) => ops.extend(smaller_keys(key).into_iter().map(Remove)),
+            Floor(key) | Ceiling(key) | Predecessor(key) | Successor(key) | Search(key) => {
+                ops.push(Search(key));
+                ops.extend(smaller_keys(key).into_iter().map(Search));
+            }
+            First | Last | PopFirst | PopLast => {}
+        }
+        ops
+    }
+}
+
+fn tree_op(rng: &mut Rng) -> TreeOp {
+    use TreeOp::*;
+    match rng.below(16) {
+        0..=5 => Insert(key(rng), rng.below(1000) as i32),
+        6..=8 => Remove(key(rng)),
+        9 | 10 => Search(key(rng)),
+        11 => Floor(key(rng)),
+        12 => Ceiling(key(rng)),
+        13 => Predecessor(key(rng)),
+        14 => Successor(key(rng)),
+        _ => match rng.below(4) {
+            0 => First,
+            1 => Last,
+            2 => PopFirst,
+            _ => PopLast,
+        },
+    }
+}
+
+/// replay `ops` on a tree from `fresh` and on a `BTreeMap`
+fn run_tree<T, F, I>(ops: &[TreeOp], fresh: &F, invariants: &I) -> Result<(), String>
+where
+    T: BST<i32, i32>,
+    for<'a> &'a T: IntoIterator<Item = (&'a i32, &'a i32)>,
+    F: Fn() -> T,
+    I: Fn(&T) -> Result<(), InvariantError>,
+{
+    use TreeOp::*;
+    let own = |entry: Option<(&i32, &i32)>| entry.map(|(k, v)| (*k, *v));
+    let mut tree = fresh();
+    let mut model = BTreeMap::new();
+    model.insert(ROOT, ROOT);
+
+    for (step, &op) in ops.iter().enumerate() {
+        let what = format!("{:?}", op);
+        match op {
+            Insert(key, val) => {
+                tree.insert(key, val);
+                model.insert(key, val);
+            }
+            Remove(key) => expect(step, &what, tree.remove(&key), model.remove(&key))?,
+            Search(key) => expect(
+                step,
+                &what,
+                tree.search(&key).copied(),
+                model.get(&key).copied(),
+            )?,
+            First => expect(step, &what, own(tree.first()), own(model.iter().next()))?,
+            Last => expect(step, &what, own(tree.last()), own(model.iter().next_back()))?,
+            Floor(key) => expect(
+                step,
+                &what,
+                own(tree.floor(&key)),
+                own(model.range(..=key).next_back()),
+            )?,
+            Ceiling(key) => expect(
+                step,
+                &what,
+                own(tree.ceiling(&key)),
+                own(model.range(key..).next()),
+            )?,
+            Predecessor(key) => expect(
+                step,
+                &what,
+                own(tree.predecessor(&key)),
+                own(model.range(..key).next_back()),
+            )?,
+            Successor(key) => expect(
+                step,
+                &what,
+                own(tree.successor(&key)),
+                own(model.range(key + 1..).next()),
+            )?,
+            PopFirst => expect(step, &what, tree.pop_first(), model.pop_first())?,
+            PopLast => expect(step, &what, tree.pop_last(), model.pop_last())?,
+        }
+        invariants(&tree).map_err(|err| format!("step {}: {:?} broke {}", step, op, err))?;
+    }
+
+    let entries: Vec<_> = (&tree).into_iter().map(|(k, v)| (*k, *v)).collect();
+    let expected: Vec<_> = model.into_iter().collect();
+    expect(ops.len(), "iteration", entries, expected)
+}
+
+fn tree_model<T, F, I>(name: &str, fresh: F, invariants: I)
+where
+    T: BST<i32, i32>,
+    for<'a> &'a T: IntoIterator<Item = (&'a i32, &'a i32)>,
+    F: Fn() -> T,
+    I: Fn(&T) -> Result<(), InvariantError>,
+{
+    forall(name, tree_op, |ops| run_tree(ops, &fresh, &invariants));
+}
+
+#[test]
+fn binary_search_tree_model() {
+    tree_model(
+        "BinarySearchTree",
+        || BinarySearchTree::new(ROOT, ROOT),
+        BinarySearchTree::check_invariants,
+    );
+}
+
+#[test]
+fn avl_tree_model() {
+    tree_model(
+        "AVLTree",
+        || AVLTree::new(ROOT, ROOT),
+        AVLTree::check_invariants,
+    );
+}
+
+#[test]
+fn splay_tree_model() {
+    tree_model(
+        "SplayTree",
+        || SplayTree::new(ROOT, ROOT),
+        SplayTree::check_invariants,
+    );
+}
+
+#[test]
+fn red_black_tree_model() {
+    tree_model(
+        "RedBlackTree",
+        || RedBlackTree::new(ROOT, ROOT),
+        RedBlackTree::check_invariants,
+    );
+}
+
+#[test]
+fn btree_model() {
+    for order in 3..8 {
+        let fresh = || {
+            let mut tree = BTree::new(0, order);
+            tree.insert(ROOT, ROOT);
+            tree
+        };
+        tree_model(
+            &format!("BTree of order {}", order),
+            fresh,
+            BTree::check_invariants,
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MapOp {
+    Insert(i32, i32),
+    Remove(i32),
+    Get(i32),
+    /// add to the value through the entry API, inserting if absent
+    Add(i32, i32),
+    /// keep the entries whose value is not a multiple of this
+    Retain(i32),
+}
+
+impl Shrink for MapOp {
+    fn simpler(&self) -> Vec<Self> {
+        use MapOp::*;
+        match *self {
+            Insert(key, val) => {
+                let mut ops: Vec<_> = smaller_keys(key)
+                    .into_iter()
+                    .map(|k| Insert(k, val))
+                    .collect();
+                if val != 0 {
+                    ops.push(Insert(key, 0));
+                }
+                ops
+            }
+            Remove(key) => smaller_keys(key).into_iter().map(Remove).collect(),
+            Get(key) => smaller_keys(key).into_iter().map(Get).collect(),
+            Add(key, val) => smaller_keys(key)
+                .into_iter()
+                .map(|k| Add(k, val))
+                .chain(Some(Insert(key, val)))
+                .collect(),
+            Retain(modulus) => (2..modulus).map(Retain).collect(),
+        }
+    }
+}
+
+fn map_op(rng: &mut Rng) -> MapOp {
+    match rng.below(41) {
+        0..=15 => MapOp::Insert(key(rng), rng.below(1000) as i32),
+        16..=23 => MapOp::Remove(key(rng)),
+        24..=31 => MapOp::Add(key(rng), rng.below(1000) as i32),
+        32..=39 => MapOp::Get(key(rng)),
+        _ => MapOp::Retain(2 + rng.below(4) as i32),
+    }
+}
+
+/// key hashing into only a few buckets, for long probe chains
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Colliding(i32);
+
+impl Hash for Colliding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 % 4).hash(state);
+    }
+}
+
+/// replay `ops` on a `HashMap` over `table` and on std's, with keys made by
+/// `key`
+fn run_map<K, F, T>(ops: &[MapOp], key: F, table: T) -> Result<(), String>
+where
+    K: Eq + Hash + Clone + Debug,
+    F: Fn(i32) -> K,
+    T: Table<K, i32>,
+{
+    let mut map = HashMap::with_table(table);
+    let mut model = std::collections::HashMap::new();
+
+    for (step, &op) in ops.iter().enumerate() {
+        let what = format!("{:?}", op);
+        match op {
+            MapOp::Insert(k, val) => {
+                expect(step, &what, map.insert(key(k), val), model.insert(k, val))?
+            }
+            MapOp::Remove(k) => expect(step, &what, map.remove(&key(k)), model.remove(&k))?,
+            MapOp::Get(k) => expect(step, &what, map.get(&key(k)), model.get(&k))?,
+            MapOp::Add(k, val) => {
+                let got = *map.entry(key(k)).and_modify(|v| *v += val).or_insert(val);
+                let want = *model.entry(k).and_modify(|v| *v += val).or_insert(val);
+                expect(step, &what, got, want)?
+            }
+            MapOp::Retain(modulus) => {
+                map.retain(|_, v| *v % modulus != 0);
+                model.retain(|_, v| *v % modulus != 0);
+            }
+        }
+        expect(step, "len", map.len(), model.len())?;
+    }
+
+    for k in 0..KEYS {
+        expect(ops.len(), "final get", map.get(&key(k)), model.get(&k))?;
+    }
+    let mut entries: Vec<_> = map.into_iter().map(|(_, v)| v).collect();
+    let mut want: Vec<_> = model.into_values().collect();
+    entries.sort_unstable();
+    want.sort_unstable();
+    expect(ops.len(), "values", entries, want)?;
+    Ok(())
+}
+
+#[test]
+fn hash_map_model() {
+    forall("HashMap", map_op, |ops| {
+        run_map(ops, |k| k, Quadratic::default())
+    });
+}
+
+#[test]
+fn colliding_hash_map_model() {
+    forall("HashMap with colliding keys", map_op, |ops| {
+        run_map(ops, Colliding, Quadratic::default())
+    });
+}
+
+#[test]
+fn chained_hash_map_model() {
+    forall("ChainedHashMap", map_op, |ops| {
+        run_map(ops, |k| k, Chained::default())
+    });
+    forall("ChainedHashMap with colliding keys", map_op, |ops| {
+        run_map(ops, Colliding, Chained::default())
+    });
+}
+
+#[test]
+fn robin_hood_hash_map_model() {
+    forall("RobinHoodHashMap", map_op, |ops| {
+        run_map(ops, |k| k, RobinHood::default())
+    });
+    forall("RobinHoodHashMap with colliding keys", map_op, |ops| {
+        run_map(ops, Colliding, RobinHood::default())
+    });
+}
+
+#[test]
+fn cuckoo_hash_map_model() {
+    forall("CuckooHashMap", map_op, |ops| {
+        run_map(ops, |k| k, Cuckoo::default())
+    });
+    forall("CuckooHashMap with colliding keys", map_op, |ops| {
+        run_map(ops, Colliding, Cuckoo::default())
+    });
+}
+
+#[derive(Debug, Clone, Copy)]
+enum HeapOp {
+    Push(i32),
+    Pop,
+    Peek,
+}
+
+impl Shrink for HeapOp {
+    fn simpler(&self) -> Vec<Self> {
+        match *self {
+            HeapOp::Push(elem) => smaller_keys(elem).into_iter().map(HeapOp::Push).collect(),
+            HeapOp::Pop => vec![HeapOp::Peek],
+            HeapOp::Peek => vec![],
+        }
+    }
+}
+
+fn heap_op(rng: &mut Rng) -> HeapOp {
+    match rng.below(8) {
+        0..=3 => HeapOp::Push(key(rng)),
+        4..=6 => HeapOp::Pop,
+        _ => HeapOp::Peek,
+    }
+}
+
+/// check `heap` against a std max-heap of `(order(elem), elem)`
+fn run_binary_heap<C: Compare<i32>>(
+    ops: &[HeapOp],
+    mut heap: BinaryHeap<i32, C>,
+    order: fn(i32) -> i64,
+) -> Result<(), String> {
+    let mut model = std::collections::BinaryHeap::new();
+
+    for (step, &op) in ops.iter().enumerate() {
+        let what = format!("{:?}", op);
+        match op {
+            HeapOp::Push(elem) => {
+                heap.push(elem);
+                model.push((order(elem), elem));
+            }
+            HeapOp::Pop => expect(step, &what, heap.pop(), model.pop().map(|(_, e)| e))?,
+            HeapOp::Peek => expect(step, &what, heap.peek(), model.peek().map(|(_, e)| e))?,
+        }
+        expect(step, "len", heap.len(), model.len())?;
+    }
+    Ok(())
+}
+
+fn run_leftist_heap<C: Compare<i32>>(
+    ops: &[HeapOp],
+    mut heap: LeftistHeap<i32, C>,
+    order: fn(i32) -> i64,
+) -> Result<(), String> {
+    let mut model = std::collections::BinaryHeap::new();
+
+    for (step, &op) in ops.iter().enumerate() {
+        let what = format!("{:?}", op);
+        match op {
+            HeapOp::Push(elem) => {
+                heap.insert(elem);
+                model.push((order(elem), elem));
+            }
+            HeapOp::Pop => expect(step, &what, heap.remove(), model.pop().map(|(_, e)| e))?,
+            HeapOp::Peek => expect(step, &what, heap.get(), model.peek().map(|(_, e)| e))?,
+        }
+        heap.check_invariants()
+            .map_err(|err| format!("step {}: {:?} broke {}", step, op, err))?;
+    }
+    Ok(())
+}
+
+/// check a mergeable heap against a std max-heap of `(order(elem), elem)`,
+/// pushing every other item through a heap melded in
+fn run_mergeable_heap<H: MergeableHeap<i32>>(
+    ops: &[HeapOp],
+    new: fn() -> H,
+    order: fn(i32) -> i64,
+) -> Result<(), String> {
+    let mut heap = new();
+    let mut model = std::collections::BinaryHeap::new();
+
+    for (step, &op) in ops.iter().enumerate() {
+        let what = format!("{:?}", op);
+        match op {
+            HeapOp::Push(elem) if step % 2 == 0 => heap.push(elem),
+            HeapOp::Push(elem) => {
+                let mut other = new();
+                other.push(elem);
+                heap.meld(other);
+            }
+            HeapOp::Pop => expect(step, &what, heap.pop(), model.pop().map(|(_, e)| e))?,
+            HeapOp::Peek => expect(
+                step,
+                &what,
+                heap.peek().as_deref(),
+                model.peek().map(|(_, e)| e),
+            )?,
+        }
+        if let HeapOp::Push(elem) = op {
+            model.push((order(elem), elem));
+        }
+        expect(step, "is_empty", heap.is_empty(), model.is_empty())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn mergeable_heap_model() {
+    let max = i64::from;
+    let min = |e| -i64::from(e);
+    forall("SkewHeap", heap_op, |ops| {
+        run_mergeable_heap(ops, SkewHeap::new, max)
+    });
+    forall("BinomialHeap", heap_op, |ops| {
+        run_mergeable_heap(ops, BinomialHeap::new_min, min)
+    });
+    forall("PairingHeap", heap_op, |ops| {
+        run_mergeable_heap(ops, PairingHeap::new, max)
+    });
+    forall("FibonacciHeap", heap_op, |ops| {
+        run_mergeable_heap(ops, FibonacciHeap::new_min, min)
+    });
+    forall("LeftistHeap", heap_op, |ops| {
+        run_mergeable_heap(ops, LeftistHeap::new, max)
+    });
+}
+
+#[test]
+fn binary_heap_model() {
+    forall("BinaryHeap", heap_op, |ops| {
+        run_binary_heap(ops, BinaryHeap::new(), i64::from)
+    });
+    forall("min BinaryHeap", heap_op, |ops| {
+        run_binary_heap(ops, BinaryHeap::new_min(), |e| -i64::from(e))
+    });
+    forall("BinaryHeap by last digit", heap_op, |ops| {
+        let heap = BinaryHeap::with_comparator(|a: &i32, b: &i32| {
+            (a.rem_euclid(10), a).cmp(&(b.rem_euclid(10), b))
+        });
+        run_binary_heap(ops, heap, |e| i64::from(e.rem_euclid(10)))
+    });
+}
+
+#[test]
+fn leftist_heap_model() {
+    forall("LeftistHeap", heap_op, |ops| {
+        run_leftist_heap(ops, LeftistHeap::new(), i64::from)
+    });
+    forall("min LeftistHeap", heap_op, |ops| {
+        run_leftist_heap(ops, LeftistHeap::new_min(), |e| -i64::from(e))
+    });
+    forall("LeftistHeap by key", heap_op, |ops| {
+        let heap = LeftistHeap::with_comparator(ByKey(|&e: &i32| std::cmp::Reverse(e)));
+        run_leftist_heap(ops, heap, |e| -i64::from(e))
+    });
+}